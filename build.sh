@@ -1,7 +1,7 @@
 #!/bin/bash
 NAME="counterpuncher"
 
-cargo build --release --target wasm32-unknown-unknown
+cargo build --release --target wasm32-unknown-unknown -p counterpuncher-gui
 wasm-bindgen "target/wasm32-unknown-unknown/release/$NAME.wasm" --out-dir wasm --no-modules --no-typescript
 
 mv "wasm/$NAME.js" "wasm/myblob.js"
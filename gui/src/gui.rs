@@ -4,8 +4,7 @@ use rltk::{Algorithm2D, Rltk, RGB};
 // #region UI constants
 pub const MAP_X: i32 = SIDE_W + 1;
 pub const MAP_Y: i32 = 1;
-pub const MAP_W: i32 = 79;
-pub const MAP_H: i32 = 50;
+pub use crate::map::{MAP_H, MAP_W};
 
 const CARD_Y: i32 = SIDE_H;
 pub const CARD_W: i32 = 10;
@@ -35,29 +34,35 @@ pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
 
     let map = ecs.fetch::<Map>();
     let floor_str = format!("FLOOR {}", map.depth);
-    ctx.print(MAP_W + MAP_X - floor_str.len() as i32, MAP_Y - 1, floor_str);
+    let floor_x = MAP_W + MAP_X - floor_str.len() as i32;
+    ctx.print(floor_x, MAP_Y - 1, floor_str);
+
+    // how much attention the player has drawn, next to the floor number
+    let stealth = sys_noise::player_stealth(ecs);
+    let stealth_str = stealth.name();
+    ctx.print_color(
+        floor_x - stealth_str.len() as i32 - 1,
+        MAP_Y - 1,
+        stealth_color(stealth),
+        bg_color(),
+        stealth_str,
+    );
 
     let mut x = 0;
     let mut y = 0;
 
     for (idx, tile) in map.tiles.iter().enumerate() {
         if map.known_tiles[idx] || SHOW_MAP {
-            let mut symbol;
-            let mut fg = map.color_map[idx];
-
-            match tile {
-                TileType::Floor => {
-                    symbol = rltk::to_cp437('.');
-                }
-                TileType::Wall => {
-                    symbol = rltk::to_cp437('#');
-                }
-            }
-
-            if idx == map.level_exit {
-                symbol = rltk::to_cp437('>');
-                fg = map_exit_color();
-            }
+            let (symbol, mut fg) = match tile {
+                TileType::Floor => (rltk::to_cp437('.'), map.color_map[idx]),
+                TileType::Wall => (rltk::to_cp437('#'), map.color_map[idx]),
+                TileType::DownStairs => (rltk::to_cp437('>'), map_exit_color()),
+                TileType::Door => (rltk::to_cp437('+'), map_door_color()),
+                TileType::OpenDoor => (rltk::to_cp437('\''), map_door_color()),
+                TileType::ShallowWater => (rltk::to_cp437('~'), map_water_color()),
+                TileType::Chasm => (rltk::to_cp437('░'), map_chasm_color()),
+                TileType::Rubble => (rltk::to_cp437(':'), map_rubble_color()),
+            };
 
             if !map.visible_tiles[idx] {
                 fg = fg.to_greyscale()
@@ -76,26 +81,10 @@ pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
 pub fn draw_renderables(ecs: &World, ctx: &mut Rltk) {
     let positions = ecs.read_storage::<Position>();
     let renderables = ecs.read_storage::<Renderable>();
-    let particles = ecs.read_storage::<ParticleLifetime>();
     let map = ecs.fetch::<Map>();
 
-    for (pos, render, particle) in (&positions, &renderables, (&particles).maybe()).join() {
-        if let Some(lifetime) = particle {
-            let mut fg = render.fg;
-            let mut bg = render.bg;
-
-            if lifetime.should_fade {
-                let fade_percent = ezing::expo_inout(1.0 - lifetime.remaining / lifetime.base);
-                let base_color = bg_color();
-
-                fg = fg.lerp(base_color, fade_percent);
-                bg = bg.lerp(base_color, fade_percent);
-            }
-
-            ctx.set_active_console(0);
-            ctx.set(MAP_X + pos.x, MAP_Y + pos.y, fg, bg, render.symbol);
-            ctx.set_active_console(1);
-        } else if map.visible_tiles[map.get_index(pos.x, pos.y)] || SHOW_REND {
+    for (pos, render) in (&positions, &renderables).join() {
+        if map.visible_tiles[map.get_index(pos.x, pos.y)] || SHOW_REND {
             ctx.set(
                 MAP_X + pos.x,
                 MAP_Y + pos.y,
@@ -105,14 +94,38 @@ pub fn draw_renderables(ecs: &World, ctx: &mut Rltk) {
             );
         }
     }
+
+    let builder = ecs.fetch::<ParticleBuilder>();
+    ctx.set_active_console(0);
+
+    for particle in builder.particles.iter() {
+        let mut fg = particle.color;
+        let mut bg = bg_color();
+
+        if particle.should_fade {
+            let fade_percent = ezing::expo_inout(1.0 - particle.remaining / particle.base);
+            let base_color = bg_color();
+
+            fg = fg.lerp(base_color, fade_percent);
+            bg = bg.lerp(base_color, fade_percent);
+        }
+
+        ctx.set(
+            MAP_X + particle.position.x,
+            MAP_Y + particle.position.y,
+            fg,
+            bg,
+            particle.symbol,
+        );
+    }
+
+    ctx.set_active_console(1);
 }
 
 pub fn draw_active_attacks(ecs: &World, ctx: &mut Rltk) {
-    let card_stack_active = crate::events::CARDSTACK
-        .lock()
-        .expect("Failed to lock CARDSTACK");
+    let queue = ecs.fetch::<EventQueue>();
 
-    for card in card_stack_active.iter() {
+    for card in queue.cards.iter() {
         if let Some(attack_ent) = card.source {
             // highlight source on map
             let positions = ecs.read_storage::<Position>();
@@ -152,6 +165,26 @@ pub fn draw_intents(ecs: &World, ctx: &mut Rltk) {
     if let Some(outgoing) = intents.prev_outgoing_intent {
         draw_card_combined(ctx, outgoing, 3, 33, &*intents, false);
     }
+
+    // bigger brawls don't fit on two cards, so everyone's speed goes between them
+    if intents.chain.len() > 2 && !intents.hidden {
+        let mut x = SIDE_X + 1;
+        for link in intents.chain.iter() {
+            let speed_str = format!("{}", link.speed);
+            if x + 1 + speed_str.len() as i32 >= SIDE_X + SIDE_W {
+                break;
+            }
+
+            let color = if link.interrupted {
+                card_interrupted_color()
+            } else {
+                RGB::named(rltk::WHITE)
+            };
+            ctx.set(x, 30, color, bg_color(), link.glyph);
+            ctx.print(x + 1, 30, speed_str.as_str());
+            x += speed_str.len() as i32 + 2;
+        }
+    }
 }
 
 fn format_roll_bonuses<'a>(
@@ -250,9 +283,8 @@ pub fn draw_card_hand(
             crate::AttackTrait::Damage => ctx.print(x_start + 1, y_type_line, "Attack"),
             crate::AttackTrait::Modifier => ctx.print(x_start + 1, y_type_line, "Modifier"),
             crate::AttackTrait::Movement => ctx.print(x_start + 1, y_type_line, "Movement"),
-            crate::AttackTrait::Equipment => ctx.print(x_start + 1, y_type_line, "Equipment"),
             crate::AttackTrait::Knockback { amount } => {
-                ctx.print(x_start + 1, y_trait_line, format!("PUSH {}", amount - 1));
+                ctx.print(x_start + 1, y_trait_line, format!("PUSH {}", amount));
                 y_trait_line += 2;
             }
             crate::AttackTrait::Draw { amount } => {
@@ -263,10 +295,34 @@ pub fn draw_card_hand(
                 ctx.print(x_start + 1, y_trait_line, format!("HEAL {}", amount));
                 y_trait_line += 2;
             }
+            crate::AttackTrait::Status { kind, .. } => {
+                ctx.print(x_start + 1, y_trait_line, status_label(kind));
+                y_trait_line += 2;
+            }
+            crate::AttackTrait::Block { amount } => {
+                ctx.print(x_start + 1, y_trait_line, format!("BLOCK {}", amount));
+                y_trait_line += 2;
+            }
+            crate::AttackTrait::Brace { amount } => {
+                ctx.print(x_start + 1, y_trait_line, format!("BRACE {}", amount));
+                y_trait_line += 2;
+            }
+            crate::AttackTrait::Parry => ctx.print(x_start + 1, y_type_line, "Parry"),
         }
     }
 }
 
+// short enough to fit on a card
+fn status_label(kind: StatusKind) -> &'static str {
+    match kind {
+        StatusKind::Stunned => "STUN",
+        StatusKind::OffBalance => "OFF BAL",
+        StatusKind::Bleeding => "BLEED",
+        StatusKind::Hastened => "HASTE",
+        StatusKind::Guarded => "GUARD",
+    }
+}
+
 fn draw_card_combined(
     ctx: &mut Rltk,
     attack: AttackIntent,
@@ -294,7 +350,8 @@ fn draw_card_combined(
         border_color = RGB::named(rltk::WHITE);
     }
 
-    if intents.prev_outgoing_intent.is_none() {
+    // the card hit, but a block soaked up some of it
+    if (incoming && intents.incoming_blocked) || (!incoming && intents.outgoing_blocked) {
         border_color = card_blocked_color();
     }
 
@@ -316,8 +373,12 @@ fn draw_card_combined(
     );
 
     let y_stats = y_start + 5;
-    let (power_str, speed_str, guard_str) =
-        format_roll_bonuses(intents.rolls, intents.incoming_went_first, incoming);
+    // the rolls belong to whoever attacked, which is the outgoing card when it was countered
+    let (power_str, speed_str, guard_str) = format_roll_bonuses(
+        intents.rolls,
+        intents.incoming_went_first != intents.countered,
+        incoming != intents.countered,
+    );
     ctx.print(x_start + 3 - (power_str.len() as i32), y_stats, power_str);
     ctx.print(x_start + 6 - (speed_str.len() as i32), y_stats, speed_str);
     ctx.print(x_start + 9 - (guard_str.len() as i32), y_stats, guard_str);
@@ -482,6 +543,8 @@ pub fn draw_sidebar(ecs: &World, ctx: &mut Rltk) {
     let viewsheds = ecs.read_storage::<Viewshed>();
     let positions = ecs.read_storage::<Position>();
     let in_progress = ecs.read_storage::<AttackInProgress>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let ai_states = ecs.read_storage::<AiState>();
 
     let player = ecs.fetch::<Entity>();
     let player_view = viewsheds
@@ -501,12 +564,14 @@ pub fn draw_sidebar(ecs: &World, ctx: &mut Rltk) {
     let mut y = SIDE_Y + 1;
     let mut index = 0;
 
-    for (rend, mut view, pos, health, attack) in (
+    for (rend, mut view, pos, health, attack, status, ai_state) in (
         &rends,
         &mut viewables,
         &positions,
         &healths,
         (&in_progress).maybe(),
+        (&statuses).maybe(),
+        (&ai_states).maybe(),
     )
         .join()
     {
@@ -559,6 +624,32 @@ pub fn draw_sidebar(ecs: &World, ctx: &mut Rltk) {
                     rltk::to_cp437('o'),
                 );
             }
+
+            // what it's up to goes under its glyph
+            if let Some(state) = ai_state {
+                if let Some(icon) = state.status.icon() {
+                    ctx.set(
+                        x,
+                        y + 1,
+                        behavior_color(&state.status),
+                        bg_color(),
+                        rltk::to_cp437(icon),
+                    );
+                }
+            }
+
+            // status icons go in the gap under the health bar
+            if let Some(status) = status {
+                for (i, effect) in status.effects.iter().enumerate() {
+                    ctx.set(
+                        x + i as i32 + 2,
+                        y + 1,
+                        status_color(effect.kind),
+                        bg_color(),
+                        rltk::to_cp437(effect.kind.icon()),
+                    );
+                }
+            }
         }
 
         y += 2;
@@ -567,6 +658,8 @@ pub fn draw_sidebar(ecs: &World, ctx: &mut Rltk) {
         // TODO: what to do with excess?
     }
 
+    draw_equipment(ecs, ctx, x, SIDE_Y + SIDE_H - 1);
+
     // ctx.draw_box(
     //     0,
     //     50,
@@ -585,6 +678,48 @@ pub fn draw_sidebar(ecs: &World, ctx: &mut Rltk) {
     // draw_tooltips(ecs, ctx);
 }
 
+// what the player is wearing, in the two rows under the outgoing card
+fn draw_equipment(ecs: &World, ctx: &mut Rltk, x: i32, y: i32) {
+    let equipped = ecs.read_storage::<Equipped>();
+    let player = ecs.fetch::<Entity>();
+    let worn = match equipped.get(*player) {
+        None => return,
+        Some(worn) => worn,
+    };
+
+    for (i, item) in [worn.head, worn.hands, worn.feet].iter().enumerate() {
+        let slot_x = x + 5 * i as i32;
+
+        match item {
+            None => ctx.set(slot_x, y, hp_alt_color(), bg_color(), rltk::to_cp437('-')),
+            Some(item) => {
+                let def = crate::equipment::get_equipment(item);
+                ctx.set(
+                    slot_x,
+                    y,
+                    RGB::from_u8(def.fg.0, def.fg.1, def.fg.2),
+                    bg_color(),
+                    rltk::to_cp437(def.glyph),
+                );
+                ctx.print(slot_x + 1, y, def.name.chars().take(4).collect::<String>());
+            }
+        }
+    }
+
+    // the total added to every card, lined up with the stats on the cards above
+    if !worn.items().is_empty() {
+        let bonus = worn.bonus();
+        draw_card_stats(
+            ctx,
+            x + 2,
+            y + 1,
+            &format!("{:+}", bonus.power),
+            &format!("{:+}", bonus.speed),
+            &format!("{:+}", bonus.guard),
+        );
+    }
+}
+
 pub fn update_controls_text(ecs: &World, ctx: &mut Rltk, status: &RunState) {
     ctx.set_active_console(3);
 
@@ -645,6 +780,13 @@ pub fn update_controls_text(ecs: &World, ctx: &mut Rltk, status: &RunState) {
             let card_section_x = 45;
             ctx.print_color(card_section_x, y, icon_color, bg_color, "[1-7]");
             ctx.print(card_section_x + 6, y, "use card");
+
+            // save
+            let save_section_x = 60;
+            ctx.print_color(save_section_x, y, icon_color, bg_color, "s");
+            ctx.print(save_section_x + 1, y, "ave");
+            ctx.print_color(save_section_x + 5, y, icon_color, bg_color, "q");
+            ctx.print(save_section_x + 6, y, "uit");
         }
         RunState::Targetting {
             attack_type: _,
@@ -701,6 +843,19 @@ pub fn update_controls_text(ecs: &World, ctx: &mut Rltk, status: &RunState) {
             // restart
             ctx.print_color(x, y, icon_color, bg_color, "r");
             ctx.print(x + 1, y, "estart");
+
+            // show enough info to reproduce this run
+            let seed = ecs.fetch::<RunSeed>();
+            let map = ecs.fetch::<Map>();
+            let run_section_x = 13;
+            ctx.print_color(
+                run_section_x,
+                y,
+                inactive_color,
+                bg_color,
+                format!("seed {}, depth {}", seed.0, map.depth),
+            );
+
             ctx.print_color(CONSOLE_WIDTH - 6, y, text_dead_color(), bg_color, " DEAD");
         }
         RunState::HitPause { .. } => {
@@ -725,6 +880,64 @@ pub fn update_controls_text(ecs: &World, ctx: &mut Rltk, status: &RunState) {
     ctx.set_active_console(1);
 }
 
+// shown on the death screen, since the rules can only change for a new game
+pub fn draw_ruleset_choice(ctx: &mut Rltk, rules: &Ruleset) {
+    ctx.set_active_console(3);
+
+    let y = CONSOLE_HEIGHT - 1;
+    let rules_section_x = 50;
+    ctx.print_color(
+        rules_section_x,
+        y,
+        text_highlight_color(),
+        bg_color(),
+        "[TAB]",
+    );
+    ctx.print(rules_section_x + 6, y, format!("rules: {}", rules.name));
+
+    ctx.set_active_console(1);
+}
+
+pub fn draw_replay_controls(ctx: &mut Rltk, playback: &crate::replay::Playback, turn: u32) {
+    ctx.set_active_console(3);
+    ctx.cls();
+
+    let y = CONSOLE_HEIGHT - 1;
+    let icon_color = text_highlight_color();
+    let bg_color = bg_color();
+    let inactive_color = text_inactive_color();
+
+    // step
+    ctx.print_color(0, y, icon_color, bg_color, "[.]");
+    ctx.print(4, y, "step");
+
+    // jump between turns
+    let turn_section_x = 13;
+    ctx.set(turn_section_x, y, icon_color, bg_color, 27);
+    ctx.set(turn_section_x + 1, y, icon_color, bg_color, 26);
+    ctx.print(turn_section_x + 3, y, "turn");
+
+    let page_section_x = 25;
+    ctx.print_color(page_section_x, y, icon_color, bg_color, "[PGUP/PGDN]");
+    ctx.print(page_section_x + 12, y, "10 turns");
+
+    ctx.print_color(
+        60,
+        y,
+        inactive_color,
+        bg_color,
+        format!("turn {}/{}", turn, playback.replay.turns),
+    );
+
+    if playback.is_finished() {
+        ctx.print_color(CONSOLE_WIDTH - 6, y, inactive_color, bg_color, "  END");
+    } else {
+        ctx.print_color(CONSOLE_WIDTH - 6, y, inactive_color, bg_color, "REPLAY");
+    }
+
+    ctx.set_active_console(1);
+}
+
 fn draw_movement_controls(ctx: &mut Rltk, x: i32, y: i32, fg: RGB, bg: RGB, inactive: bool) {
     ctx.set(x + 1, y, fg, bg, 27);
     ctx.set(x + 2, y, fg, bg, 25);
@@ -755,6 +968,11 @@ pub fn draw_viewable_info(ecs: &World, ctx: &mut Rltk, entity: &Entity, index: u
     let healths = ecs.read_storage::<Health>();
     let atk_in_progress = ecs.read_storage::<AttackInProgress>();
     let blocking = ecs.read_storage::<BlockAttack>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let scheds = ecs.read_storage::<Schedulable>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let enemy_decks = ecs.read_storage::<EnemyDeck>();
+    let ai_states = ecs.read_storage::<AiState>();
 
     let pos = positions
         .get(*entity)
@@ -765,9 +983,31 @@ pub fn draw_viewable_info(ecs: &World, ctx: &mut Rltk, entity: &Entity, index: u
     let x = MAP_X + pos.x;
     let y = MAP_Y + pos.y;
 
+    let mut description = view.description.clone();
+    if let (Some(status), Some(sched)) = (statuses.get(*entity), scheds.get(*entity)) {
+        description.extend(status.describe(sched.base));
+    }
+    if let Some(worn) = equipped.get(*entity) {
+        for item in worn.items() {
+            let def = crate::equipment::get_equipment(&item);
+            description.push(format!("{}: {}", def.name, def.labels().join(" ")));
+        }
+    }
+    if let Some(enemy_deck) = enemy_decks.get(*entity) {
+        description.push(format!(
+            "Deck {} Discard {}",
+            enemy_deck.deck.cards_remaining(),
+            enemy_deck.deck.cards_discarded()
+        ));
+    }
+
     highlight_bg(ctx, &Position::as_point(pos), selected_color);
 
-    let (box_x, box_y) = position_box(ctx, x, y, 15, 10, selected_color, bg_color);
+    // grow the box to fit any status or equipment lines
+    let longest = description.iter().map(|line| line.len()).max().unwrap_or(0) as i32;
+    let box_w = std::cmp::max(15, longest + 2);
+    let box_h = std::cmp::max(10, description.len() as i32 + 6);
+    let (box_x, box_y) = position_box(ctx, x, y, box_w, box_h, selected_color, bg_color);
 
     ctx.print(box_x + 1, box_y, view.name.clone());
     ctx.print(
@@ -781,10 +1021,13 @@ pub fn draw_viewable_info(ecs: &World, ctx: &mut Rltk, entity: &Entity, index: u
     } else if blocking.get(*entity).is_some() {
         ctx.print(box_x + 1, box_y + 3, "Blocking");
     } else {
-        ctx.print(box_x + 1, box_y + 3, "Idle");
+        let behavior = ai_states
+            .get(*entity)
+            .and_then(|state| state.status.describe());
+        ctx.print(box_x + 1, box_y + 3, behavior.unwrap_or("Idle"));
     }
 
-    for (i, line) in view.description.iter().enumerate() {
+    for (i, line) in description.iter().enumerate() {
         ctx.print(box_x + 1, box_y + 5 + i as i32, line.clone());
     }
 }
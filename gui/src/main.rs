@@ -0,0 +1,292 @@
+rltk::embedded_resource!(FONT, "../../resources/Zilk-16x16.png");
+rltk::embedded_resource!(ICONS, "../../resources/custom_icons.png");
+
+use rltk::{GameState, Rltk};
+use specs::prelude::*;
+
+mod gui;
+mod player;
+
+use counterpuncher::*;
+
+pub struct State {
+    game: game::Game,
+    cursor: rltk::Point,
+    tab_targets: Vec<rltk::Point>,
+    tab_index: usize,
+    // set when we are watching a replay instead of playing
+    playback: Option<replay::Playback>,
+    // the rules the next new game will use
+    rules: Ruleset,
+}
+
+impl GameState for State {
+    fn tick(&mut self, ctx: &mut Rltk) {
+        // cleanup
+        ctx.set_active_console(0);
+        ctx.cls();
+        ctx.set_active_console(2);
+        ctx.cls();
+        ctx.set_active_console(1);
+        ctx.cls();
+        sys_particle::cleanup_particles(&mut self.game.ecs, ctx.frame_time_ms);
+
+        // draw map + gui
+        gui::draw_map(&self.game.ecs, ctx);
+        gui::draw_renderables(&self.game.ecs, ctx);
+        gui::draw_sidebar(&self.game.ecs, ctx);
+        gui::draw_active_attacks(&self.game.ecs, ctx);
+        gui::draw_intents(&self.game.ecs, ctx);
+        gui::draw_hand(&self.game.ecs, ctx);
+
+        // get the current RunState
+        let curr_status = self.game.run_state();
+        let mut next_status = curr_status;
+
+        if let Some(playback) = &self.playback {
+            gui::draw_replay_controls(ctx, playback, self.game.turn());
+
+            // the playback takes over any time the player would have made a decision
+            match next_status {
+                RunState::AwaitingInput
+                | RunState::Targetting { .. }
+                | RunState::ChooseReward { .. }
+                | RunState::Dead => {
+                    next_status = player::replay_input(self, ctx);
+
+                    let mut status_writer = self.game.ecs.write_resource::<RunState>();
+                    *status_writer = next_status;
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        match next_status {
+            RunState::AwaitingInput => {
+                gui::update_controls_text(&self.game.ecs, ctx, &next_status);
+                next_status = player::player_input(self, ctx);
+            }
+            RunState::Targetting {
+                attack_type,
+                ignore_targetting,
+            } => {
+                gui::update_controls_text(&self.game.ecs, ctx, &next_status);
+                next_status = player::ranged_target(self, ctx, attack_type, ignore_targetting);
+            }
+            RunState::ViewEnemy { index } => {
+                gui::update_controls_text(&self.game.ecs, ctx, &next_status);
+                next_status = player::view_input(self, ctx, index);
+            }
+            RunState::ViewCard => {
+                gui::update_controls_text(&self.game.ecs, ctx, &next_status);
+                next_status = player::view_input(self, ctx, 0);
+            }
+            RunState::Running => {
+                // uncomment while loop to skip rendering intermediate states
+                while next_status == RunState::Running {
+                    next_status = self.game.run_systems();
+
+                    if next_status != RunState::Running {
+                        break;
+                    }
+
+                    // std::thread::sleep(std::time::Duration::from_millis(100));
+                    next_status = self.game.run_state();
+                }
+            }
+            RunState::HitPause { remaining_time } => {
+                {
+                    gui::update_controls_text(&self.game.ecs, ctx, &next_status);
+                    let mut intents = self.game.ecs.fetch_mut::<crate::IntentData>();
+                    intents.hidden = false;
+                }
+
+                if self.playback.is_none() {
+                    player::pause_input(self, ctx);
+                }
+
+                let stack_empty = events::process_stack_visual_only(&mut self.game.ecs);
+                sys_particle::ParticleSpawnSystem.run_now(&self.game.ecs);
+
+                let new_time = remaining_time - ctx.frame_time_ms;
+                if new_time < 0.0 || stack_empty {
+                    next_status = RunState::Running;
+                } else {
+                    next_status = RunState::HitPause {
+                        remaining_time: new_time,
+                    }
+                }
+            }
+            RunState::ChooseReward { choices } => {
+                gui::update_controls_text(&self.game.ecs, ctx, &next_status);
+                next_status = player::choice_screen(self, ctx, choices);
+            }
+            RunState::GenerateMap => {
+                self.game.change_level();
+                next_status = RunState::AwaitingInput;
+            }
+            RunState::Dead => {
+                gui::update_controls_text(&self.game.ecs, ctx, &next_status);
+                gui::draw_ruleset_choice(ctx, &self.rules);
+
+                match ctx.key {
+                    None => {}
+                    Some(key) => match key {
+                        rltk::VirtualKeyCode::R => {
+                            let seed = game::Game::random_seed();
+                            self.game = game::Game::new(seed, self.rules.clone());
+                            next_status = RunState::Running;
+                        }
+                        rltk::VirtualKeyCode::Tab => {
+                            self.rules = ruleset::next_ruleset(&self.rules.name);
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+
+        // dying writes out a replay, so that the run can be looked at again
+        if next_status == RunState::Dead && curr_status != RunState::Dead && self.playback.is_none()
+        {
+            player::save_replay(self);
+        }
+
+        let mut status_writer = self.game.ecs.write_resource::<RunState>();
+        *status_writer = next_status;
+    }
+}
+
+fn main() -> rltk::BError {
+    use rltk::RltkBuilder;
+    rltk::link_resource!(FONT, "resources/Zilk-16x16.png");
+    rltk::link_resource!(ICONS, "resources/custom_icons.png");
+
+    if let Err(err) = load_data() {
+        println!("{}", err);
+        std::process::exit(1);
+    }
+
+    let context = RltkBuilder::simple(gui::CONSOLE_WIDTH, gui::CONSOLE_HEIGHT)?
+        .with_title("counterpuncher")
+        .with_font("Zilk-16x16.png", 16, 16)
+        .with_font("custom_icons.png", 16, 16)
+        .with_simple_console_no_bg(gui::CONSOLE_WIDTH, gui::CONSOLE_HEIGHT, "Zilk-16x16.png") // main layer
+        .with_sparse_console_no_bg(gui::CONSOLE_WIDTH, gui::CONSOLE_HEIGHT, "custom_icons.png") // custom icons
+        .with_sparse_console(gui::CONSOLE_WIDTH, gui::CONSOLE_HEIGHT, "Zilk-16x16.png") // control line
+        .build()
+        .expect("Failed to build console");
+
+    let rules = parse_rules();
+    let (game, playback) = match start_replay() {
+        Some((game, playback)) => (game, Some(playback)),
+        None => (start_game(rules.clone()), None),
+    };
+
+    let gs = State {
+        game,
+        cursor: rltk::Point::zero(),
+        tab_targets: Vec::new(),
+        tab_index: 0,
+        playback,
+        rules,
+    };
+
+    rltk::main_loop(context, gs)
+}
+
+fn start_game(rules: Ruleset) -> game::Game {
+    // a run can be reproduced by starting with the same seed
+    if let Some(seed) = parse_seed() {
+        println!("seed {}", seed);
+        return game::Game::new(seed, rules);
+    }
+
+    if saveload::save_exists(saveload::SAVE_PATH) {
+        match game::Game::load(saveload::SAVE_PATH) {
+            Ok(game) => {
+                // in permadeath mode, there is only ever one chance to resume a save
+                if has_flag("--permadeath") {
+                    saveload::delete_save(saveload::SAVE_PATH);
+                }
+
+                return game;
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    let seed = game::Game::random_seed();
+    println!("seed {}", seed);
+    game::Game::new(seed, rules)
+}
+
+fn start_replay() -> Option<(game::Game, replay::Playback)> {
+    let path = parse_arg("--replay")?;
+
+    let replay = match replay::Replay::load(&path) {
+        Ok(replay) => replay,
+        Err(err) => {
+            println!("{}", err);
+            return None;
+        }
+    };
+
+    if !replay.is_current_version() {
+        println!(
+            "replay was recorded on version {}, it may not play back the same way",
+            replay.version
+        );
+    }
+
+    println!("seed {}", replay.seed);
+    let mut playback = match replay::Playback::new(replay) {
+        Ok(playback) => playback,
+        Err(err) => {
+            println!("{}", err);
+            return None;
+        }
+    };
+    let mut game = playback.start();
+
+    if let Some(turn) = parse_arg("--turn").and_then(|turn| turn.parse::<u32>().ok()) {
+        playback.jump_to_turn(&mut game, turn);
+    }
+
+    Some((game, playback))
+}
+
+fn has_flag(flag: &str) -> bool {
+    std::env::args().any(|arg| arg == flag)
+}
+
+fn parse_arg(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag_index = args.iter().position(|arg| arg == flag)?;
+
+    args.get(flag_index + 1).cloned()
+}
+
+fn parse_rules() -> Ruleset {
+    let name = match parse_arg("--rules") {
+        None => return ruleset::default_ruleset(),
+        Some(name) => name,
+    };
+
+    match ruleset::find_ruleset(&name) {
+        Some(rules) => rules,
+        None => {
+            println!(
+                "unknown ruleset \"{}\", pick one of: {}",
+                name,
+                ruleset::ruleset_names().join(", ")
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_seed() -> Option<u64> {
+    parse_arg("--seed").and_then(|seed| seed.parse::<u64>().ok())
+}
@@ -0,0 +1,377 @@
+use crate::*;
+use rltk::{Algorithm2D, Rltk, VirtualKeyCode};
+
+fn select_card(gs: &mut State, index: usize) -> RunState {
+    let next_status = gs.game.apply(Command::SelectCard { index });
+
+    let attack_type = match next_status {
+        RunState::Targetting { attack_type, .. } => attack_type,
+        _ => return next_status,
+    };
+
+    let (is_reaction, reaction_target) = gs.game.reaction_status();
+    let player_point = gs.game.player_point();
+
+    // update targetting specific state
+    if is_reaction {
+        let positions = gs.game.ecs.read_storage::<Position>();
+        let target_point = reaction_target
+            .and_then(|target| positions.get(target))
+            .map(|target_pos| target_pos.as_point());
+
+        if let Some(target_point) = target_point {
+            if let Some(point) =
+                crate::move_type::is_attack_valid(&attack_type, player_point, target_point)
+            {
+                // TODO: other points in range are still valid, but maybe they shouldn't be
+                gs.cursor = point;
+                gs.tab_index = 0;
+            }
+        }
+    } else {
+        let valid_targets = gs.game.valid_targets(&attack_type);
+        let map = gs.game.ecs.fetch::<Map>();
+        let mut tab_targets = Vec::new();
+
+        // We can target visible tiles in range
+        for idx in valid_targets.iter() {
+            let index = map.point2d_to_index(*idx);
+
+            if map.blocked_tiles[index] && !map.tiles[index].blocks_movement() {
+                tab_targets.push(*idx);
+            }
+        }
+
+        let init_point;
+        if tab_targets.len() > 0 {
+            init_point = tab_targets[0];
+        } else {
+            init_point = player_point;
+        }
+
+        gs.cursor = init_point;
+        gs.tab_targets = tab_targets;
+        gs.tab_index = 0;
+    }
+
+    next_status
+}
+
+fn save_game(gs: &mut State) {
+    let message = match gs.game.save(crate::saveload::SAVE_PATH) {
+        Ok(()) => "Game saved".to_string(),
+        Err(err) => format!("{}", err),
+    };
+
+    println!("{}", message);
+    let mut log = gs.game.ecs.fetch_mut::<crate::gamelog::GameLog>();
+    log.entries.push(message);
+}
+
+// keep a record of the run so that it can be played back later
+pub fn save_replay(gs: &mut State) {
+    let message = match gs.game.recording().save(crate::replay::REPLAY_PATH) {
+        Ok(()) => format!("Replay saved to {}", crate::replay::REPLAY_PATH),
+        Err(err) => format!("{}", err),
+    };
+
+    println!("{}", message);
+    let mut log = gs.game.ecs.fetch_mut::<crate::gamelog::GameLog>();
+    log.entries.push(message);
+}
+
+// saving and quitting still work while an exchange is being shown, the save picks up partway
+// through it
+pub fn pause_input(gs: &mut State, ctx: &mut Rltk) {
+    match ctx.key {
+        Some(VirtualKeyCode::S) => save_game(gs),
+        Some(VirtualKeyCode::Q) => {
+            save_game(gs);
+            save_replay(gs);
+            ctx.quit();
+        }
+        _ => {}
+    }
+}
+
+// holding shift sneaks instead
+fn step(ctx: &Rltk, dx: i32, dy: i32) -> Command {
+    if ctx.shift {
+        Command::Sneak { dx, dy }
+    } else {
+        Command::Move { dx, dy }
+    }
+}
+
+pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+    match ctx.key {
+        None => RunState::AwaitingInput,
+        Some(key) => match key {
+            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
+                gs.game.apply(step(ctx, -1, 0))
+            }
+            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
+                gs.game.apply(step(ctx, 1, 0))
+            }
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
+                gs.game.apply(step(ctx, 0, -1))
+            }
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
+                gs.game.apply(step(ctx, 0, 1))
+            }
+            VirtualKeyCode::V => RunState::ViewEnemy { index: 0 },
+            VirtualKeyCode::S => {
+                save_game(gs);
+                RunState::AwaitingInput
+            }
+            VirtualKeyCode::Q => {
+                save_game(gs);
+                save_replay(gs);
+                ctx.quit();
+                RunState::AwaitingInput
+            }
+            VirtualKeyCode::Space | VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                gs.game.apply(Command::Pass)
+            }
+            VirtualKeyCode::Key1 => select_card(gs, 0),
+            VirtualKeyCode::Key2 => select_card(gs, 1),
+            VirtualKeyCode::Key3 => select_card(gs, 2),
+            VirtualKeyCode::Key4 => select_card(gs, 3),
+            VirtualKeyCode::Key5 => select_card(gs, 4),
+            VirtualKeyCode::Key6 => select_card(gs, 5),
+            VirtualKeyCode::Key7 => select_card(gs, 6),
+            _ => RunState::AwaitingInput,
+        },
+    }
+}
+
+pub fn ranged_target(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    attack_type: AttackType,
+    ignore_targetting: bool,
+) -> RunState {
+    if ignore_targetting {
+        ctx.print_color(
+            crate::gui::MAP_X,
+            crate::gui::MAP_Y - 1,
+            crate::header_message_color(),
+            crate::bg_color(),
+            "Confirm use",
+        );
+    } else {
+        ctx.set_active_console(0);
+
+        // Highlight available target cells
+        let available_cells = gs.game.valid_targets(&attack_type);
+        for idx in available_cells.iter() {
+            ctx.set_bg(
+                crate::gui::MAP_X + idx.x,
+                crate::gui::MAP_Y + idx.y,
+                crate::tiles_in_range_color(),
+            );
+        }
+
+        // Draw cursor
+        let valid_target = available_cells
+            .iter()
+            .any(|pos| pos.x == gs.cursor.x && pos.y == gs.cursor.y);
+
+        let cursor_color;
+        if valid_target {
+            cursor_color = crate::valid_cursor_color();
+        } else {
+            cursor_color = crate::invalid_cursor_color();
+        }
+        ctx.set_bg(
+            crate::gui::MAP_X + gs.cursor.x,
+            crate::gui::MAP_Y + gs.cursor.y,
+            cursor_color,
+        );
+        ctx.set_active_console(1);
+
+        if valid_target {
+            ctx.print_color(
+                crate::gui::MAP_X,
+                crate::gui::MAP_Y - 1,
+                crate::header_message_color(),
+                crate::bg_color(),
+                "Select Target",
+            );
+        } else {
+            ctx.print_color(
+                crate::gui::MAP_X,
+                crate::gui::MAP_Y - 1,
+                crate::header_err_color(),
+                crate::bg_color(),
+                "Invalid Target",
+            );
+        }
+    }
+
+    match ctx.key {
+        None => {}
+        Some(key) => match key {
+            VirtualKeyCode::Escape => return gs.game.apply(Command::CancelTarget),
+            VirtualKeyCode::Space | VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                // invalid targets cancel the selection
+                return gs.game.apply(Command::Target {
+                    point: Some(gs.cursor),
+                });
+            }
+            VirtualKeyCode::Tab => {
+                let length = gs.tab_targets.len();
+
+                if length > 0 {
+                    gs.tab_index += 1;
+                    gs.cursor = gs.tab_targets[gs.tab_index % length];
+                }
+            }
+            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
+                gs.cursor.x -= 1;
+            }
+            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
+                gs.cursor.x += 1;
+            }
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
+                gs.cursor.y -= 1;
+            }
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
+                gs.cursor.y += 1;
+            }
+            // TODO: placeholder
+            VirtualKeyCode::V => return gs.game.apply(Command::CancelTarget),
+            _ => {}
+        },
+    };
+
+    RunState::Targetting {
+        attack_type,
+        ignore_targetting,
+    }
+}
+
+pub fn view_input(gs: &mut State, ctx: &mut Rltk, index: u32) -> RunState {
+    let entities = gs.game.ecs.entities();
+    let v_indexes = gs.game.ecs.read_storage::<ViewableIndex>();
+    let viewables = gs.game.ecs.read_storage::<Viewable>();
+
+    let mut new_index = index;
+    let mut max_index = 0;
+
+    for (ent, viewables, v_index) in (&entities, &viewables, &v_indexes).join() {
+        if let Some(list_index) = v_index.list_index {
+            max_index = std::cmp::max(list_index, max_index);
+
+            if list_index == index {
+                crate::gui::draw_viewable_info(&gs.game.ecs, ctx, &ent, index);
+            }
+        }
+    }
+
+    match ctx.key {
+        None => {}
+        Some(key) => match key {
+            VirtualKeyCode::Escape => return RunState::AwaitingInput,
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
+                if new_index > 0 {
+                    new_index -= 1;
+                } else {
+                    new_index += max_index;
+                }
+            }
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
+                new_index += 1;
+            }
+            _ => {}
+        },
+    }
+
+    RunState::ViewEnemy {
+        index: new_index % (max_index + 1),
+    }
+}
+
+pub fn choice_screen(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    choices: [Option<crate::AttackType>; 4],
+) -> RunState {
+    ctx.set_active_console(2);
+    ctx.cls();
+    ctx.set_active_console(1);
+    ctx.cls();
+
+    let y_pos = (crate::gui::CONSOLE_HEIGHT - 1 - crate::gui::CARD_H) / 2;
+    let x_pos = (crate::gui::CONSOLE_WIDTH - 1 - 3 * crate::gui::CARD_W) / 4;
+
+    if let Some(card) = choices[0] {
+        crate::gui::draw_card_hand(ctx, &card, x_pos, y_pos, 0, false);
+    }
+    if let Some(card) = choices[1] {
+        crate::gui::draw_card_hand(ctx, &card, 2 * x_pos + crate::gui::CARD_W, y_pos, 1, false);
+    }
+    if let Some(card) = choices[2] {
+        crate::gui::draw_card_hand(
+            ctx,
+            &card,
+            3 * x_pos + 2 * crate::gui::CARD_W,
+            y_pos,
+            2,
+            false,
+        );
+    }
+    if let Some(card) = choices[3] {
+        // TODO: put this somewhere else
+        crate::gui::draw_card_hand(ctx, &card, x_pos, y_pos + crate::gui::CARD_H + 5, 3, false);
+    }
+
+    let index = match ctx.key {
+        None => return RunState::ChooseReward { choices },
+        Some(key) => match key {
+            VirtualKeyCode::Space | VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => None,
+            VirtualKeyCode::Key1 => Some(0),
+            VirtualKeyCode::Key2 => Some(1),
+            VirtualKeyCode::Key3 => Some(2),
+            VirtualKeyCode::Key4 => Some(3),
+            _ => return RunState::ChooseReward { choices },
+        },
+    };
+
+    gs.game.apply(Command::ChooseReward { index })
+}
+
+// in replay mode, the keys control the playback instead of the player
+pub fn replay_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+    let playback = match gs.playback.as_mut() {
+        None => return gs.game.run_state(),
+        Some(playback) => playback,
+    };
+
+    let turn = gs.game.turn();
+
+    match ctx.key {
+        None => {}
+        Some(key) => match key {
+            VirtualKeyCode::Period | VirtualKeyCode::Space => {
+                playback.apply_next(&mut gs.game);
+            }
+            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
+                playback.jump_to_turn(&mut gs.game, turn + 1);
+            }
+            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
+                playback.jump_to_turn(&mut gs.game, turn.saturating_sub(1));
+            }
+            VirtualKeyCode::PageDown => {
+                playback.jump_to_turn(&mut gs.game, turn + 10);
+            }
+            VirtualKeyCode::PageUp => {
+                playback.jump_to_turn(&mut gs.game, turn.saturating_sub(10));
+            }
+            VirtualKeyCode::Escape | VirtualKeyCode::Q => ctx.quit(),
+            _ => {}
+        },
+    }
+
+    gs.game.run_state()
+}
@@ -0,0 +1,402 @@
+use crate::*;
+use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator};
+
+// give up on a game if this many commands go by without the turn moving on
+const STUCK_LIMIT: u32 = 500;
+
+/// Decides what the player does, so that games can be played without anyone at the keyboard.
+/// Each method is called at the matching decision point, and commands that don't make sense
+/// there are ignored by the game.
+pub trait Policy {
+    /// Move, pass, or select a card on the player's own turn
+    fn take_turn(&mut self, game: &Game) -> Command;
+    /// Select a card to counter with, or pass to block
+    fn react(&mut self, game: &Game, attacker: Option<Entity>) -> Command;
+    /// Aim the selected card with `Command::Target`, or back out of it
+    fn aim(&mut self, game: &Game, card: AttackType) -> Command;
+    /// Take one of the offered cards, or skip them
+    fn choose_reward(&mut self, game: &Game, choices: &[Option<AttackType>; 4]) -> Command;
+}
+
+/// When to stop a game that hasn't ended on its own
+#[derive(Copy, Clone)]
+pub struct RunLimits {
+    pub max_depth: i32,
+    pub max_turns: u32,
+}
+
+pub enum RunEnd {
+    Died { killed_by: String },
+    ReachedDepth,
+    OutOfTurns,
+    // the policy kept giving commands that went nowhere
+    Stuck,
+}
+
+pub struct RunReport {
+    pub seed: u64,
+    pub depth: i32,
+    pub turns: u32,
+    pub cards_played: u32,
+    pub end: RunEnd,
+}
+
+/// Play a game until the player dies or one of the limits is hit
+pub fn play(game: &mut Game, policy: &mut dyn Policy, limits: RunLimits) -> RunReport {
+    let mut cards_played = 0;
+    let mut stuck_commands = 0;
+    let mut last_turn = game.turn();
+
+    let end = loop {
+        let run_state = game.advance();
+
+        if current_depth(game) >= limits.max_depth {
+            break RunEnd::ReachedDepth;
+        }
+        if game.turn() >= limits.max_turns {
+            break RunEnd::OutOfTurns;
+        }
+
+        let command = match run_state {
+            RunState::AwaitingInput => match game.reaction_status() {
+                (true, attacker) => policy.react(game, attacker),
+                (false, _) => policy.take_turn(game),
+            },
+            RunState::Targetting {
+                attack_type,
+                ignore_targetting,
+            } => {
+                if ignore_targetting {
+                    Command::Target { point: None }
+                } else {
+                    policy.aim(game, attack_type)
+                }
+            }
+            RunState::ChooseReward { choices } => policy.choose_reward(game, &choices),
+            RunState::Dead => {
+                let log = game.ecs.fetch::<gamelog::GameLog>();
+                let killed_by = log
+                    .killed_by
+                    .clone()
+                    .unwrap_or_else(|| "something".to_string());
+
+                break RunEnd::Died { killed_by };
+            }
+            // advance only stops at decision points
+            _ => unreachable!("the game stopped outside of a decision point"),
+        };
+
+        let was_targetting = matches!(run_state, RunState::Targetting { .. });
+        if game.apply(command) == RunState::Running && was_targetting {
+            cards_played += 1;
+        }
+
+        if game.turn() == last_turn {
+            stuck_commands += 1;
+            if stuck_commands >= STUCK_LIMIT {
+                break RunEnd::Stuck;
+            }
+        } else {
+            last_turn = game.turn();
+            stuck_commands = 0;
+        }
+    };
+
+    RunReport {
+        seed: game.ecs.fetch::<RunSeed>().0,
+        depth: current_depth(game),
+        turns: game.turn(),
+        cards_played,
+        end,
+    }
+}
+
+fn current_depth(game: &Game) -> i32 {
+    game.ecs.fetch::<Map>().depth
+}
+
+/// Picks any command that could do something, with its own rng so the game's rolls aren't
+/// disturbed
+pub struct RandomPolicy {
+    rng: RandomNumberGenerator,
+}
+
+impl RandomPolicy {
+    pub fn new(seed: u64) -> Self {
+        RandomPolicy {
+            rng: RandomNumberGenerator::seeded(seed),
+        }
+    }
+}
+
+impl Policy for RandomPolicy {
+    fn take_turn(&mut self, game: &Game) -> Command {
+        let hand_size = game.ecs.fetch::<deck::Deck>().hand.len();
+
+        match self.rng.range(0, 6 + hand_size) {
+            0 => Command::Move { dx: -1, dy: 0 },
+            1 => Command::Move { dx: 1, dy: 0 },
+            2 => Command::Move { dx: 0, dy: -1 },
+            3 => Command::Move { dx: 0, dy: 1 },
+            4 | 5 => Command::Pass,
+            index => Command::SelectCard { index: index - 6 },
+        }
+    }
+
+    fn react(&mut self, game: &Game, attacker: Option<Entity>) -> Command {
+        let counters = counters(game, attacker);
+
+        // sometimes block even when there is a counter
+        if counters.is_empty() || self.rng.range(0, counters.len() + 1) == 0 {
+            Command::Pass
+        } else {
+            let pick = self.rng.range(0, counters.len());
+            Command::SelectCard {
+                index: counters[pick],
+            }
+        }
+    }
+
+    fn aim(&mut self, game: &Game, card: AttackType) -> Command {
+        let targets = game.valid_targets(&card);
+
+        if targets.is_empty() {
+            Command::CancelTarget
+        } else {
+            let pick = self.rng.range(0, targets.len());
+            Command::Target {
+                point: Some(targets[pick]),
+            }
+        }
+    }
+
+    fn choose_reward(&mut self, _game: &Game, choices: &[Option<AttackType>; 4]) -> Command {
+        let offered: Vec<usize> = (0..choices.len())
+            .filter(|index| choices[*index].is_some())
+            .collect();
+
+        // skipping is one more option
+        let pick = self.rng.range(0, offered.len() + 1);
+        Command::ChooseReward {
+            index: offered.get(pick).copied(),
+        }
+    }
+}
+
+/// Hits whatever it can with the strongest card it has, otherwise closes in on the nearest enemy
+/// or heads for the exit. Picks the rarest card it is offered.
+pub struct GreedyPolicy;
+
+impl Policy for GreedyPolicy {
+    fn take_turn(&mut self, game: &Game) -> Command {
+        let player_pos = game.player_point();
+        let enemies = visible_enemies(game);
+
+        // the strongest card that can hit anyone right now
+        let best_attack = {
+            let deck = game.ecs.fetch::<deck::Deck>();
+            deck.hand
+                .iter()
+                .enumerate()
+                .filter(|(_, card)| deals_damage(card))
+                .filter(|(_, card)| {
+                    enemies.iter().any(|(_, pos)| {
+                        move_type::is_attack_valid(card, player_pos, *pos).is_some()
+                    })
+                })
+                .max_by_key(|(_, card)| move_type::get_attack_power(card))
+                .map(|(index, _)| index)
+        };
+
+        if let Some(index) = best_attack {
+            return Command::SelectCard { index };
+        }
+
+        // otherwise close in, or draw while waiting for something to hit with
+        let nearest_enemy = enemies
+            .iter()
+            .min_by_key(|(_, pos)| distance(player_pos, *pos));
+        let step = match nearest_enemy {
+            Some((_, pos)) => path_beside(game, *pos).or_else(|| step_towards(game, *pos)),
+            None => {
+                let exit = {
+                    let map = game.ecs.fetch::<Map>();
+                    map.index_to_point2d(map.level_exit)
+                };
+
+                path_towards(game, exit).or_else(|| step_towards(game, exit))
+            }
+        };
+
+        step.unwrap_or(Command::Pass)
+    }
+
+    fn react(&mut self, game: &Game, attacker: Option<Entity>) -> Command {
+        let deck = game.ecs.fetch::<deck::Deck>();
+
+        counters(game, attacker)
+            .into_iter()
+            .max_by_key(|index| {
+                let card = &deck.hand[*index];
+                (
+                    move_type::get_attack_power(card),
+                    move_type::get_attack_speed(card),
+                )
+            })
+            .map_or(Command::Pass, |index| Command::SelectCard { index })
+    }
+
+    fn aim(&mut self, game: &Game, card: AttackType) -> Command {
+        let player_pos = game.player_point();
+        let targets = game.valid_targets(&card);
+
+        // aim at whichever enemy is closest, through the tile the card would hit them from
+        let mut enemies = visible_enemies(game);
+        enemies.sort_by_key(|(_, pos)| distance(player_pos, *pos));
+
+        let point = enemies
+            .iter()
+            .filter_map(|(_, pos)| move_type::is_attack_valid(&card, player_pos, *pos))
+            .find(|tile| targets.contains(tile));
+
+        match point {
+            Some(point) => Command::Target { point: Some(point) },
+            None => Command::CancelTarget,
+        }
+    }
+
+    fn choose_reward(&mut self, _game: &Game, choices: &[Option<AttackType>; 4]) -> Command {
+        let index = (0..choices.len())
+            .filter_map(|index| choices[index].map(|card| (index, card)))
+            .max_by_key(|(_, card)| cards::get_card(card).rarity)
+            .map(|(index, _)| index);
+
+        Command::ChooseReward { index }
+    }
+}
+
+// hand indexes of the cards that can be played back at the attacker
+fn counters(game: &Game, attacker: Option<Entity>) -> Vec<usize> {
+    let attacker_pos = match attacker {
+        None => return Vec::new(),
+        Some(attacker) => match game.ecs.read_storage::<Position>().get(attacker) {
+            None => return Vec::new(),
+            Some(pos) => pos.as_point(),
+        },
+    };
+
+    let player_pos = game.player_point();
+    let deck = game.ecs.fetch::<deck::Deck>();
+
+    (0..deck.hand.len())
+        .filter(|index| {
+            let card = &deck.hand[*index];
+            move_type::get_attack_timing(card) == AttackTiming::Fast
+                && move_type::is_attack_valid(card, player_pos, attacker_pos).is_some()
+        })
+        .collect()
+}
+
+fn deals_damage(card: &AttackType) -> bool {
+    move_type::get_attack_traits(card).contains(&AttackTrait::Damage)
+        && move_type::get_attack_power(card) > 0
+}
+
+// anything with an ai that the player can currently see
+fn visible_enemies(game: &Game) -> Vec<(Entity, Point)> {
+    let entities = game.ecs.entities();
+    let positions = game.ecs.read_storage::<Position>();
+    let ais = game.ecs.read_storage::<AiState>();
+    let map = game.ecs.fetch::<Map>();
+
+    (&entities, &positions, &ais)
+        .join()
+        .filter(|(_, pos, _)| map.visible_tiles[map.get_index(pos.x, pos.y)])
+        .map(|(ent, pos, _)| (ent, pos.as_point()))
+        .collect()
+}
+
+fn distance(a: Point, b: Point) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+// take the first step of the shortest open path
+fn path_towards(game: &Game, target: Point) -> Option<Command> {
+    let player_pos = game.player_point();
+    let map = game.ecs.fetch::<Map>();
+    let start = map.point2d_to_index(player_pos);
+    let end = map.point2d_to_index(target);
+    let path = rltk::a_star_search(start, end, &*map);
+
+    if path.success && path.steps.len() > 1 {
+        let next = map.index_to_point2d(path.steps[1]);
+        Some(Command::Move {
+            dx: next.x - player_pos.x,
+            dy: next.y - player_pos.y,
+        })
+    } else {
+        None
+    }
+}
+
+// take the first step towards whichever open tile next to the target is closest, so that
+// rubble and the like get walked around
+fn path_beside(game: &Game, target: Point) -> Option<Command> {
+    let player_pos = game.player_point();
+    if distance(player_pos, target) <= 1 {
+        return None;
+    }
+
+    let map = game.ecs.fetch::<Map>();
+    let start = map.point2d_to_index(player_pos);
+
+    let path = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .iter()
+        .map(|(dx, dy)| Point::new(target.x + dx, target.y + dy))
+        .filter(|next| map.in_bounds(*next) && !map.blocked_tiles[map.point2d_to_index(*next)])
+        .map(|next| rltk::a_star_search(start, map.point2d_to_index(next), &*map))
+        .filter(|path| path.success && path.steps.len() > 1)
+        .min_by_key(|path| path.steps.len())?;
+
+    let next = map.index_to_point2d(path.steps[1]);
+    Some(Command::Move {
+        dx: next.x - player_pos.x,
+        dy: next.y - player_pos.y,
+    })
+}
+
+// move in whichever direction closes the most distance
+// bumping into a barrel is fine since that opens it, but there's no point walking into a wall
+fn step_towards(game: &Game, target: Point) -> Option<Command> {
+    let player_pos = game.player_point();
+    let map = game.ecs.fetch::<Map>();
+
+    let open_step = map
+        .get_available_exits(map.point2d_to_index(player_pos))
+        .iter()
+        .map(|(index, _)| map.index_to_point2d(*index))
+        .min_by_key(|next| distance(*next, target))
+        .filter(|next| distance(*next, target) < distance(player_pos, target));
+
+    let next = open_step.or_else(|| {
+        let dx = (target.x - player_pos.x).signum();
+        let dy = (target.y - player_pos.y).signum();
+        let bump = if dx != 0 {
+            Point::new(player_pos.x + dx, player_pos.y)
+        } else {
+            Point::new(player_pos.x, player_pos.y + dy)
+        };
+
+        let index = map.point2d_to_index(bump);
+        let openable = map.creature_map.get(&index).map_or(false, |ent| {
+            game.ecs.read_storage::<Openable>().contains(*ent)
+        });
+
+        Some(bump).filter(|_| openable)
+    })?;
+
+    Some(Command::Move {
+        dx: next.x - player_pos.x,
+        dy: next.y - player_pos.y,
+    })
+}
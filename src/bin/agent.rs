@@ -0,0 +1,136 @@
+use counterpuncher::observation::Observation;
+use counterpuncher::*;
+use serde::Deserialize;
+use std::io::{BufRead, Write};
+
+const USAGE: &str = "usage: agent [--seed n] [--rules name]
+plays a game over stdin and stdout, one json object per line
+an observation is written at the start and after every request, requests look like
+  {\"Act\": \"Pass\"}
+  {\"Act\": {\"Move\": {\"dx\": 1, \"dy\": 0}}}
+  {\"Act\": {\"SelectCard\": {\"index\": 0}}}
+  {\"Act\": {\"Target\": {\"point\": {\"x\": 10, \"y\": 4}}}}
+  {\"Reset\": {\"seed\": null}}
+  \"Quit\"
+the actions allowed at each point are listed in the observation's legal_actions";
+
+#[derive(Deserialize)]
+enum Request {
+    Act(Command),
+    // start over, on a new random seed if none is given
+    Reset { seed: Option<u64> },
+    Quit,
+}
+
+struct Session {
+    game: Game,
+    rules: Ruleset,
+    // how many log entries have already been sent
+    seen_messages: usize,
+}
+
+impl Session {
+    fn new(seed: u64, rules: Ruleset) -> Self {
+        let mut game = Game::new(seed, rules.clone());
+        game.advance();
+
+        Session {
+            game,
+            rules,
+            seen_messages: 0,
+        }
+    }
+
+    fn observe(&mut self) -> String {
+        let observation = Observation::new(&self.game, self.seen_messages);
+        self.seen_messages += observation.messages.len();
+
+        serde_json::to_string(&observation).expect("Failed to serialize observation")
+    }
+
+    fn act(&mut self, command: Command) -> Result<(), String> {
+        if !self.game.legal_commands().contains(&command) {
+            return Err("that action isn't legal right now".to_string());
+        }
+
+        self.game.apply(command);
+        self.game.advance();
+        Ok(())
+    }
+}
+
+fn main() {
+    if let Err(err) = load_data() {
+        println!("{}", err);
+        std::process::exit(1);
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let seed = parse_arg(&args, "--seed")
+        .and_then(|seed| seed.parse::<u64>().ok())
+        .unwrap_or_else(Game::random_seed);
+    let rules = match parse_arg(&args, "--rules") {
+        None => ruleset::default_ruleset(),
+        Some(name) => ruleset::find_ruleset(&name).unwrap_or_else(|| {
+            println!(
+                "unknown ruleset \"{}\", pick one of: {}",
+                name,
+                ruleset::ruleset_names().join(", ")
+            );
+            std::process::exit(1);
+        }),
+    };
+
+    let mut session = Session::new(seed, rules);
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    send(&mut stdout, &session.observe());
+
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match serde_json::from_str::<Request>(&line) {
+            Err(err) => error_reply(&format!("couldn't read request: {}", err)),
+            Ok(Request::Quit) => break,
+            Ok(Request::Reset { seed }) => {
+                let seed = seed.unwrap_or_else(Game::random_seed);
+                session = Session::new(seed, session.rules.clone());
+                session.observe()
+            }
+            Ok(Request::Act(command)) => match session.act(command) {
+                Ok(()) => session.observe(),
+                Err(err) => error_reply(&err),
+            },
+        };
+
+        send(&mut stdout, &reply);
+    }
+}
+
+fn send(stdout: &mut std::io::Stdout, line: &str) {
+    let mut out = stdout.lock();
+    // if whoever is on the other end went away, there's nobody left to play for
+    if writeln!(out, "{}", line).and_then(|_| out.flush()).is_err() {
+        std::process::exit(0);
+    }
+}
+
+fn error_reply(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn parse_arg(args: &[String], flag: &str) -> Option<String> {
+    let flag_index = args.iter().position(|arg| arg == flag)?;
+    args.get(flag_index + 1).cloned()
+}
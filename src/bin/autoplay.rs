@@ -0,0 +1,221 @@
+use counterpuncher::autoplay::{self, GreedyPolicy, Policy, RandomPolicy, RunEnd, RunLimits};
+use counterpuncher::*;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+
+const DEFAULT_GAMES: u32 = 100;
+const DEFAULT_MAX_DEPTH: i32 = 10;
+const DEFAULT_MAX_TURNS: u32 = 2000;
+
+const USAGE: &str = "usage: autoplay [--games n] [--policy greedy|random] [--max-depth n]
+                [--max-turns n] [--seed n] [--rules name]
+game n of a session is played on seed + n, so any game can be rerun on its own";
+
+enum Outcome {
+    Finished(autoplay::RunReport),
+    Panicked { message: String },
+}
+
+fn main() {
+    if let Err(err) = load_data() {
+        println!("{}", err);
+        std::process::exit(1);
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let games = parse_number(&args, "--games").unwrap_or(DEFAULT_GAMES);
+    let limits = RunLimits {
+        max_depth: parse_number(&args, "--max-depth").unwrap_or(DEFAULT_MAX_DEPTH),
+        max_turns: parse_number(&args, "--max-turns").unwrap_or(DEFAULT_MAX_TURNS),
+    };
+    let base_seed = parse_number(&args, "--seed").unwrap_or_else(Game::random_seed);
+    let policy_name = parse_arg(&args, "--policy").unwrap_or_else(|| "greedy".to_string());
+    let rules = match parse_arg(&args, "--rules") {
+        None => ruleset::default_ruleset(),
+        Some(name) => ruleset::find_ruleset(&name).unwrap_or_else(|| {
+            println!(
+                "unknown ruleset \"{}\", pick one of: {}",
+                name,
+                ruleset::ruleset_names().join(", ")
+            );
+            std::process::exit(1);
+        }),
+    };
+
+    if new_policy(&policy_name, 0).is_none() {
+        println!(
+            "unknown policy \"{}\", pick one of: greedy, random",
+            policy_name
+        );
+        std::process::exit(1);
+    }
+
+    println!(
+        "{} games with the {} policy and {} rules, starting from seed {}",
+        games, policy_name, rules.name, base_seed
+    );
+
+    let mut outcomes = Vec::new();
+    for game_index in 0..games {
+        let seed = base_seed.wrapping_add(game_index as u64);
+        let mut policy = new_policy(&policy_name, seed).expect("Policy was already checked");
+        let mut game = None;
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let game = game.insert(Game::new(seed, rules.clone()));
+            autoplay::play(game, policy.as_mut(), limits)
+        }));
+
+        let outcome = match result {
+            Ok(report) => Outcome::Finished(report),
+            Err(payload) => {
+                let message = panic_message(payload);
+
+                // the commands leading up to the panic can be played back in the game
+                if let Some(game) = &game {
+                    let path = format!("./autoplay-{}.json", seed);
+                    if game.recording().save(&path).is_ok() {
+                        println!("seed {}: replay saved to {}", seed, path);
+                    }
+                }
+
+                Outcome::Panicked { message }
+            }
+        };
+
+        print_game(seed, &outcome);
+        outcomes.push(outcome);
+    }
+
+    print_summary(&outcomes);
+}
+
+fn new_policy(name: &str, seed: u64) -> Option<Box<dyn Policy>> {
+    match name {
+        "greedy" => Some(Box::new(GreedyPolicy)),
+        "random" => Some(Box::new(RandomPolicy::new(seed))),
+        _ => None,
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+fn print_game(seed: u64, outcome: &Outcome) {
+    match outcome {
+        Outcome::Panicked { message } => println!("seed {}: PANIC {}", seed, message),
+        Outcome::Finished(report) => {
+            let ending = match &report.end {
+                RunEnd::Died { killed_by } => format!("killed by {}", killed_by),
+                RunEnd::ReachedDepth => "reached the depth cap".to_string(),
+                RunEnd::OutOfTurns => "ran out of turns".to_string(),
+                RunEnd::Stuck => "got stuck".to_string(),
+            };
+
+            println!(
+                "seed {}: depth {}, {} turns, {} cards played, {}",
+                seed, report.depth, report.turns, report.cards_played, ending
+            );
+        }
+    }
+}
+
+fn print_summary(outcomes: &[Outcome]) {
+    let reports: Vec<&autoplay::RunReport> = outcomes
+        .iter()
+        .filter_map(|outcome| match outcome {
+            Outcome::Finished(report) => Some(report),
+            Outcome::Panicked { .. } => None,
+        })
+        .collect();
+    let count =
+        |matches: fn(&RunEnd) -> bool| reports.iter().filter(|report| matches(&report.end)).count();
+
+    println!();
+    println!("games       {}", outcomes.len());
+    println!(
+        "died        {}",
+        count(|end| matches!(end, RunEnd::Died { .. }))
+    );
+    println!(
+        "depth cap   {}",
+        count(|end| matches!(end, RunEnd::ReachedDepth))
+    );
+    println!(
+        "out of turns {}",
+        count(|end| matches!(end, RunEnd::OutOfTurns))
+    );
+    println!("stuck       {}", count(|end| matches!(end, RunEnd::Stuck)));
+    println!("panicked    {}", outcomes.len() - reports.len());
+
+    if reports.is_empty() {
+        return;
+    }
+
+    let games = reports.len() as f32;
+    let total_turns: u32 = reports.iter().map(|report| report.turns).sum();
+    let total_cards: u32 = reports.iter().map(|report| report.cards_played).sum();
+    println!();
+    println!("average turns        {:.1}", total_turns as f32 / games);
+    println!("average cards played {:.1}", total_cards as f32 / games);
+
+    // how many games got to each depth, and how many of those died there
+    let deepest = reports.iter().map(|report| report.depth).max().unwrap_or(1);
+    println!();
+    println!("depth  reached  died there");
+    for depth in 1..=deepest {
+        let reached = reports
+            .iter()
+            .filter(|report| report.depth >= depth)
+            .count();
+        let died = reports
+            .iter()
+            .filter(|report| report.depth == depth)
+            .filter(|report| matches!(report.end, RunEnd::Died { .. }))
+            .count();
+
+        println!(
+            "{:>5}  {:>7}  {:>5} ({:.0}%)",
+            depth,
+            reached,
+            died,
+            100.0 * died as f32 / reached.max(1) as f32
+        );
+    }
+
+    let mut killers: BTreeMap<&str, u32> = BTreeMap::new();
+    for report in reports.iter() {
+        if let RunEnd::Died { killed_by } = &report.end {
+            *killers.entry(killed_by.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    if !killers.is_empty() {
+        println!();
+        println!("killed by");
+        for (killer, deaths) in killers {
+            println!("  {:<14} {}", killer, deaths);
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    parse_arg(args, flag).and_then(|value| value.parse::<T>().ok())
+}
+
+fn parse_arg(args: &[String], flag: &str) -> Option<String> {
+    let flag_index = args.iter().position(|arg| arg == flag)?;
+    args.get(flag_index + 1).cloned()
+}
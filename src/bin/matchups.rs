@@ -0,0 +1,225 @@
+use counterpuncher::exchange::ExchangeSim;
+use counterpuncher::*;
+use std::fmt::Write;
+
+const DEFAULT_CSV_PATH: &str = "./matchups.csv";
+
+const USAGE: &str = "usage: matchups [--rules name] [--csv path]
+every card the player can hold, reacting to every enemy move";
+
+struct Cell {
+    win: f32,
+    lose: f32,
+    // how much more hp the enemy loses than the player
+    hp_swing: f32,
+}
+
+struct Row {
+    reaction: AttackIntent,
+    allowed: bool,
+    cells: Vec<Cell>,
+}
+
+fn main() {
+    if let Err(err) = load_data() {
+        println!("{}", err);
+        std::process::exit(1);
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let rules = match parse_arg(&args, "--rules") {
+        None => ruleset::default_ruleset(),
+        Some(name) => ruleset::find_ruleset(&name).unwrap_or_else(|| {
+            println!(
+                "unknown ruleset \"{}\", pick one of: {}",
+                name,
+                ruleset::ruleset_names().join(", ")
+            );
+            std::process::exit(1);
+        }),
+    };
+    let csv_path = parse_arg(&args, "--csv").unwrap_or_else(|| DEFAULT_CSV_PATH.to_string());
+
+    let enemy_moves = enemy_moves();
+    let mut sim = ExchangeSim::new(rules.clone(), 0);
+    let rows: Vec<Row> = player_reactions()
+        .into_iter()
+        .map(|reaction| Row {
+            reaction,
+            allowed: move_type::get_attack_timing(&reaction.main) == AttackTiming::Fast,
+            cells: enemy_moves
+                .iter()
+                .map(|attack| matchup(&mut sim, attack, &reaction))
+                .collect(),
+        })
+        .collect();
+
+    print_table(&rules, &enemy_moves, &rows);
+
+    match std::fs::write(&csv_path, to_csv(&enemy_moves, &rows)) {
+        Ok(_) => println!("\nwrote {}", csv_path),
+        Err(err) => {
+            println!("\ncouldn't write {}: {}", csv_path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// every card an enemy can attack with, going by the spawn templates
+fn enemy_moves() -> Vec<AttackIntent> {
+    let mut moves = Vec::new();
+
+    for template in templates::all_templates() {
+        let moveset = template.moveset.iter().flatten().map(|(name, _)| name);
+        let deck = template.deck.iter().flat_map(|deck| deck.cards.iter());
+
+        for name in moveset.chain(deck) {
+            let card = cards::card_named(name);
+            if !moves.contains(&card) {
+                moves.push(card);
+            }
+        }
+    }
+
+    moves
+        .iter()
+        .map(|card| move_type::get_attack_intent(card, rltk::Point::zero(), None))
+        .collect()
+}
+
+// every card the player can hold, on its own and boosted by each modifier
+fn player_reactions() -> Vec<AttackIntent> {
+    let is_modifier =
+        |card: &AttackType| move_type::get_attack_traits(card).contains(&AttackTrait::Modifier);
+    let (modifiers, cards): (Vec<AttackType>, Vec<AttackType>) =
+        cards::player_cards().into_iter().partition(is_modifier);
+
+    let mut reactions = Vec::new();
+    for card in cards.iter() {
+        reactions.push(move_type::get_attack_intent(
+            card,
+            rltk::Point::zero(),
+            None,
+        ));
+
+        for modifier in modifiers.iter() {
+            reactions.push(move_type::get_attack_intent(
+                card,
+                rltk::Point::zero(),
+                Some(*modifier),
+            ));
+        }
+    }
+
+    reactions
+}
+
+fn matchup(sim: &mut ExchangeSim, attack: &AttackIntent, reaction: &AttackIntent) -> Cell {
+    let mut cell = Cell {
+        win: 0.0,
+        lose: 0.0,
+        hp_swing: 0.0,
+    };
+
+    // the player is the one reacting
+    for (chance, outcome) in sim.every_outcome(attack, reaction) {
+        let swing = outcome.damage_to_attacker - outcome.damage_to_defender;
+
+        if swing > 0 {
+            cell.win += chance;
+        } else if swing < 0 {
+            cell.lose += chance;
+        }
+        cell.hp_swing += chance * swing as f32;
+    }
+
+    cell
+}
+
+fn print_table(rules: &Ruleset, enemy_moves: &[AttackIntent], rows: &[Row]) {
+    let name_width = rows
+        .iter()
+        .map(|row| move_type::get_intent_name(&row.reaction).len())
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let cell_width = 14;
+    let combinations = rules.speed_faces().len().pow(2) * rules.guard_faces().len().pow(2);
+
+    println!(
+        "{} rules, {} roll combinations per matchup",
+        rules.name, combinations
+    );
+    println!("player win % and expected hp swing, reacting to each enemy move");
+    println!();
+
+    let mut line = format!("{:width$}", "", width = name_width);
+    for attack in enemy_moves {
+        let _ = write!(
+            line,
+            "{:>width$}",
+            move_type::get_intent_name(attack),
+            width = cell_width
+        );
+    }
+    println!("{}", line);
+
+    for row in rows {
+        let mut name = move_type::get_intent_name(&row.reaction);
+        if !row.allowed {
+            name.push('*');
+        }
+
+        let mut line = format!("{:width$}", name, width = name_width);
+        for cell in row.cells.iter() {
+            let _ = write!(
+                line,
+                "{:>width$}",
+                format!("{:.0}% {:+.2}", 100.0 * cell.win, cell.hp_swing),
+                width = cell_width
+            );
+        }
+        println!("{}", line);
+    }
+
+    println!();
+    println!("* slow, so it can't be played as a reaction in a real game");
+}
+
+fn to_csv(enemy_moves: &[AttackIntent], rows: &[Row]) -> String {
+    let mut csv = String::from("player,modifier,enemy,reaction_allowed,win,lose,hp_swing\n");
+
+    for row in rows {
+        let modifier = row
+            .reaction
+            .modifier
+            .map(|modifier| move_type::get_attack_name(&modifier))
+            .unwrap_or_default();
+
+        for (attack, cell) in enemy_moves.iter().zip(row.cells.iter()) {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{:.4},{:.4},{:.4}",
+                move_type::get_attack_name(&row.reaction.main),
+                modifier,
+                move_type::get_attack_name(&attack.main),
+                row.allowed,
+                cell.win,
+                cell.lose,
+                cell.hp_swing
+            );
+        }
+    }
+
+    csv
+}
+
+fn parse_arg(args: &[String], flag: &str) -> Option<String> {
+    let flag_index = args.iter().position(|arg| arg == flag)?;
+    args.get(flag_index + 1).cloned()
+}
@@ -0,0 +1,156 @@
+use counterpuncher::exchange::{ExchangeOutcome, ExchangeSim};
+use counterpuncher::*;
+
+const DEFAULT_RUNS: u32 = 10000;
+
+const USAGE: &str = "usage: simulate <attack> <reaction> [--runs n] [--rules name] [--seed n]
+cards are given by name, add a modifier with + (for example punch+quick)";
+
+fn main() {
+    if let Err(err) = load_data() {
+        println!("{}", err);
+        std::process::exit(1);
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (attack, reaction) = match (args.get(0), args.get(1)) {
+        (Some(attack), Some(reaction)) if !attack.starts_with("--") => {
+            match (parse_intent(attack), parse_intent(reaction)) {
+                (Ok(attack), Ok(reaction)) => (attack, reaction),
+                (Err(err), _) | (_, Err(err)) => {
+                    println!("{}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            println!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let runs = parse_arg(&args, "--runs")
+        .and_then(|runs| runs.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_RUNS)
+        .max(1);
+    let seed = parse_arg(&args, "--seed")
+        .and_then(|seed| seed.parse::<u64>().ok())
+        .unwrap_or_else(Game::random_seed);
+    let rules = match parse_arg(&args, "--rules") {
+        None => ruleset::default_ruleset(),
+        Some(name) => ruleset::find_ruleset(&name).unwrap_or_else(|| {
+            println!(
+                "unknown ruleset \"{}\", pick one of: {}",
+                name,
+                ruleset::ruleset_names().join(", ")
+            );
+            std::process::exit(1);
+        }),
+    };
+
+    println!(
+        "{} vs {}, {} runs with the {} rules (seed {})",
+        move_type::get_intent_name(&attack),
+        move_type::get_intent_name(&reaction),
+        runs,
+        rules.name,
+        seed
+    );
+    warn_if_unplayable(&reaction);
+
+    let mut sim = ExchangeSim::new(rules, seed);
+    let outcomes: Vec<ExchangeOutcome> = (0..runs).map(|_| sim.run(&attack, &reaction)).collect();
+    report(&outcomes);
+}
+
+// "main" or "main+modifier"
+fn parse_intent(text: &str) -> Result<AttackIntent, String> {
+    let mut names = text.split('+');
+    let main = parse_card(names.next().unwrap_or(""))?;
+    let modifier = match names.next() {
+        None => None,
+        Some(name) => Some(parse_card(name)?),
+    };
+
+    if names.next().is_some() {
+        return Err(format!("\"{}\" can only have one modifier", text));
+    }
+    if is_modifier(&main) {
+        return Err(format!(
+            "{} is a modifier, put it after the card it boosts",
+            move_type::get_attack_name(&main)
+        ));
+    }
+    if let Some(modifier) = modifier {
+        if !is_modifier(&modifier) {
+            return Err(format!(
+                "{} isn't a modifier",
+                move_type::get_attack_name(&modifier)
+            ));
+        }
+    }
+
+    Ok(move_type::get_attack_intent(
+        &main,
+        rltk::Point::zero(),
+        modifier,
+    ))
+}
+
+fn parse_card(name: &str) -> Result<AttackType, String> {
+    cards::find_card(name).ok_or_else(|| format!("there is no card called \"{}\"", name))
+}
+
+fn is_modifier(card: &AttackType) -> bool {
+    move_type::get_attack_traits(card).contains(&AttackTrait::Modifier)
+}
+
+// the simulation runs anyways, but it's worth knowing the game wouldn't allow this
+fn warn_if_unplayable(reaction: &AttackIntent) {
+    if move_type::get_attack_timing(&reaction.main) == AttackTiming::Slow {
+        println!(
+            "note: {} is slow, so it can't be played as a reaction in a real game",
+            move_type::get_attack_name(&reaction.main)
+        );
+    }
+}
+
+fn parse_arg(args: &[String], flag: &str) -> Option<String> {
+    let flag_index = args.iter().position(|arg| arg == flag)?;
+    args.get(flag_index + 1).cloned()
+}
+
+fn report(outcomes: &[ExchangeOutcome]) {
+    let runs = outcomes.len() as f32;
+    let percent = |count: usize| 100.0 * count as f32 / runs;
+    let average = |total: i32| total as f32 / runs;
+
+    let attacker_first = outcomes.iter().filter(|o| o.attacker_first).count();
+    let defender_interrupted = outcomes
+        .iter()
+        .filter(|o| o.attacker_first && o.interrupted)
+        .count();
+    let attacker_interrupted = outcomes
+        .iter()
+        .filter(|o| !o.attacker_first && o.interrupted)
+        .count();
+    let damage_to_defender: i32 = outcomes.iter().map(|o| o.damage_to_defender).sum();
+    let damage_to_attacker: i32 = outcomes.iter().map(|o| o.damage_to_attacker).sum();
+
+    println!();
+    println!("attacker goes first   {:>6.1}%", percent(attacker_first));
+    println!(
+        "defender goes first   {:>6.1}%",
+        percent(outcomes.len() - attacker_first)
+    );
+    println!(
+        "defender interrupted  {:>6.1}%",
+        percent(defender_interrupted)
+    );
+    println!(
+        "attacker interrupted  {:>6.1}%",
+        percent(attacker_interrupted)
+    );
+    println!("damage to defender    {:>7.2}", average(damage_to_defender));
+    println!("damage to attacker    {:>7.2}", average(damage_to_attacker));
+}
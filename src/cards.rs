@@ -0,0 +1,374 @@
+use crate::{AttackTiming, AttackTrait, AttackType, RangeType};
+use rltk::RandomNumberGenerator;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+// edits to this file are picked up the next time the game starts
+// if it can't be found, the copy built into the game is used instead
+pub const CARDS_PATH: &str = "resources/cards.ron";
+const BUILTIN_CARDS: &str = include_str!("../resources/cards.ron");
+
+// along with a hash of the file it came from
+static CARDS: OnceLock<(CardList, u64)> = OnceLock::new();
+
+pub enum CardError {
+    Io(std::io::Error),
+    Format(ron::error::SpannedError),
+    Invalid { card: String, reason: String },
+    NoDrops,
+}
+
+impl fmt::Display for CardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CardError::Io(err) => write!(f, "couldn't read {}: {}", CARDS_PATH, err),
+            CardError::Format(err) => write!(f, "{}:{}", CARDS_PATH, err),
+            CardError::Invalid { card, reason } => {
+                write!(f, "{}: card \"{}\" {}", CARDS_PATH, card, reason)
+            }
+            CardError::NoDrops => write!(
+                f,
+                "{}: at least one card needs a drop_weight above 0",
+                CARDS_PATH
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct CardDef {
+    pub name: String,
+    pub range: RangeType,
+    pub shape: RangeType,
+    pub power: i32,
+    pub speed: i32,
+    pub guard: i32,
+    pub timing: AttackTiming,
+    pub traits: Vec<AttackTrait>,
+    pub rarity: i32,
+    pub drop_weight: u32,
+}
+
+#[derive(Deserialize)]
+struct CardFile {
+    starting_deck: Vec<String>,
+    cards: Vec<CardDef>,
+}
+
+struct CardList {
+    cards: Vec<CardDef>,
+    starting_deck: Vec<AttackType>,
+}
+
+/// Load the card definitions, reporting anything wrong with the card file
+/// Cards are otherwise loaded the first time they are needed, and a bad file is quietly swapped
+/// for the built-in cards then
+pub fn load_cards() -> Result<(), CardError> {
+    if CARDS.get().is_none() {
+        let cards = read_cards()?;
+        let _ = CARDS.set(cards);
+    }
+
+    Ok(())
+}
+
+fn loaded() -> &'static (CardList, u64) {
+    CARDS.get_or_init(|| {
+        read_cards()
+            .or_else(|_| {
+                parse_cards(BUILTIN_CARDS).map(|list| (list, crate::hash_data(BUILTIN_CARDS)))
+            })
+            .unwrap_or_else(|err| panic!("built-in cards are broken, {}", err))
+    })
+}
+
+fn card_list() -> &'static CardList {
+    &loaded().0
+}
+
+/// A hash of the card file that was loaded, so replays can tell when it has changed
+pub fn data_hash() -> u64 {
+    loaded().1
+}
+
+fn read_cards() -> Result<(CardList, u64), CardError> {
+    let contents = if std::path::Path::new(CARDS_PATH).exists() {
+        std::fs::read_to_string(CARDS_PATH).map_err(CardError::Io)?
+    } else {
+        BUILTIN_CARDS.to_string()
+    };
+
+    let list = parse_cards(&contents)?;
+    Ok((list, crate::hash_data(&contents)))
+}
+
+fn parse_cards(contents: &str) -> Result<CardList, CardError> {
+    let file: CardFile = ron::de::from_str(contents).map_err(CardError::Format)?;
+    let mut names = HashSet::new();
+
+    for card in file.cards.iter() {
+        let invalid = |reason: &str| CardError::Invalid {
+            card: card.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        if card.name.is_empty() {
+            return Err(invalid("has no name"));
+        }
+        if !names.insert(card.name.as_str()) {
+            return Err(invalid("is defined more than once"));
+        }
+        if card.rarity < 0 {
+            return Err(invalid("has a negative rarity"));
+        }
+        if card.traits.is_empty() {
+            return Err(invalid("doesn't do anything, it needs at least one trait"));
+        }
+
+        for att_trait in card.traits.iter() {
+            match att_trait {
+                AttackTrait::Knockback { amount }
+                | AttackTrait::Draw { amount }
+                | AttackTrait::Heal { amount }
+                | AttackTrait::Block { amount }
+                | AttackTrait::Brace { amount } => {
+                    if *amount <= 0 {
+                        return Err(invalid("has a trait with an amount below 1"));
+                    }
+                }
+                AttackTrait::Status { duration, .. } => {
+                    if *duration <= 0 {
+                        return Err(invalid("has a status with a duration below 1"));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if file.cards.iter().all(|card| card.drop_weight == 0) {
+        return Err(CardError::NoDrops);
+    }
+
+    let find = |name: &str| file.cards.iter().position(|card| card.name == name);
+    let mut starting_deck = Vec::new();
+
+    for name in file.starting_deck.iter() {
+        match find(name) {
+            None => {
+                return Err(CardError::Invalid {
+                    card: name.clone(),
+                    reason: "is in the starting deck, but isn't defined".to_string(),
+                })
+            }
+            Some(index) => starting_deck.push(AttackType(index)),
+        }
+    }
+
+    Ok(CardList {
+        cards: file.cards,
+        starting_deck,
+    })
+}
+
+pub fn get_card(attack_type: &AttackType) -> &'static CardDef {
+    &card_list().cards[attack_type.0]
+}
+
+pub fn find_card(name: &str) -> Option<AttackType> {
+    card_list()
+        .cards
+        .iter()
+        .position(|card| card.name == name)
+        .map(AttackType)
+}
+
+// for cards the code refers to directly, which have to be in the card file
+pub fn card_named(name: &str) -> AttackType {
+    find_card(name).unwrap_or_else(|| panic!("{}: card \"{}\" isn't defined", CARDS_PATH, name))
+}
+
+pub fn starting_deck() -> Vec<AttackType> {
+    card_list().starting_deck.clone()
+}
+
+// every card the player can end up holding, in the order they are defined
+pub fn player_cards() -> Vec<AttackType> {
+    let list = card_list();
+
+    (0..list.cards.len())
+        .map(AttackType)
+        .filter(|card| list.starting_deck.contains(card) || list.cards[card.0].drop_weight > 0)
+        .collect()
+}
+
+// roll for a card drop, higher quality makes rarer cards more likely
+pub fn roll_card_drop(rng: &mut RandomNumberGenerator, quality: i32) -> AttackType {
+    let cards = &card_list().cards;
+    let roll = rng.range(0 + quality, 3 + 2 * quality);
+
+    let droppable = || {
+        cards
+            .iter()
+            .enumerate()
+            .filter(|(_, card)| card.drop_weight > 0)
+    };
+
+    // take the rarest tier the roll reaches, or the most common one if the roll is too low
+    let rarity = droppable()
+        .map(|(_, card)| card.rarity)
+        .filter(|rarity| *rarity <= roll)
+        .max()
+        .or_else(|| droppable().map(|(_, card)| card.rarity).min())
+        .expect("There are no cards that can drop");
+
+    let tier: Vec<(usize, u32)> = droppable()
+        .filter(|(_, card)| card.rarity == rarity)
+        .map(|(index, card)| (index, card.drop_weight))
+        .collect();
+
+    if tier.len() == 1 {
+        return AttackType(tier[0].0);
+    }
+
+    let total: u32 = tier.iter().map(|(_, weight)| weight).sum();
+    let mut pick = rng.range(0, total);
+
+    for (index, weight) in tier.iter() {
+        if pick < *weight {
+            return AttackType(*index);
+        }
+
+        pick -= weight;
+    }
+
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(name: &str, range: &str, traits: &str) -> String {
+        format!(
+            "(name: \"{}\", range: {}, shape: Single, power: 1, speed: 0, guard: 0, \
+             timing: Fast, traits: [{}], rarity: 0, drop_weight: 1)",
+            name, range, traits
+        )
+    }
+
+    fn card_file(starting_deck: &str, cards: &[String]) -> String {
+        format!(
+            "(starting_deck: [{}], cards: [{}])",
+            starting_deck,
+            cards.join(", ")
+        )
+    }
+
+    // the message the player would see for a card file that should be turned down
+    fn rejection(contents: &str) -> String {
+        match parse_cards(contents) {
+            Ok(_) => panic!("card file should have been rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn builtin_cards_parse() {
+        if let Err(err) = parse_cards(BUILTIN_CARDS) {
+            panic!("{}", err);
+        }
+    }
+
+    #[test]
+    fn valid_file_parses() {
+        let contents = card_file(
+            "\"punch\", \"punch\"",
+            &[
+                card("punch", "Single", "Damage"),
+                card("shove", "Single", "Knockback(amount: 1)"),
+            ],
+        );
+
+        match parse_cards(&contents) {
+            Ok(list) => {
+                assert_eq!(list.cards.len(), 2);
+                assert!(list.starting_deck == vec![AttackType(0), AttackType(0)]);
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let contents = card_file(
+            "",
+            &[
+                card("punch", "Single", "Damage"),
+                card("punch", "Single", "Damage"),
+            ],
+        );
+
+        assert_eq!(
+            rejection(&contents),
+            "resources/cards.ron: card \"punch\" is defined more than once"
+        );
+    }
+
+    #[test]
+    fn trait_amounts_below_one_are_rejected() {
+        let contents = card_file("", &[card("shove", "Single", "Knockback(amount: 0)")]);
+        assert_eq!(
+            rejection(&contents),
+            "resources/cards.ron: card \"shove\" has a trait with an amount below 1"
+        );
+
+        let contents = card_file(
+            "",
+            &[card("cut", "Single", "Status(kind: Bleeding, duration: 0)")],
+        );
+        assert_eq!(
+            rejection(&contents),
+            "resources/cards.ron: card \"cut\" has a status with a duration below 1"
+        );
+    }
+
+    #[test]
+    fn cards_without_traits_are_rejected() {
+        let contents = card_file("", &[card("nothing", "Single", "")]);
+        assert_eq!(
+            rejection(&contents),
+            "resources/cards.ron: card \"nothing\" doesn't do anything, it needs at least one trait"
+        );
+    }
+
+    #[test]
+    fn undefined_starting_cards_are_rejected() {
+        let contents = card_file("\"kick\"", &[card("punch", "Single", "Damage")]);
+        assert_eq!(
+            rejection(&contents),
+            "resources/cards.ron: card \"kick\" is in the starting deck, but isn't defined"
+        );
+    }
+
+    #[test]
+    fn unknown_ranges_are_rejected() {
+        let contents = card_file("", &[card("punch", "Circle(size: 2)", "Damage")]);
+        let message = rejection(&contents);
+
+        // ron points at the line and column of the bad range
+        assert!(message.starts_with("resources/cards.ron:1:"), "{}", message);
+        assert!(message.contains("Circle"), "{}", message);
+    }
+
+    #[test]
+    fn empty_card_list_is_rejected() {
+        let contents = card_file("", &[]);
+        assert_eq!(
+            rejection(&contents),
+            "resources/cards.ron: at least one card needs a drop_weight above 0"
+        );
+    }
+}
@@ -100,6 +100,37 @@ pub fn particle_hit_color() -> RGB {
     rltk::RGB::named(rltk::RED)
 }
 
+pub fn particle_push_color() -> RGB {
+    RGB::named(rltk::LIGHTBLUE)
+}
+
+pub fn status_color(kind: crate::StatusKind) -> RGB {
+    match kind {
+        crate::StatusKind::Stunned => RGB::named(rltk::YELLOW),
+        crate::StatusKind::OffBalance => RGB::named(rltk::ORANGE),
+        crate::StatusKind::Bleeding => RGB::named(rltk::CRIMSON),
+        crate::StatusKind::Hastened => RGB::named(rltk::CYAN),
+        crate::StatusKind::Guarded => RGB::named(rltk::LIGHTBLUE),
+    }
+}
+
+pub fn behavior_color(behavior: &crate::Behavior) -> RGB {
+    match behavior {
+        crate::Behavior::Sleep => RGB::named(rltk::STEELBLUE),
+        crate::Behavior::Wander => RGB::named(rltk::WHITE),
+        crate::Behavior::Chase => RGB::named(rltk::RED),
+        crate::Behavior::Flee => RGB::named(rltk::PINK),
+    }
+}
+
+pub fn stealth_color(stealth: crate::sys_noise::Stealth) -> RGB {
+    match stealth {
+        crate::sys_noise::Stealth::Hidden => RGB::named(rltk::GREEN),
+        crate::sys_noise::Stealth::Hunted => RGB::named(rltk::ORANGE),
+        crate::sys_noise::Stealth::Seen => RGB::named(rltk::RED),
+    }
+}
+
 pub fn health_color() -> RGB {
     RGB::named(rltk::MAGENTA)
 }
@@ -107,3 +138,20 @@ pub fn health_color() -> RGB {
 pub fn map_exit_color() -> RGB {
     RGB::named(rltk::WHITE)
 }
+
+pub fn map_door_color() -> RGB {
+    RGB::from_hex("#8B5A2B").unwrap()
+}
+
+pub fn map_water_color() -> RGB {
+    RGB::from_hex("#4682B4").unwrap()
+}
+
+pub fn map_chasm_color() -> RGB {
+    RGB::from_hex("#2F3F4F").unwrap()
+}
+
+pub fn map_rubble_color() -> RGB {
+    let hsv = HSV::from_f32(0.05, 0.15, 0.45);
+    hsv.to_rgb()
+}
@@ -1,8 +1,9 @@
 use rltk::{Point, RGB};
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs::Component;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -14,17 +15,17 @@ impl Position {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Renderable {
     pub symbol: rltk::FontCharType,
     pub fg: RGB,
     pub bg: RGB,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Player;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Viewshed {
     pub visible: Vec<Point>,
     pub dirty: bool,
@@ -37,99 +38,123 @@ pub struct CanActFlag {
     pub reaction_target: Option<Entity>,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct CanReactFlag;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Schedulable {
     pub current: i32,
     pub base: i32,
     pub delta: i32,
 }
 
-#[derive(Component)]
-pub struct ParticleLifetime {
-    pub base: f32,
-    pub remaining: f32,
-    pub should_fade: bool,
-}
-
-#[derive(Component)]
-pub struct CardLifetime {
-    pub remaining: f32,
-    pub data: super::CardRequest,
-}
-
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct BlocksTile;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Health {
     pub current: i32,
     pub max: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct DeathTrigger {
     pub event: crate::EventType,
     pub range: crate::RangeType,
 }
 
-#[derive(Component, Copy, Clone)]
+#[derive(Component, Serialize, Deserialize, Copy, Clone)]
 pub struct AttackIntent {
     pub main: crate::AttackType,
     pub modifier: Option<crate::AttackType>,
     pub loc: Point,
+    // from whatever the attacker is wearing
+    #[serde(default)]
+    pub bonus: crate::StatBonus,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct MoveIntent {
     pub loc: rltk::Point,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Moveset {
     pub moves: Vec<(crate::AttackType, f32)>,
 }
 
-#[derive(Component)]
+// an enemy with this only uses the cards in its hand, instead of rolling from its moveset
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct EnemyDeck {
+    pub deck: crate::deck::Deck,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Viewable {
     pub name: String,
     pub description: Vec<String>,
     pub seen: bool,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct ViewableIndex {
     pub list_index: Option<u32>,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct AttackInProgress;
 
-#[derive(Component)]
+// used up by the next hit, whether or not it soaks up any damage
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct BlockAttack {
     pub block_amount: u32,
+    // added to guard in the stun check, if the hit is part of an exchange
+    #[serde(default)]
+    pub guard_bonus: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct AiState {
     pub status: crate::Behavior,
     pub tracking: Option<rltk::Point>,
+    // runs from the player at or below this much health, 0 never runs
+    #[serde(default)]
+    pub flee_health: i32,
+    // how loud the noise it's tracking was, anything quieter is ignored until this fades
+    #[serde(default)]
+    pub heard: f32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Heal {
     pub amount: u32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SkillChoice {
     pub choices: Vec<crate::AttackType>,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct EquipmentPickup {
+    pub item: crate::EquipmentType,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Item;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Openable;
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct StatusEffects {
+    pub effects: Vec<crate::StatusEffect>,
+}
+
+// worn by the player, so it comes along to the next level
+#[derive(Component, Serialize, Deserialize, Clone, Default)]
+pub struct Equipped {
+    pub head: Option<crate::EquipmentType>,
+    pub hands: Option<crate::EquipmentType>,
+    pub feet: Option<crate::EquipmentType>,
+}
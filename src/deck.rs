@@ -1,52 +1,71 @@
 use crate::AttackType;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-
-const HAND_LIMIT: usize = 7;
+use rltk::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Deck {
     cards: Vec<AttackType>,
     discard: Vec<AttackType>,
     pub hand: Vec<AttackType>,
     pub selected: i32,
+    hand_limit: usize,
 }
 
 impl Deck {
-    pub fn new(cards: Vec<AttackType>) -> Self {
+    pub fn new(cards: Vec<AttackType>, hand_limit: usize) -> Self {
         Deck {
             cards,
             discard: Vec::new(),
             hand: Vec::new(),
             selected: -1,
+            hand_limit,
+        }
+    }
+
+    // enemies start with their cards shuffled and a full hand
+    pub fn new_shuffled(
+        cards: Vec<AttackType>,
+        hand_limit: usize,
+        rng: &mut RandomNumberGenerator,
+    ) -> Self {
+        let mut deck = Self::new(cards, hand_limit);
+        deck.cards.shuffle(rng.get_rng());
+
+        for _ in 0..hand_limit {
+            deck.draw(rng);
         }
+
+        deck
     }
 
     pub fn new_starting_hand(ecs: &specs::World) -> Self {
         let mut rng = ecs.fetch_mut::<rltk::RandomNumberGenerator>();
+        let rules = ecs.fetch::<crate::Ruleset>();
 
         // base cards you start with
-        let mut cards = vec![AttackType::Punch, AttackType::Sweep, AttackType::Super];
+        let mut cards = crate::cards::starting_deck();
 
         // plus two random cards
         for _ in 0..2 {
-            let new_card = attack_type_table(&mut rng, 0);
+            let new_card = crate::cards::roll_card_drop(&mut rng, 0);
             cards.push(new_card);
         }
 
-        Self::new(cards)
+        Self::new(cards, rules.hand_limit)
     }
 
     pub fn add(&mut self, card: AttackType) {
         self.cards.push(card);
     }
 
-    pub fn draw(&mut self) {
-        if self.hand.len() >= HAND_LIMIT {
+    pub fn draw(&mut self, rng: &mut RandomNumberGenerator) {
+        if self.hand.len() >= self.hand_limit {
             return;
         }
 
         if self.cards.len() == 0 {
-            self.shuffle();
+            self.shuffle(rng);
         }
 
         // draw can be empty if both the discard and library are empty
@@ -66,6 +85,23 @@ impl Deck {
         self.selected = -1;
     }
 
+    // a full hand isn't cut down when the limit drops, it just won't draw until it is under again
+    pub fn change_hand_limit(&mut self, amount: i32) {
+        self.hand_limit = std::cmp::max(self.hand_limit as i32 + amount, 1) as usize;
+    }
+
+    // play a card straight from the hand, without going through selection
+    pub fn discard_card(&mut self, card: AttackType) {
+        if let Some(index) = self.hand.iter().position(|held| *held == card) {
+            let card = self.hand.remove(index);
+            self.discard.push(card);
+        }
+    }
+
+    pub fn discard_pile(&self) -> &[AttackType] {
+        &self.discard
+    }
+
     pub fn cards_remaining(&self) -> i32 {
         self.cards.len() as i32
     }
@@ -74,29 +110,12 @@ impl Deck {
         self.discard.len() as i32
     }
 
-    fn shuffle(&mut self) {
+    fn shuffle(&mut self, rng: &mut RandomNumberGenerator) {
         for card in self.discard.drain(..) {
             self.cards.push(card);
         }
 
-        self.cards.shuffle(&mut thread_rng());
-    }
-}
-
-pub fn attack_type_table(rng: &mut rltk::RandomNumberGenerator, quality: i32) -> AttackType {
-    let roll = rng.range(0 + quality, 3 + 2 * quality);
-
-    match roll {
-        0 => AttackType::Punch,
-        1 => AttackType::Sweep,
-        2 => AttackType::Super,
-        3 => AttackType::Stun,
-        4 => AttackType::Quick,
-        5 => AttackType::Push,
-        6 => AttackType::Push,
-        7 => AttackType::Dodge,
-        8 => AttackType::Dodge,
-        9 => AttackType::Ponder,
-        _ => AttackType::Recover,
+        // use the game's rng so that shuffles are reproducible from the seed
+        self.cards.shuffle(rng.get_rng());
     }
 }
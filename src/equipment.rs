@@ -0,0 +1,396 @@
+use crate::{deck::Deck, Equipped, Health, Viewshed};
+use rltk::RandomNumberGenerator;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+// like the card file, edits are picked up the next time the game starts
+pub const EQUIPMENT_PATH: &str = "resources/equipment.ron";
+const BUILTIN_EQUIPMENT: &str = include_str!("../resources/equipment.ron");
+
+// along with a hash of the file it came from
+static EQUIPMENT: OnceLock<(Vec<EquipmentDef>, u64)> = OnceLock::new();
+
+pub enum EquipmentError {
+    Io(std::io::Error),
+    Format(ron::error::SpannedError),
+    Invalid { item: String, reason: String },
+}
+
+impl fmt::Display for EquipmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EquipmentError::Io(err) => write!(f, "couldn't read {}: {}", EQUIPMENT_PATH, err),
+            EquipmentError::Format(err) => write!(f, "{}:{}", EQUIPMENT_PATH, err),
+            EquipmentError::Invalid { item, reason } => {
+                write!(f, "{}: \"{}\" {}", EQUIPMENT_PATH, item, reason)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum Slot {
+    Head,
+    Hands,
+    Feet,
+}
+
+impl Slot {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Slot::Head => "head",
+            Slot::Hands => "hands",
+            Slot::Feet => "feet",
+        }
+    }
+}
+
+/// Added to the stats of every card played by whoever has this on
+#[derive(PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct StatBonus {
+    pub power: i32,
+    pub speed: i32,
+    pub guard: i32,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct EquipmentDef {
+    pub name: String,
+    pub slot: Slot,
+    pub glyph: char,
+    pub fg: (u8, u8, u8),
+    pub description: Vec<String>,
+    #[serde(default)]
+    pub power: i32,
+    #[serde(default)]
+    pub speed: i32,
+    #[serde(default)]
+    pub guard: i32,
+    #[serde(default)]
+    pub health: i32,
+    #[serde(default)]
+    pub sight: i32,
+    #[serde(default)]
+    pub hand_size: i32,
+    pub rarity: i32,
+}
+
+impl EquipmentDef {
+    pub fn bonus(&self) -> StatBonus {
+        StatBonus {
+            power: self.power,
+            speed: self.speed,
+            guard: self.guard,
+        }
+    }
+
+    /// Short labels for everything it changes, like "POW+1"
+    pub fn labels(&self) -> Vec<String> {
+        [
+            ("POW", self.power),
+            ("SPD", self.speed),
+            ("GRD", self.guard),
+            ("HP", self.health),
+            ("SIGHT", self.sight),
+            ("HAND", self.hand_size),
+        ]
+        .iter()
+        .filter(|(_, amount)| *amount != 0)
+        .map(|(label, amount)| format!("{}{:+}", label, amount))
+        .collect()
+    }
+}
+
+/// One of the pieces of equipment defined in the equipment file
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EquipmentType(usize);
+
+// saved by name, the same as cards
+impl Serialize for EquipmentType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&get_equipment(self).name)
+    }
+}
+
+impl<'de> Deserialize<'de> for EquipmentType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        find_equipment(&name)
+            .ok_or_else(|| de::Error::custom(format!("unknown equipment \"{}\"", name)))
+    }
+}
+
+/// Load the equipment definitions, reporting anything wrong with the file
+/// Equipment is otherwise loaded the first time it is needed, and a bad file is quietly swapped
+/// for the built-in equipment then
+pub fn load_equipment() -> Result<(), EquipmentError> {
+    if EQUIPMENT.get().is_none() {
+        let equipment = read_equipment()?;
+        let _ = EQUIPMENT.set(equipment);
+    }
+
+    Ok(())
+}
+
+fn loaded() -> &'static (Vec<EquipmentDef>, u64) {
+    EQUIPMENT.get_or_init(|| {
+        read_equipment()
+            .or_else(|_| {
+                parse_equipment(BUILTIN_EQUIPMENT)
+                    .map(|list| (list, crate::hash_data(BUILTIN_EQUIPMENT)))
+            })
+            .unwrap_or_else(|err| panic!("built-in equipment is broken, {}", err))
+    })
+}
+
+fn equipment_list() -> &'static [EquipmentDef] {
+    &loaded().0
+}
+
+/// A hash of the equipment file that was loaded, so replays can tell when it has changed
+pub fn data_hash() -> u64 {
+    loaded().1
+}
+
+fn read_equipment() -> Result<(Vec<EquipmentDef>, u64), EquipmentError> {
+    let contents = if std::path::Path::new(EQUIPMENT_PATH).exists() {
+        std::fs::read_to_string(EQUIPMENT_PATH).map_err(EquipmentError::Io)?
+    } else {
+        BUILTIN_EQUIPMENT.to_string()
+    };
+
+    let list = parse_equipment(&contents)?;
+    Ok((list, crate::hash_data(&contents)))
+}
+
+fn parse_equipment(contents: &str) -> Result<Vec<EquipmentDef>, EquipmentError> {
+    let list: Vec<EquipmentDef> = ron::de::from_str(contents).map_err(EquipmentError::Format)?;
+    let mut names = HashSet::new();
+
+    for item in list.iter() {
+        let invalid = |reason: &str| EquipmentError::Invalid {
+            item: item.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        if item.name.is_empty() {
+            return Err(invalid("has no name"));
+        }
+        if !names.insert(item.name.as_str()) {
+            return Err(invalid("is defined more than once"));
+        }
+        if item.rarity < 0 {
+            return Err(invalid("has a negative rarity"));
+        }
+    }
+
+    if list.is_empty() {
+        return Err(EquipmentError::Invalid {
+            item: "equipment".to_string(),
+            reason: "needs at least one entry".to_string(),
+        });
+    }
+
+    Ok(list)
+}
+
+pub fn get_equipment(item: &EquipmentType) -> &'static EquipmentDef {
+    &equipment_list()[item.0]
+}
+
+pub fn find_equipment(name: &str) -> Option<EquipmentType> {
+    equipment_list()
+        .iter()
+        .position(|item| item.name == name)
+        .map(EquipmentType)
+}
+
+// pick something to drop, anything with a rarity at or below the quality can show up
+pub fn roll_equipment_drop(rng: &mut RandomNumberGenerator, quality: i32) -> EquipmentType {
+    let list = equipment_list();
+    let mut possible: Vec<usize> = (0..list.len())
+        .filter(|index| list[*index].rarity <= quality)
+        .collect();
+
+    // nothing is common enough, so fall back to the most common pieces
+    if possible.is_empty() {
+        let lowest = list.iter().map(|item| item.rarity).min().unwrap_or(0);
+        possible = (0..list.len())
+            .filter(|index| list[*index].rarity == lowest)
+            .collect();
+    }
+
+    let pick = rng.range(0, possible.len());
+    EquipmentType(possible[pick])
+}
+
+impl Equipped {
+    pub fn items(&self) -> Vec<EquipmentType> {
+        [self.head, self.hands, self.feet]
+            .iter()
+            .filter_map(|item| *item)
+            .collect()
+    }
+
+    pub fn in_slot(&self, slot: Slot) -> Option<EquipmentType> {
+        match slot {
+            Slot::Head => self.head,
+            Slot::Hands => self.hands,
+            Slot::Feet => self.feet,
+        }
+    }
+
+    /// Put on a piece, handing back whatever was in its slot before
+    pub fn equip(&mut self, item: EquipmentType) -> Option<EquipmentType> {
+        let slot = match get_equipment(&item).slot {
+            Slot::Head => &mut self.head,
+            Slot::Hands => &mut self.hands,
+            Slot::Feet => &mut self.feet,
+        };
+
+        slot.replace(item)
+    }
+
+    pub fn bonus(&self) -> StatBonus {
+        let mut total = StatBonus::default();
+
+        for item in self.items() {
+            let bonus = get_equipment(&item).bonus();
+            total.power += bonus.power;
+            total.speed += bonus.speed;
+            total.guard += bonus.guard;
+        }
+
+        total
+    }
+}
+
+// the card bonuses are read off whatever is worn when a card is played, but the rest has to be
+// added when a piece goes on and taken back off when it comes off
+pub fn put_on(
+    item: EquipmentType,
+    health: Option<&mut Health>,
+    viewshed: Option<&mut Viewshed>,
+    deck: Option<&mut Deck>,
+) {
+    change_passives(item, 1, health, viewshed, deck);
+}
+
+pub fn take_off(
+    item: EquipmentType,
+    health: Option<&mut Health>,
+    viewshed: Option<&mut Viewshed>,
+    deck: Option<&mut Deck>,
+) {
+    change_passives(item, -1, health, viewshed, deck);
+}
+
+fn change_passives(
+    item: EquipmentType,
+    sign: i32,
+    health: Option<&mut Health>,
+    viewshed: Option<&mut Viewshed>,
+    deck: Option<&mut Deck>,
+) {
+    let def = get_equipment(&item);
+
+    if def.health != 0 {
+        if let Some(health) = health {
+            health.max = std::cmp::max(health.max + sign * def.health, 1);
+            // taking something off never kills outright
+            health.current = std::cmp::max(health.current + sign * def.health, 1);
+            health.current = std::cmp::min(health.current, health.max);
+        }
+    }
+
+    if def.sight != 0 {
+        if let Some(viewshed) = viewshed {
+            viewshed.range = std::cmp::max(viewshed.range + sign * def.sight, 1);
+            viewshed.dirty = true;
+        }
+    }
+
+    if let Some(deck) = deck {
+        deck.change_hand_limit(sign * def.hand_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, slot: &str, rarity: i32) -> String {
+        format!(
+            "(name: \"{}\", slot: {}, glyph: '(', fg: (0, 0, 0), description: [], rarity: {})",
+            name, slot, rarity
+        )
+    }
+
+    // the message the player would see for an equipment file that should be turned down
+    fn rejection(contents: &str) -> String {
+        match parse_equipment(contents) {
+            Ok(_) => panic!("equipment file should have been rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn builtin_equipment_parses() {
+        if let Err(err) = parse_equipment(BUILTIN_EQUIPMENT) {
+            panic!("{}", err);
+        }
+    }
+
+    #[test]
+    fn valid_file_parses() {
+        let contents = format!(
+            "[{}, {}]",
+            item("wraps", "Hands", 0),
+            item("cap", "Head", 2)
+        );
+
+        match parse_equipment(&contents) {
+            Ok(list) => {
+                assert_eq!(list.len(), 2);
+                assert_eq!(list[1].name, "cap");
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn unknown_slots_are_rejected() {
+        let contents = format!("[{}]", item("belt", "Waist", 0));
+        let message = rejection(&contents);
+
+        // ron points at the line and column of the bad slot
+        assert!(
+            message.starts_with("resources/equipment.ron:1:"),
+            "{}",
+            message
+        );
+        assert!(message.contains("Waist"), "{}", message);
+    }
+
+    #[test]
+    fn bad_entries_are_rejected() {
+        let contents = format!("[{}, {}]", item("cap", "Head", 0), item("cap", "Head", 1));
+        assert_eq!(
+            rejection(&contents),
+            "resources/equipment.ron: \"cap\" is defined more than once"
+        );
+
+        let contents = format!("[{}]", item("cap", "Head", -1));
+        assert_eq!(
+            rejection(&contents),
+            "resources/equipment.ron: \"cap\" has a negative rarity"
+        );
+
+        assert_eq!(
+            rejection("[]"),
+            "resources/equipment.ron: \"equipment\" needs at least one entry"
+        );
+    }
+}
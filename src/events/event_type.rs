@@ -1,33 +1,55 @@
-use crate::ParticleRequest;
+use crate::sys_noise::{make_noise, NoiseKind, NoiseQueue};
+use crate::{ParticleRequest, StatusKind, TileType};
 use rltk::{Algorithm2D, Point};
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 
 const PARTICLE_HIT_LIFETIME: f32 = 600.0;
+const PARTICLE_PUSH_LIFETIME: f32 = 300.0;
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum DropType {
     Skill,
     Health,
+    Equipment,
 }
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum EventType {
-    Damage { amount: i32 },
-    Push { source_pos: Point, amount: i32 },
+    // the status sticks to anything the hit lands on
+    Damage {
+        amount: i32,
+        status: Option<(StatusKind, i32)>,
+    },
+    Push {
+        source_pos: Point,
+        amount: i32,
+    },
     Movement,
-    ParticleSpawn { request: ParticleRequest },
-    ItemDrop { drop_type: DropType, quality: i32 },
+    // only does anything when it beats an attack in an exchange
+    Parry,
+    ParticleSpawn {
+        request: ParticleRequest,
+    },
+    ItemDrop {
+        drop_type: DropType,
+        quality: i32,
+    },
     // ShowCard { request: CardRequest, offset: i32 },
 }
 
-pub fn get_resolver(event: &EventType) -> Box<dyn EventResolver + Send> {
+pub fn get_resolver(event: &EventType) -> Box<dyn EventResolver + Send + Sync> {
     match event {
-        EventType::Damage { amount } => Box::new(DamageResolver { amount: *amount }),
+        EventType::Damage { amount, status } => Box::new(DamageResolver {
+            amount: *amount,
+            status: *status,
+        }),
         EventType::Push { source_pos, amount } => Box::new(PushResolver {
             source_pos: *source_pos,
             amount: *amount,
         }),
         EventType::Movement => Box::new(MovementResolver),
+        EventType::Parry => Box::new(ParryResolver),
         EventType::ItemDrop { drop_type, quality } => Box::new(DropResolver {
             drop_type: *drop_type,
             quality: *quality,
@@ -42,20 +64,41 @@ pub trait EventResolver {
 
 pub struct DamageResolver {
     amount: i32,
+    status: Option<(StatusKind, i32)>,
 }
 
 impl EventResolver for DamageResolver {
-    fn resolve(&self, world: &mut World, _source: Option<Entity>, targets: Vec<Point>) {
-        for pos in targets.iter() {
-            super::add_particle_event(*pos, crate::particle_hit_color(), PARTICLE_HIT_LIFETIME);
+    fn resolve(&self, world: &mut World, source: Option<Entity>, targets: Vec<Point>) {
+        {
+            let mut queue = world.fetch_mut::<super::EventQueue>();
+            for pos in targets.iter() {
+                super::add_particle_event(
+                    &mut queue,
+                    *pos,
+                    crate::particle_hit_color(),
+                    PARTICLE_HIT_LIFETIME,
+                );
+            }
         }
 
         let affected = super::get_affected_entities(world, &targets);
+        let player = *world.fetch::<Entity>();
+        let positions = world.read_storage::<crate::Position>();
+        let mut ai_states = world.write_storage::<crate::AiState>();
+        let viewables = world.read_storage::<crate::Viewable>();
         let mut healths = world.write_storage::<crate::Health>();
         let mut blocks = world.write_storage::<crate::BlockAttack>();
+        let scheds = world.read_storage::<crate::Schedulable>();
+        let mut statuses = world.write_storage::<crate::StatusEffects>();
+        let mut intents = world.fetch_mut::<crate::IntentData>();
+        let mut log = world.fetch_mut::<crate::gamelog::GameLog>();
 
         for e_aff in affected.iter() {
             let mut damage_amount = self.amount;
+            // bracing only helps against the stun, it doesn't stop anything landing
+            let blocked = blocks
+                .get(*e_aff)
+                .map_or(false, |block| block.block_amount > 0);
             if let Some(block) = blocks.get(*e_aff) {
                 damage_amount -= block.block_amount as i32;
                 damage_amount = std::cmp::max(damage_amount, 0);
@@ -63,14 +106,75 @@ impl EventResolver for DamageResolver {
                 blocks.remove(*e_aff);
             };
 
+            // let the cards show that the block did something
+            if damage_amount < self.amount {
+                if *e_aff == player {
+                    intents.incoming_blocked = true;
+                } else if source == Some(player) {
+                    intents.outgoing_blocked = true;
+                }
+            }
+
+            // a blocked hit doesn't leave anything behind, and only things that take turns can
+            // wait out an effect
+            if let Some((kind, duration)) = self.status {
+                if !blocked && scheds.contains(*e_aff) {
+                    crate::status::inflict(&mut statuses, *e_aff, kind, duration);
+                }
+            }
+
             let affected = healths.get_mut(*e_aff);
             if let Some(mut affected) = affected {
+                let was_alive = affected.current > 0;
                 affected.current -= damage_amount;
+
+                if *e_aff == player && was_alive && affected.current <= 0 {
+                    // only death triggers deal damage without a source
+                    let killer = match source.and_then(|source| viewables.get(source)) {
+                        Some(viewable) => viewable.name.clone(),
+                        None => "an explosion".to_string(),
+                    };
+
+                    log.entries.push(format!("You were killed by {}", killer));
+                    log.killed_by = Some(killer);
+                }
+
+                // getting hit wakes anything up, and it goes after whoever hit it, or runs from
+                // them if the hit left it badly hurt
+                if let Some(state) = ai_states.get_mut(*e_aff) {
+                    if let Some(hit_pos) = positions.get(*e_aff) {
+                        state.wake(hit_pos.as_point());
+                    }
+
+                    if let Some(source_pos) = source.and_then(|source| positions.get(source)) {
+                        state.tracking = Some(source_pos.as_point());
+                    }
+
+                    if affected.current > 0 && affected.current <= state.flee_health {
+                        state.status = crate::Behavior::Flee;
+                    }
+                }
             }
         }
     }
 }
 
+pub struct ParryResolver;
+
+impl EventResolver for ParryResolver {
+    fn resolve(&self, world: &mut World, _source: Option<Entity>, targets: Vec<Point>) {
+        let mut queue = world.fetch_mut::<super::EventQueue>();
+        for pos in targets.iter() {
+            super::add_particle_event(
+                &mut queue,
+                *pos,
+                crate::card_blocked_color(),
+                PARTICLE_HIT_LIFETIME,
+            );
+        }
+    }
+}
+
 pub struct ParticleResolver {
     request: ParticleRequest,
 }
@@ -88,53 +192,153 @@ pub struct PushResolver {
 }
 
 impl EventResolver for PushResolver {
-    fn resolve(&self, world: &mut World, _source: Option<Entity>, targets: Vec<Point>) {
-        for pos in targets.iter() {
-            super::add_particle_event(*pos, crate::particle_hit_color(), PARTICLE_HIT_LIFETIME);
+    fn resolve(&self, world: &mut World, source: Option<Entity>, targets: Vec<Point>) {
+        {
+            let mut queue = world.fetch_mut::<super::EventQueue>();
+            for pos in targets.iter() {
+                super::add_particle_event(
+                    &mut queue,
+                    *pos,
+                    crate::particle_hit_color(),
+                    PARTICLE_HIT_LIFETIME,
+                );
+            }
         }
 
         let affected = super::get_affected_entities(world, &targets);
-        let mut positions = world.write_storage::<crate::Position>();
-        let mut map = world.fetch_mut::<crate::Map>();
+        // where something got slammed into something else, and how hard
+        let mut impacts = Vec::new();
+        let mut path = Vec::new();
+        let mut fallen = Vec::new();
+
+        {
+            let mut positions = world.write_storage::<crate::Position>();
+            let mut viewsheds = world.write_storage::<crate::Viewshed>();
+            let mut map = world.fetch_mut::<crate::Map>();
+
+            for e_aff in affected.iter() {
+                let affected = match positions.get_mut(*e_aff) {
+                    None => continue,
+                    Some(affected) => affected,
+                };
+
+                // only creatures get pushed around, not items lying on the floor
+                let start = affected.as_point();
+                if map.creature_map.get(&map.point2d_to_index(start)) != Some(e_aff) {
+                    continue;
+                }
 
-        for e_aff in affected.iter() {
-            let affected = positions.get_mut(*e_aff);
-            if let Some(mut affected) = affected {
                 // find the closest direction to push
                 let dx = i32::signum(affected.x - self.source_pos.x);
                 let dy = i32::signum(affected.y - self.source_pos.y);
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
 
-                let mut next_x = affected.x;
-                let mut next_y = affected.y;
+                let mut curr = start;
 
                 // push along the direction until we hit something else
-                for _ in 0..self.amount {
-                    let possible = Point::new(next_x + dx, next_y + dy);
+                for pushed in 0..self.amount {
+                    let remaining = self.amount - pushed;
+                    let possible = Point::new(curr.x + dx, curr.y + dy);
 
                     if !map.in_bounds(possible) {
+                        impacts.push((curr, remaining));
                         break;
                     }
 
                     let possible_index = map.point2d_to_index(possible);
-                    if map.blocked_tiles[possible_index] {
+                    if map.creature_map.contains_key(&possible_index) {
+                        // both of them take the hit
+                        impacts.push((curr, remaining));
+                        impacts.push((possible, remaining));
                         break;
                     }
 
-                    next_x = possible.x;
-                    next_y = possible.y;
+                    let tile = map.tiles[possible_index];
+                    if tile == TileType::Chasm {
+                        curr = possible;
+                        path.push(curr);
+                        fallen.push(*e_aff);
+                        break;
+                    }
+
+                    if tile.blocks_movement() || tile == TileType::Door {
+                        impacts.push((curr, remaining));
+                        break;
+                    }
+
+                    curr = possible;
+                    path.push(curr);
                 }
 
-                // fix indexing
-                map.move_creature(
-                    *e_aff,
-                    Point::new(affected.x, affected.y),
-                    Point::new(next_y, next_y),
+                if curr != start {
+                    map.move_creature(*e_aff, start, curr);
+                    affected.x = curr.x;
+                    affected.y = curr.y;
+
+                    if let Some(viewshed) = viewsheds.get_mut(*e_aff) {
+                        viewshed.dirty = true;
+                    }
+                }
+            }
+        }
+
+        {
+            let mut queue = world.fetch_mut::<super::EventQueue>();
+            let mut noises = world.fetch_mut::<NoiseQueue>();
+            for (point, amount) in impacts {
+                make_noise(&mut noises, NoiseKind::Slam, point, None);
+                super::add_event(
+                    &mut queue,
+                    &EventType::Damage {
+                        amount,
+                        status: None,
+                    },
+                    None,
+                    source,
+                    &super::RangeType::Single,
+                    point,
+                    false,
                 );
+            }
 
-                affected.x = next_x;
-                affected.y = next_y;
+            for point in path {
+                super::add_particle_event(
+                    &mut queue,
+                    point,
+                    crate::particle_push_color(),
+                    PARTICLE_PUSH_LIFETIME,
+                );
             }
         }
+
+        for victim in fallen {
+            fall_into_chasm(world, victim);
+        }
+    }
+}
+
+// nothing comes back out of a chasm
+fn fall_into_chasm(world: &mut World, victim: Entity) {
+    let player = *world.fetch::<Entity>();
+    let viewables = world.read_storage::<crate::Viewable>();
+    let mut healths = world.write_storage::<crate::Health>();
+    let mut log = world.fetch_mut::<crate::gamelog::GameLog>();
+
+    if let Some(mut health) = healths.get_mut(victim) {
+        let was_alive = health.current > 0;
+        health.current = 0;
+
+        if victim == player {
+            if was_alive {
+                log.entries.push("You fell into a chasm".to_string());
+                log.killed_by = Some("a fall".to_string());
+            }
+        } else if let Some(viewable) = viewables.get(victim) {
+            log.entries
+                .push(format!("The {} fell into a chasm", viewable.name));
+        }
     }
 }
 
@@ -145,6 +349,7 @@ impl EventResolver for MovementResolver {
         if let Some(source) = source {
             let mut positions = world.write_storage::<crate::Position>();
             let mut map = world.fetch_mut::<crate::Map>();
+            let mut noises = world.fetch_mut::<NoiseQueue>();
 
             if let Some(source_pos) = positions.get_mut(source) {
                 // if we have more than one target position to move to, pick at random
@@ -166,6 +371,7 @@ impl EventResolver for MovementResolver {
 
                     source_pos.x = target.x;
                     source_pos.y = target.y;
+                    make_noise(&mut noises, NoiseKind::Step, target, Some(source));
                 }
             }
         }
@@ -206,7 +412,7 @@ impl EventResolver for DropResolver {
 
                     // generate 3 choices by default
                     for _ in 0..3 {
-                        skill_ary.push(crate::deck::attack_type_table(&mut rng, self.quality));
+                        skill_ary.push(crate::cards::roll_card_drop(&mut rng, self.quality));
                     }
 
                     skill_ary
@@ -217,6 +423,17 @@ impl EventResolver for DropResolver {
                 let mut map = world.fetch_mut::<crate::Map>();
                 map.track_item(book_item, drop_point);
             }
+            DropType::Equipment => {
+                let item = {
+                    let mut rng = world.fetch_mut::<rltk::RandomNumberGenerator>();
+                    crate::equipment::roll_equipment_drop(&mut rng, self.quality)
+                };
+
+                let equipment_item =
+                    crate::spawner::build_equipment_pickup(world, drop_point, item);
+                let mut map = world.fetch_mut::<crate::Map>();
+                map.track_item(equipment_item, drop_point);
+            }
         }
     }
 }
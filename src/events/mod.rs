@@ -1,8 +1,11 @@
 use super::{AttackIntent, CardRequest};
 use crate::move_type;
+use crate::saveload::SaveError;
 use rltk::Point;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::Arc;
 
 mod event_type;
 pub mod range_type;
@@ -10,28 +13,214 @@ pub mod range_type;
 pub use event_type::{DropType, EventType};
 pub use range_type::*;
 
-const ATK_SPD_BONUS: i32 = 0;
-const DEF_GUARD_BONUS: i32 = 1;
-
-const SPEED_ROLL_RANGE: i32 = 6;
-const GUARD_ROLL_RANGE: i32 = 6;
+/// Everything waiting to happen in the world
+pub struct EventQueue {
+    stack: Vec<Event>,
+    // an exchange that was interrupted to let the player react
+    processing: Option<PendingExchange>,
+    // attacks headed at the player, waiting to be shown
+    pub cards: Vec<CardRequest>,
+}
 
-lazy_static! {
-    static ref STACK: Mutex<Vec<Event>> = Mutex::new(Vec::new());
-    static ref PROCESSING: Mutex<Option<Event>> = Mutex::new(None);
-    pub static ref CARDSTACK: Mutex<Vec<CardRequest>> = Mutex::new(Vec::new());
+impl EventQueue {
+    pub fn new() -> Self {
+        EventQueue {
+            stack: Vec::new(),
+            processing: None,
+            cards: Vec::new(),
+        }
+    }
 }
 
+/// Rolls to use instead of the dice, for tools that need to walk through every possible exchange
+/// A real game never has one of these
+pub struct FixedRolls(pub crate::IntentRolls);
+
 struct Event {
     event_type: EventType,
     attack_intent: Option<AttackIntent>,
-    resolver: Box<dyn event_type::EventResolver + Send>,
+    resolver: Box<dyn event_type::EventResolver + Send + Sync>,
     source: Option<Entity>,
     target_tiles: Arc<Vec<Point>>,
     invokes_reaction: bool,
 }
 
+// events refer to entities by their index in the save file
+#[derive(Serialize, Deserialize)]
+struct SavedEvent {
+    event_type: EventType,
+    attack_intent: Option<AttackIntent>,
+    source: Option<usize>,
+    target_tiles: Vec<Point>,
+    invokes_reaction: bool,
+}
+
+// one card in an exchange, along with the card it was played in answer to
+struct Link {
+    event: Event,
+    reacting_to: Option<usize>,
+}
+
+struct PendingExchange {
+    links: Vec<Link>,
+    // the link the player is answering
+    reacting_to: usize,
+    // how big the stack was when the player was asked, everything above it is their answer
+    stack_size: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedLink {
+    event: SavedEvent,
+    reacting_to: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedExchange {
+    links: Vec<SavedLink>,
+    reacting_to: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedCard {
+    attack_intent: AttackIntent,
+    source: Option<usize>,
+    offset: i32,
+    affected: Vec<Point>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedEvents {
+    stack: Vec<SavedEvent>,
+    processing: Option<SavedExchange>,
+    cards: Vec<SavedCard>,
+}
+
+impl Event {
+    fn save(&self, entity_ids: &HashMap<Entity, usize>) -> SavedEvent {
+        SavedEvent {
+            event_type: self.event_type,
+            attack_intent: self.attack_intent,
+            source: self.source.and_then(|ent| entity_ids.get(&ent).copied()),
+            target_tiles: self.target_tiles.to_vec(),
+            invokes_reaction: self.invokes_reaction,
+        }
+    }
+
+    fn load(saved: SavedEvent, entities: &[Entity]) -> Result<Self, SaveError> {
+        Ok(Event {
+            event_type: saved.event_type,
+            attack_intent: saved.attack_intent,
+            resolver: event_type::get_resolver(&saved.event_type),
+            source: load_entity(saved.source, entities)?,
+            target_tiles: Arc::new(saved.target_tiles),
+            invokes_reaction: saved.invokes_reaction,
+        })
+    }
+}
+
+// snapshot the pending events, including an exchange that is waiting on a reaction
+pub fn save_events(queue: &EventQueue, entity_ids: &HashMap<Entity, usize>) -> SavedEvents {
+    SavedEvents {
+        stack: queue
+            .stack
+            .iter()
+            .map(|event| event.save(entity_ids))
+            .collect(),
+        processing: queue.processing.as_ref().map(|pending| SavedExchange {
+            links: pending
+                .links
+                .iter()
+                .map(|link| SavedLink {
+                    event: link.event.save(entity_ids),
+                    reacting_to: link.reacting_to,
+                })
+                .collect(),
+            reacting_to: pending.reacting_to,
+        }),
+        cards: queue
+            .cards
+            .iter()
+            .map(|card| SavedCard {
+                attack_intent: card.attack_intent,
+                source: card.source.and_then(|ent| entity_ids.get(&ent).copied()),
+                offset: card.offset,
+                affected: card.affected.to_vec(),
+            })
+            .collect(),
+    }
+}
+
+// an entity the save refers to, which has to be one of the saved entities
+fn load_entity(index: Option<usize>, entities: &[Entity]) -> Result<Option<Entity>, SaveError> {
+    match index {
+        None => Ok(None),
+        Some(index) => match entities.get(index) {
+            Some(ent) => Ok(Some(*ent)),
+            None => Err(SaveError::MissingEntity(index)),
+        },
+    }
+}
+
+// rebuild the pending events from a save
+pub fn load_events(saved: SavedEvents, entities: &[Entity]) -> Result<EventQueue, SaveError> {
+    let stack: Vec<Event> = saved
+        .stack
+        .into_iter()
+        .map(|event| Event::load(event, entities))
+        .collect::<Result<_, _>>()?;
+
+    let processing = match saved.processing {
+        None => None,
+        Some(pending) => {
+            let mut links = Vec::with_capacity(pending.links.len());
+            for (index, link) in pending.links.into_iter().enumerate() {
+                // a card can only answer one that was played before it
+                if link.reacting_to.map_or(false, |answered| answered >= index) {
+                    return Err(SaveError::MissingLink(link.reacting_to.unwrap()));
+                }
+
+                links.push(Link {
+                    event: Event::load(link.event, entities)?,
+                    reacting_to: link.reacting_to,
+                });
+            }
+
+            if pending.reacting_to >= links.len() {
+                return Err(SaveError::MissingLink(pending.reacting_to));
+            }
+
+            // the game is only ever saved before the player answers
+            Some(PendingExchange {
+                links,
+                reacting_to: pending.reacting_to,
+                stack_size: stack.len(),
+            })
+        }
+    };
+
+    let cards = saved
+        .cards
+        .into_iter()
+        .map(|card| {
+            Ok(CardRequest {
+                attack_intent: card.attack_intent,
+                source: load_entity(card.source, entities)?,
+                offset: card.offset,
+                affected: Arc::new(card.affected),
+            })
+        })
+        .collect::<Result<_, SaveError>>()?;
+
+    Ok(EventQueue {
+        stack,
+        processing,
+        cards,
+    })
+}
+
 pub fn add_event(
+    queue: &mut EventQueue,
     event_type: &EventType,
     intent: Option<AttackIntent>,
     source: Option<Entity>,
@@ -39,7 +228,6 @@ pub fn add_event(
     loc: Point,
     invokes_reaction: bool,
 ) {
-    let mut stack = STACK.lock().expect("Failed to lock STACK");
     let event = Event {
         event_type: *event_type,
         attack_intent: intent,
@@ -49,14 +237,25 @@ pub fn add_event(
         invokes_reaction,
     };
 
-    stack.push(event);
+    queue.stack.push(event);
 }
 
-pub fn add_damage_event(intent: &AttackIntent, source: Option<Entity>, invokes_reaction: bool) {
-    let mut stack = STACK.lock().expect("Failed to lock STACK");
-
+pub fn add_damage_event(
+    queue: &mut EventQueue,
+    intent: &AttackIntent,
+    source: Option<Entity>,
+    invokes_reaction: bool,
+) {
+    // only the first status on the intent rides along with the hit
+    let status = move_type::get_intent_traits(intent)
+        .into_iter()
+        .find_map(|att_trait| match att_trait {
+            crate::AttackTrait::Status { kind, duration } => Some((kind, duration)),
+            _ => None,
+        });
     let damage_event = EventType::Damage {
         amount: move_type::get_intent_power(intent),
+        status,
     };
     let range = &move_type::get_attack_shape(&intent.main);
     let resolver = event_type::get_resolver(&damage_event);
@@ -70,11 +269,17 @@ pub fn add_damage_event(intent: &AttackIntent, source: Option<Entity>, invokes_r
         invokes_reaction,
     };
 
-    stack.push(event);
+    queue.stack.push(event);
 }
 
-pub fn add_particle_event(position: Point, color: rltk::RGB, lifetime: f32) {
+pub fn add_particle_event(
+    queue: &mut EventQueue,
+    position: Point,
+    color: rltk::RGB,
+    lifetime: f32,
+) {
     add_event(
+        queue,
         &EventType::ParticleSpawn {
             request: crate::ParticleRequest {
                 position,
@@ -92,21 +297,42 @@ pub fn add_particle_event(position: Point, color: rltk::RGB, lifetime: f32) {
 }
 
 pub fn process_stack(ecs: &mut World) -> crate::RunState {
-    // if we have an event that was interrupted, resume it
-    // also we need to wrap this mutex since we try to lock it again later
-    let mut processing = PROCESSING.lock().expect("Failed to lock PROCESSING");
-    let stashed_event = processing.take();
+    // if we have an exchange that was waiting on the player, pick it back up
+    let stashed = ecs.fetch_mut::<EventQueue>().processing.take();
 
-    *processing = None;
+    if let Some(pending) = stashed {
+        let mut links = pending.links;
 
-    if let Some(event) = stashed_event {
-        // the stashed event is no longer in-progress
-        if let Some(event_ent) = event.source {
+        // the card being answered is no longer in-progress
+        if let Some(event_ent) = links[pending.reacting_to].event.source {
             let mut in_progress = ecs.write_storage::<crate::AttackInProgress>();
             in_progress.remove(event_ent);
         }
 
-        process_event(ecs, event);
+        // the player's reaction is everything their card put on the stack, cards like block
+        // take effect when played and don't put anything there
+        let player = *ecs.fetch::<Entity>();
+        let reaction = {
+            let mut queue = ecs.fetch_mut::<EventQueue>();
+            let added = queue.stack.split_off(pending.stack_size);
+            let (reaction, others): (Vec<Event>, Vec<Event>) = added
+                .into_iter()
+                .partition(|event| event.source == Some(player) && event.attack_intent.is_some());
+            queue.stack.extend(others);
+            reaction
+        };
+
+        for event in reaction {
+            links.push(Link {
+                event,
+                reacting_to: Some(pending.reacting_to),
+            });
+        }
+
+        match gather_reactions(ecs, links, pending.reacting_to + 1) {
+            None => return crate::RunState::AwaitingInput,
+            Some(links) => settle_links(ecs, links),
+        }
 
         return crate::RunState::HitPause {
             remaining_time: 600.0,
@@ -114,7 +340,7 @@ pub fn process_stack(ecs: &mut World) -> crate::RunState {
     }
 
     loop {
-        let event = STACK.lock().expect("Failed to lock STACK").pop();
+        let event = ecs.fetch_mut::<EventQueue>().stack.pop();
         match event {
             None => return crate::RunState::Running,
             Some(event) => {
@@ -125,7 +351,7 @@ pub fn process_stack(ecs: &mut World) -> crate::RunState {
                     // TODO, exploding barrels don't let you react properly (even without this branch)
                     process_event(ecs, event);
                 } else {
-                    let mut entities_hit = get_affected_entities(ecs, &event.target_tiles);
+                    let entities_hit = get_affected_entities(ecs, &event.target_tiles);
 
                     if let Some(intent) = &event.attack_intent {
                         add_card_to_stack(
@@ -137,45 +363,26 @@ pub fn process_stack(ecs: &mut World) -> crate::RunState {
                         );
                     }
 
-                    entities_hit.retain(|ent| entity_can_react(ecs, event.source, ent));
-
-                    // check if there are entities that can respond
-                    if event.invokes_reaction && !entities_hit.is_empty() {
-                        let mut can_act = ecs.write_storage::<super::CanActFlag>();
-                        let mut scheds = ecs.write_storage::<super::Schedulable>();
-
-                        for entity in entities_hit {
-                            // if this entity is going to act, refund their time cost
-                            if can_act.get(entity).is_some() {
-                                let mut sched = scheds.get_mut(entity).unwrap();
-                                sched.current -= sched.base;
-                            }
-
-                            can_act
-                                .insert(
-                                    entity,
-                                    super::CanActFlag {
-                                        is_reaction: true,
-                                        reaction_target: event.source,
-                                    },
-                                )
-                                .expect("Failed to insert CanActFlag");
-                        }
+                    let links = vec![Link {
+                        event,
+                        reacting_to: None,
+                    }];
 
-                        // this event is now in-progress if it came from an entity
-                        if let Some(event_ent) = event.source {
-                            let mut in_progress = ecs.write_storage::<crate::AttackInProgress>();
-                            in_progress
-                                .insert(event_ent, crate::AttackInProgress)
-                                .expect("couldn't mark event as in progress");
+                    match gather_reactions(ecs, links, 0) {
+                        // stash the exchange and return control to the main loop
+                        None => return crate::RunState::AwaitingInput,
+                        Some(mut links) if links.len() == 1 => {
+                            // nobody answered, so resolve the event
+                            let link = links.pop().unwrap();
+                            process_event(ecs, link.event);
                         }
-                        // stash the current event and return control to the main loop
-                        *processing = Some(event);
+                        Some(links) => {
+                            settle_links(ecs, links);
 
-                        return crate::RunState::AwaitingInput;
-                    } else {
-                        // otherwise resolve the event
-                        process_event(ecs, event);
+                            return crate::RunState::HitPause {
+                                remaining_time: 600.0,
+                            };
+                        }
                     }
                 }
             }
@@ -183,13 +390,96 @@ pub fn process_stack(ecs: &mut World) -> crate::RunState {
     }
 }
 
+// give everyone caught up in an exchange the chance to answer the cards in it, starting from the
+// given link, and hand the whole chain back once nobody else can react
+// returns None if the exchange has been stashed to wait on the player
+fn gather_reactions(ecs: &mut World, mut links: Vec<Link>, from: usize) -> Option<Vec<Link>> {
+    let player = *ecs.fetch::<Entity>();
+    let mut next = from;
+
+    while next < links.len() {
+        let event = &links[next].event;
+        let source = event.source;
+
+        if !event.invokes_reaction {
+            next += 1;
+            continue;
+        }
+
+        // nobody gets to react twice in the same exchange
+        let mut reactors = get_affected_entities(ecs, &event.target_tiles);
+        reactors.retain(|ent| {
+            entity_can_react(ecs, source, ent)
+                && !links.iter().any(|link| link.event.source == Some(*ent))
+        });
+
+        if source == Some(player) {
+            // counter fighters answer the player straight away, instead of waiting on the stack
+            for ent in reactors {
+                let counter = play_counter(ecs, ent, player);
+                if let Some(first) = counter.first() {
+                    show_counter(ecs, first);
+                }
+
+                for event in counter {
+                    links.push(Link {
+                        event,
+                        reacting_to: Some(next),
+                    });
+                }
+            }
+        } else if reactors.contains(&player) {
+            {
+                let mut can_act = ecs.write_storage::<super::CanActFlag>();
+                let mut scheds = ecs.write_storage::<super::Schedulable>();
+
+                // if the player is going to act, refund their time cost
+                if can_act.get(player).is_some() {
+                    let mut sched = scheds.get_mut(player).unwrap();
+                    sched.current -= sched.base;
+                }
+
+                can_act
+                    .insert(
+                        player,
+                        super::CanActFlag {
+                            is_reaction: true,
+                            reaction_target: source,
+                        },
+                    )
+                    .expect("Failed to insert CanActFlag");
+            }
+
+            // this event is now in-progress if it came from an entity
+            // the source may have died while the event waited on the stack
+            let source = source.filter(|ent| ecs.entities().is_alive(*ent));
+            if let Some(event_ent) = source {
+                let mut in_progress = ecs.write_storage::<crate::AttackInProgress>();
+                in_progress
+                    .insert(event_ent, crate::AttackInProgress)
+                    .expect("couldn't mark event as in progress");
+            }
+
+            let mut queue = ecs.fetch_mut::<EventQueue>();
+            queue.processing = Some(PendingExchange {
+                links,
+                reacting_to: next,
+                stack_size: queue.stack.len(),
+            });
+
+            return None;
+        }
+
+        next += 1;
+    }
+
+    Some(links)
+}
+
 // TODO: graphical effects need to be unentangled from the stack
 pub fn process_stack_visual_only(ecs: &mut World) -> bool {
     loop {
-        let event = {
-            let mut stack = STACK.lock().expect("Failed to lock STACK");
-            stack.pop()
-        };
+        let event = ecs.fetch_mut::<EventQueue>().stack.pop();
 
         match event {
             None => {
@@ -200,7 +490,7 @@ pub fn process_stack_visual_only(ecs: &mut World) -> bool {
                     process_event(ecs, event);
                 }
                 _ => {
-                    STACK.lock().expect("Failed to lock STACK").push(event);
+                    ecs.fetch_mut::<EventQueue>().stack.push(event);
                     return false;
                 }
             },
@@ -226,7 +516,11 @@ fn get_affected_entities(ecs: &mut World, targets: &Vec<Point>) -> Vec<Entity> {
 
 fn entity_can_react(ecs: &mut World, source: Option<Entity>, target: &Entity) -> bool {
     let react_storage = ecs.read_storage::<super::CanReactFlag>();
-    let can_react = react_storage.get(*target).is_some();
+    let statuses = ecs.read_storage::<crate::StatusEffects>();
+    let stunned = statuses
+        .get(*target)
+        .map_or(false, |status| status.has(crate::StatusKind::Stunned));
+    let can_react = react_storage.get(*target).is_some() && !stunned;
 
     match source {
         None => can_react,
@@ -240,173 +534,365 @@ fn entity_can_react(ecs: &mut World, source: Option<Entity>, target: &Entity) ->
     }
 }
 
-fn process_event(ecs: &mut World, event: Event) {
-    let top_card = CARDSTACK.lock().expect("Failed to lock CARDSTACK").pop();
+// have an enemy pick a counter to the attack and play it, handing back the events it made
+fn play_counter(ecs: &mut World, ent: Entity, attacker: Entity) -> Vec<Event> {
+    let intent = match crate::sys_ai::choose_counter(ecs, ent, attacker) {
+        Some(intent) => intent,
+        None => return Vec::new(),
+    };
+    ecs.write_storage::<AttackIntent>()
+        .insert(ent, intent)
+        .expect("Failed to insert counter AttackIntent");
+
+    // reacting takes up a turn, the same as it does for the player
+    if let Some(sched) = ecs.write_storage::<super::Schedulable>().get_mut(ent) {
+        sched.current += sched.base;
+    }
+
+    let stack_size = ecs.fetch::<EventQueue>().stack.len();
+    crate::sys_attack::AttackSystem.run_now(ecs);
+
+    // cards like block take effect when played and leave nothing to trade blows with, and every
+    // event the card did make is part of the counter, so none of them get left on the stack
+    ecs.fetch_mut::<EventQueue>().stack.split_off(stack_size)
+}
+
+// counters are played straight away, so they never wait on the stack to be shown
+fn show_counter(ecs: &mut World, counter: &Event) {
+    let active_count = current_active_card_count(ecs);
+    let card = CardRequest {
+        attack_intent: counter.attack_intent.unwrap(),
+        source: counter.source,
+        offset: active_count,
+        affected: Arc::clone(&counter.target_tiles),
+    };
+
+    let mut builder = ecs.fetch_mut::<crate::ParticleBuilder>();
+    builder.make_card(card, active_count);
+}
+
+fn show_top_card(ecs: &mut World) {
+    let top_card = ecs.fetch_mut::<EventQueue>().cards.pop();
     let active_count = current_active_card_count(ecs);
 
     if let Some(top_card) = top_card {
         let mut builder = ecs.fetch_mut::<crate::ParticleBuilder>();
         builder.make_card(top_card, active_count);
     }
+}
+
+fn process_event(ecs: &mut World, event: Event) {
+    show_top_card(ecs);
 
     match event.attack_intent {
         None => event
             .resolver
             .resolve(ecs, event.source, event.target_tiles.to_vec()),
-        Some(event_intent) => {
-            // TODO: no clue if this can be simplified
-            let stack_event = {
-                // this lock needs to be limited in scope, since the resolver may want access to the stack via add_event
-                let mut stack = STACK.lock().expect("Failed to lock STACK");
-                stack.pop()
-            };
+        Some(_) => {
+            // every reaction is gathered into an exchange before anything resolves, so an attack
+            // that gets here has nothing left to trade blows with
+            ecs.fetch_mut::<crate::IntentData>().reset();
 
-            match stack_event {
-                None => {
-                    {
-                        // reset rolls, since there's no other attack
-                        let mut intents = ecs.fetch_mut::<crate::IntentData>();
-                        intents.reset();
-                    }
+            event
+                .resolver
+                .resolve(ecs, event.source, event.target_tiles.to_vec());
+        }
+    }
+}
 
-                    event
-                        .resolver
-                        .resolve(ecs, event.source, event.target_tiles.to_vec());
-                }
-                Some(stack_event) => {
-                    if let Some(stack_source) = stack_event.source {
-                        // TODO: this won't work if we want to allow enemies to counterattack
-                        let stack_source_is_player = {
-                            let player = ecs.fetch::<Entity>();
-                            stack_source == *player
-                        };
-
-                        // If the stack event isn't from the player, the player didn't act, so there's no reaction to process
-                        if !stack_source_is_player {
-                            {
-                                // replace the stack event if we're not using it
-                                STACK
-                                    .lock()
-                                    .expect("Failed to lock STACK")
-                                    .push(stack_event);
-                            }
-
-                            event
-                                .resolver
-                                .resolve(ecs, event.source, event.target_tiles.to_vec());
-
-                            return;
-                        }
-                    }
+// the cards in an exchange while it plays out
+struct ExchangeCard {
+    // taken once the card goes off
+    event: Option<Event>,
+    event_type: EventType,
+    intent: AttackIntent,
+    source: Option<Entity>,
+    reacting_to: Option<usize>,
+    speed: i32,
+    speed_roll: i32,
+    interrupted: bool,
+}
 
-                    match stack_event.attack_intent {
-                        None => {
-                            {
-                                // replace the stack event if we're not using it
-                                STACK
-                                    .lock()
-                                    .expect("Failed to lock STACK")
-                                    .push(stack_event);
-                            }
-
-                            event
-                                .resolver
-                                .resolve(ecs, event.source, event.target_tiles.to_vec())
-                        }
-                        Some(stack_intent) => {
-                            let speed_diff = compare_event_speed(ecs, &event, &stack_event);
-
-                            let (first_event, second_event) = if speed_diff >= 0 {
-                                println!("attacker wins the speed roll");
-                                (event, stack_event)
-                            } else {
-                                println!("defender wins the speed roll");
-                                (stack_event, event)
-                            };
+struct Exchange {
+    cards: Vec<ExchangeCard>,
+    player_card: Option<usize>,
+    // the pair of cards shown as incoming and outgoing, the player's card and whatever it met
+    shown: Option<(usize, usize)>,
+}
 
-                            first_event.resolver.resolve(
-                                ecs,
-                                first_event.source,
-                                first_event.target_tiles.to_vec(),
-                            );
-
-                            let can_interrupt = match first_event.event_type {
-                                EventType::Damage { .. } => {
-                                    let (def_guard_roll, atk_power_roll) = {
-                                        let mut rng =
-                                            ecs.fetch_mut::<rltk::RandomNumberGenerator>();
-                                        let s3 = rng.range(0, GUARD_ROLL_RANGE);
-                                        let s4 = rng.range(0, GUARD_ROLL_RANGE);
-
-                                        let mut intents = ecs.fetch_mut::<crate::IntentData>();
-                                        intents.rolls.2 = s3;
-                                        intents.rolls.3 = s4;
-
-                                        (s3, s4)
-                                    };
-
-                                    let (def, def_bonus_active) = if speed_diff >= 0 {
-                                        (stack_intent, true)
-                                    } else {
-                                        (event_intent, false)
-                                    };
-
-                                    // compare power vs guard to determine if the defender can counter
-                                    // only the defender can gain the guard bonus
-                                    let mut def_guard =
-                                        move_type::get_intent_guard(&def) + def_guard_roll;
-                                    if def_bonus_active {
-                                        def_guard += DEF_GUARD_BONUS;
-                                    }
-                                    let stun_power = speed_diff.abs() + atk_power_roll;
-
-                                    stun_power > def_guard
-                                }
-                                _ => false,
-                            };
+// play out every card in an exchange. Each answer is settled against the card it answered, last
+// in first out, and whoever is faster in each pair goes first
+fn settle_links(ecs: &mut World, mut links: Vec<Link>) {
+    if links.len() == 1 {
+        let link = links.pop().unwrap();
+        process_event(ecs, link.event);
+        return;
+    }
 
-                            {
-                                let mut intents = ecs.fetch_mut::<crate::IntentData>();
-                                intents.defender_was_interrupted = can_interrupt;
-                            }
-
-                            if !can_interrupt {
-                                second_event.resolver.resolve(
-                                    ecs,
-                                    second_event.source,
-                                    second_event.target_tiles.to_vec(),
-                                );
-                            } else {
-                                println!("defender is stunned!");
-                            }
-                        }
-                    }
+    show_top_card(ecs);
+
+    let player = *ecs.fetch::<Entity>();
+    let mut cards = Vec::with_capacity(links.len());
+    for (index, link) in links.into_iter().enumerate() {
+        let intent = link.event.attack_intent.unwrap();
+        let speed_roll = match ecs.try_fetch::<FixedRolls>() {
+            // the card that started it rolls as the attacker, everything else as a defender
+            Some(fixed) if index == 0 => fixed.0 .0,
+            Some(fixed) => fixed.0 .1,
+            None => {
+                let rules = ecs.fetch::<crate::Ruleset>();
+                let mut rng = ecs.fetch_mut::<rltk::RandomNumberGenerator>();
+                rules.roll_speed(&mut rng)
+            }
+        };
+        let speed = move_type::get_intent_speed(&intent)
+            + speed_roll
+            + status_speed_bonus(ecs, link.event.source);
+
+        cards.push(ExchangeCard {
+            event_type: link.event.event_type,
+            intent,
+            source: link.event.source,
+            reacting_to: link.reacting_to,
+            speed,
+            speed_roll,
+            interrupted: false,
+            event: Some(link.event),
+        });
+    }
+
+    {
+        let viewables = ecs.read_storage::<crate::Viewable>();
+        let renderables = ecs.read_storage::<crate::Renderable>();
+        let mut intents = ecs.fetch_mut::<crate::IntentData>();
+
+        intents.chain = cards
+            .iter()
+            .map(|card| crate::ExchangeLink {
+                name: card
+                    .source
+                    .and_then(|ent| viewables.get(ent))
+                    .map_or("something".to_string(), |view| view.name.clone()),
+                glyph: card
+                    .source
+                    .and_then(|ent| renderables.get(ent))
+                    .map_or(rltk::to_cp437('?'), |rend| rend.symbol),
+                intent: card.intent,
+                reacting_to: card.reacting_to,
+                speed: card.speed,
+                interrupted: false,
+            })
+            .collect();
+        intents.hidden = false;
+        intents.incoming_blocked = false;
+        intents.outgoing_blocked = false;
+    }
+
+    let player_card = cards.iter().position(|card| card.source == Some(player));
+    let mut exchange = Exchange {
+        cards,
+        player_card,
+        shown: None,
+    };
+
+    // the player either answered something or was answered, start with the first card they met
+    let shown = player_card.and_then(|index| match exchange.cards[index].reacting_to {
+        Some(answered) => Some((answered, index)),
+        None => exchange
+            .cards
+            .iter()
+            .position(|card| card.reacting_to == Some(index))
+            .map(|answer| (index, answer)),
+    });
+    if let Some((attack, answer)) = shown {
+        show_pair(ecs, &mut exchange, attack, answer);
+    }
+
+    settle(ecs, &mut exchange, 0);
+
+    let mut intents = ecs.fetch_mut::<crate::IntentData>();
+    for (link, card) in intents.chain.iter_mut().zip(exchange.cards.iter()) {
+        link.interrupted = card.interrupted;
+    }
+}
+
+// put a pair of cards up as the incoming and outgoing intents
+fn show_pair(ecs: &mut World, exchange: &mut Exchange, attack: usize, answer: usize) {
+    let player = *ecs.fetch::<Entity>();
+    let rules = ecs.fetch::<crate::Ruleset>();
+    let mut intents = ecs.fetch_mut::<crate::IntentData>();
+    let cards = &exchange.cards;
+
+    // the incoming card is always the enemy's, even when it was played as a counter
+    let attack_first = cards[attack].speed + rules.attacker_speed_bonus >= cards[answer].speed;
+    let player_attacked = cards[attack].source == Some(player);
+    let incoming = if player_attacked { answer } else { attack };
+
+    intents.defender_was_interrupted = false;
+    intents.rolls.0 = cards[attack].speed_roll;
+    intents.rolls.1 = cards[answer].speed_roll;
+    intents.countered = player_attacked;
+    intents.incoming_went_first = attack_first != player_attacked;
+    intents.prev_incoming_intent = Some(cards[incoming].intent);
+
+    exchange.shown = Some((attack, answer));
+}
+
+// settle everything that answered a card, along with the card itself
+fn settle(ecs: &mut World, exchange: &mut Exchange, attack: usize) {
+    let attack_speed =
+        exchange.cards[attack].speed + ecs.fetch::<crate::Ruleset>().attacker_speed_bonus;
+    let (faster, slower): (Vec<usize>, Vec<usize>) = (0..exchange.cards.len())
+        .rev()
+        .filter(|index| exchange.cards[*index].reacting_to == Some(attack))
+        .partition(|answer| exchange.cards[*answer].speed > attack_speed);
+
+    // faster answers go off first, and can stop the card before it lands
+    for answer in faster {
+        // the first hit can use up the defender's block, so any bracing has to be counted
+        // before it lands
+        let brace = brace_bonus(ecs, exchange.cards[attack].source);
+        settle(ecs, exchange, answer);
+
+        if !exchange.cards[answer].interrupted && !exchange.cards[attack].interrupted {
+            let speed_diff = exchange.cards[answer].speed - attack_speed;
+            if can_interrupt(ecs, exchange, answer, attack, speed_diff, brace) {
+                interrupt(ecs, exchange, attack);
+            }
+        }
+    }
+
+    let braces: Vec<i32> = slower
+        .iter()
+        .map(|answer| brace_bonus(ecs, exchange.cards[*answer].source))
+        .collect();
+
+    let landed = !exchange.cards[attack].interrupted;
+    if landed {
+        let event = exchange.cards[attack].event.take().unwrap();
+        event
+            .resolver
+            .resolve(ecs, event.source, event.target_tiles.to_vec());
+    }
+
+    // then the slower answers, unless the card that got there first stopped them
+    for (answer, brace) in slower.into_iter().zip(braces) {
+        let speed_diff = attack_speed - exchange.cards[answer].speed;
+        if landed && can_interrupt(ecs, exchange, attack, answer, speed_diff, brace) {
+            interrupt(ecs, exchange, answer);
+        }
+
+        settle(ecs, exchange, answer);
+    }
+}
+
+// check if the card that went first stops the other one
+fn can_interrupt(
+    ecs: &mut World,
+    exchange: &mut Exchange,
+    first: usize,
+    second: usize,
+    speed_diff: i32,
+    def_brace: i32,
+) -> bool {
+    let mut guard_rolls = None;
+
+    let can_interrupt = match exchange.cards[first].event_type {
+        EventType::Damage { .. } => {
+            let rules = ecs.fetch::<crate::Ruleset>();
+            let (def_guard_roll, atk_power_roll) = match ecs.try_fetch::<FixedRolls>() {
+                Some(fixed) => (fixed.0 .2, fixed.0 .3),
+                None => {
+                    let mut rng = ecs.fetch_mut::<rltk::RandomNumberGenerator>();
+                    (rules.roll_guard(&mut rng), rules.roll_guard(&mut rng))
                 }
+            };
+            guard_rolls = Some((def_guard_roll, atk_power_roll));
+
+            // compare power vs guard to determine if the defender can counter
+            // only the defender can gain the guard bonus
+            let defender = &exchange.cards[second];
+            let mut def_guard = move_type::get_intent_guard(&defender.intent)
+                + def_guard_roll
+                + def_brace
+                + status_guard_bonus(ecs, defender.source);
+            if defender.reacting_to == Some(first) {
+                def_guard += rules.defender_guard_bonus;
             }
+            let stun_power = speed_diff.abs() + atk_power_roll;
+
+            stun_power > def_guard
         }
+        // a parry that gets there first always stops the attack
+        EventType::Parry => true,
+        _ => false,
+    };
+
+    let pair = if exchange.cards[second].reacting_to == Some(first) {
+        (first, second)
+    } else {
+        (second, first)
+    };
+
+    // whatever stopped the player's card is more interesting than whatever they met first
+    if can_interrupt && exchange.player_card == Some(second) && exchange.shown != Some(pair) {
+        show_pair(ecs, exchange, pair.0, pair.1);
     }
+
+    if exchange.shown == Some(pair) {
+        let mut intents = ecs.fetch_mut::<crate::IntentData>();
+        if let Some((def_guard_roll, atk_power_roll)) = guard_rolls {
+            intents.rolls.2 = def_guard_roll;
+            intents.rolls.3 = atk_power_roll;
+        }
+        intents.defender_was_interrupted = can_interrupt;
+    }
+
+    can_interrupt
 }
 
-fn compare_event_speed(ecs: &mut World, attack_event: &Event, react_event: &Event) -> i32 {
-    let mut rng = ecs.fetch_mut::<rltk::RandomNumberGenerator>();
-    let atk_speed_roll = rng.range(0, SPEED_ROLL_RANGE);
-    let def_speed_roll = rng.range(0, SPEED_ROLL_RANGE);
+// getting interrupted leaves the card's owner open for a while, and the card never goes off
+fn interrupt(ecs: &mut World, exchange: &mut Exchange, index: usize) {
+    exchange.cards[index].interrupted = true;
 
-    // compare speed to determine which attack resolves first
-    // also just unwrap the events here, we already pattern matched on them
-    let atk_speed = move_type::get_intent_speed(&attack_event.attack_intent.unwrap())
-        + atk_speed_roll
-        + ATK_SPD_BONUS;
-    let def_speed =
-        move_type::get_intent_speed(&react_event.attack_intent.unwrap()) + def_speed_roll;
+    let interrupted = exchange.cards[index]
+        .source
+        .filter(|ent| ecs.entities().is_alive(*ent));
+    let turn_length = interrupted.and_then(|ent| {
+        let scheds = ecs.read_storage::<super::Schedulable>();
+        scheds.get(ent).map(|sched| sched.base)
+    });
 
-    let mut intents = ecs.fetch_mut::<crate::IntentData>();
-    intents.hidden = false;
-    intents.rolls.0 = atk_speed_roll;
-    intents.rolls.1 = def_speed_roll;
-    intents.incoming_went_first = atk_speed >= def_speed;
+    if let (Some(interrupted), Some(turn_length)) = (interrupted, turn_length) {
+        let mut statuses = ecs.write_storage::<crate::StatusEffects>();
+        crate::status::inflict(
+            &mut statuses,
+            interrupted,
+            crate::StatusKind::OffBalance,
+            turn_length,
+        );
+    }
+}
 
-    atk_speed - def_speed
+fn brace_bonus(ecs: &World, source: Option<Entity>) -> i32 {
+    let blocks = ecs.read_storage::<crate::BlockAttack>();
+    source
+        .and_then(|ent| blocks.get(ent))
+        .map_or(0, |block| block.guard_bonus)
+}
+
+fn status_speed_bonus(ecs: &World, source: Option<Entity>) -> i32 {
+    let statuses = ecs.read_storage::<crate::StatusEffects>();
+    source
+        .and_then(|ent| statuses.get(ent))
+        .map_or(0, |status| status.speed_bonus())
+}
+
+fn status_guard_bonus(ecs: &World, source: Option<Entity>) -> i32 {
+    let statuses = ecs.read_storage::<crate::StatusEffects>();
+    source
+        .and_then(|ent| statuses.get(ent))
+        .map_or(0, |status| status.guard_bonus())
 }
 
 fn add_card_to_stack(
@@ -428,14 +914,14 @@ fn add_card_to_stack(
         });
 
         if let Some(visual_event_data) = visual_event_data {
-            CARDSTACK
-                .lock()
-                .expect("Failed to lock CARDSTACK")
-                .push(visual_event_data);
+            let mut queue = ecs.fetch_mut::<EventQueue>();
+            queue.cards.push(visual_event_data);
 
             let mut intents = ecs.fetch_mut::<crate::IntentData>();
 
             intents.hidden = true;
+            intents.incoming_blocked = false;
+            intents.outgoing_blocked = false;
             intents.prev_incoming_intent = Some(intent);
             intents.prev_outgoing_intent = None;
         }
@@ -443,6 +929,204 @@ fn add_card_to_stack(
 }
 
 fn current_active_card_count(ecs: &mut World) -> i32 {
-    let cards = ecs.read_storage::<crate::CardLifetime>();
-    cards.join().count() as i32
+    let builder = ecs.fetch::<crate::ParticleBuilder>();
+    builder.cards.len() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{build_fighter, build_world};
+    use crate::{Health, Position, Schedulable, StatusEffects, StatusKind};
+
+    // every roll comes up 0 and neither side gets a bonus, so only the cards decide the order
+    fn quiet_world() -> World {
+        let mut rules = crate::ruleset::default_ruleset();
+        rules.attacker_speed_bonus = 0;
+        rules.defender_guard_bonus = 0;
+
+        let mut ecs = build_world(rules, rltk::RandomNumberGenerator::seeded(0));
+        ecs.insert(FixedRolls((0, 0, 0, 0)));
+
+        // the player sits out every exchange here
+        let player = ecs.create_entity().build();
+        ecs.insert(player);
+
+        ecs
+    }
+
+    // only things that take turns can be knocked off balance
+    fn fighter(ecs: &mut World, x: i32, y: i32) -> Entity {
+        let ent = build_fighter(ecs, Point::new(x, y));
+        ecs.write_storage::<Schedulable>()
+            .insert(
+                ent,
+                Schedulable {
+                    current: 0,
+                    base: 24,
+                    delta: 4,
+                },
+            )
+            .expect("Failed to insert Schedulable");
+
+        ent
+    }
+
+    fn position(ecs: &World, ent: Entity) -> Point {
+        ecs.read_storage::<Position>().get(ent).unwrap().as_point()
+    }
+
+    fn link(
+        ecs: &World,
+        source: Entity,
+        card: &str,
+        modifier: Option<&str>,
+        target: Entity,
+        reacting_to: Option<usize>,
+    ) -> Link {
+        let intent = move_type::get_attack_intent(
+            &crate::cards::card_named(card),
+            position(ecs, target),
+            modifier.map(crate::cards::card_named),
+        );
+
+        let mut queue = EventQueue::new();
+        add_damage_event(&mut queue, &intent, Some(source), true);
+        Link {
+            event: queue.stack.pop().unwrap(),
+            reacting_to,
+        }
+    }
+
+    // every tile a card hits when aimed at the given point
+    fn tiles(card: &str, at: Point) -> Vec<Point> {
+        let shape = move_type::get_attack_shape(&crate::cards::card_named(card));
+        range_type::resolve_range_at(&shape, at)
+    }
+
+    // each hit leaves a particle on every tile it lands on, in the order the hits went off
+    fn hits(ecs: &World) -> Vec<Point> {
+        ecs.fetch::<EventQueue>()
+            .stack
+            .iter()
+            .filter_map(|event| match event.event_type {
+                EventType::ParticleSpawn { request } => Some(request.position),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn damage_taken(ecs: &World, ent: Entity) -> i32 {
+        let healths = ecs.read_storage::<Health>();
+        let health = healths.get(ent).unwrap();
+        health.max - health.current
+    }
+
+    fn off_balance(ecs: &World, ent: Entity) -> bool {
+        ecs.read_storage::<StatusEffects>()
+            .get(ent)
+            .map_or(false, |status| status.has(StatusKind::OffBalance))
+    }
+
+    fn interrupted(ecs: &World) -> Vec<bool> {
+        let intents = ecs.fetch::<crate::IntentData>();
+        intents.chain.iter().map(|link| link.interrupted).collect()
+    }
+
+    #[test]
+    fn last_answer_settles_first() {
+        let mut ecs = quiet_world();
+        let a = fighter(&mut ecs, 3, 5);
+        let b = fighter(&mut ecs, 4, 5);
+        let c = fighter(&mut ecs, 5, 5);
+
+        // b answers a, and c answers b, so c's sweep goes off before b gets to hit anything
+        let links = vec![
+            link(&ecs, a, "punch", Some("super"), b, None),
+            link(&ecs, b, "punch", None, a, Some(0)),
+            link(&ecs, c, "sweep", Some("quick"), c, Some(1)),
+        ];
+        settle_links(&mut ecs, links);
+
+        let mut expected = tiles("sweep", position(&ecs, c));
+        expected.push(position(&ecs, b));
+        assert_eq!(hits(&ecs), expected);
+
+        assert_eq!(interrupted(&ecs), vec![false, true, false]);
+        assert_eq!(damage_taken(&ecs, a), 0);
+        assert_eq!(damage_taken(&ecs, b), 3);
+        assert!(off_balance(&ecs, b));
+    }
+
+    #[test]
+    fn faster_answer_goes_off_first() {
+        let mut ecs = quiet_world();
+        let a = fighter(&mut ecs, 4, 5);
+        let b = fighter(&mut ecs, 5, 5);
+
+        // the sweep is faster, but not by enough to get through the super's guard
+        let links = vec![
+            link(&ecs, a, "punch", Some("super"), b, None),
+            link(&ecs, b, "sweep", None, b, Some(0)),
+        ];
+        settle_links(&mut ecs, links);
+
+        let mut expected = tiles("sweep", position(&ecs, b));
+        expected.push(position(&ecs, b));
+        assert_eq!(hits(&ecs), expected);
+
+        assert_eq!(interrupted(&ecs), vec![false, false]);
+        assert_eq!(damage_taken(&ecs, a), 1);
+        assert_eq!(damage_taken(&ecs, b), 3);
+    }
+
+    #[test]
+    fn faster_answer_can_stop_the_attack() {
+        let mut ecs = quiet_world();
+        let a = fighter(&mut ecs, 4, 5);
+        let b = fighter(&mut ecs, 5, 5);
+
+        let links = vec![
+            link(&ecs, a, "punch", Some("super"), b, None),
+            link(&ecs, b, "punch", None, a, Some(0)),
+        ];
+        settle_links(&mut ecs, links);
+
+        assert_eq!(hits(&ecs), vec![position(&ecs, a)]);
+        assert_eq!(interrupted(&ecs), vec![true, false]);
+        assert_eq!(damage_taken(&ecs, a), 1);
+        assert_eq!(damage_taken(&ecs, b), 0);
+        assert!(off_balance(&ecs, a));
+    }
+
+    #[test]
+    fn one_attack_answered_three_times() {
+        let mut ecs = quiet_world();
+        let a = fighter(&mut ecs, 5, 4);
+        let b = fighter(&mut ecs, 4, 5);
+        let c = fighter(&mut ecs, 5, 5);
+        let d = fighter(&mut ecs, 6, 5);
+
+        // the sweep catches all three, d's quick sweep stops it, b still gets a hit in first
+        // and c is too slow to go before it either way
+        let links = vec![
+            link(&ecs, a, "sweep", None, a, None),
+            link(&ecs, b, "punch", None, a, Some(0)),
+            link(&ecs, c, "punch", Some("super"), a, Some(0)),
+            link(&ecs, d, "sweep", Some("quick"), d, Some(0)),
+        ];
+        settle_links(&mut ecs, links);
+
+        let a_pos = position(&ecs, a);
+        let mut expected = tiles("sweep", position(&ecs, d));
+        expected.extend(vec![a_pos, a_pos]);
+        assert_eq!(hits(&ecs), expected);
+
+        assert_eq!(interrupted(&ecs), vec![true, false, false, false]);
+        assert_eq!(damage_taken(&ecs, a), 4);
+        assert!(off_balance(&ecs, a));
+        for ent in [b, c, d].iter() {
+            assert_eq!(damage_taken(&ecs, *ent), 0);
+        }
+    }
 }
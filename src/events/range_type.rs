@@ -1,6 +1,7 @@
 use rltk::Point;
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
 pub enum RangeType {
     Empty,
     Single,
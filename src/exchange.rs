@@ -0,0 +1,292 @@
+use crate::*;
+use rltk::{Point, RandomNumberGenerator};
+
+// the two sides stand next to each other in the middle of an empty room
+const ARENA_SIZE: i32 = 11;
+const ATTACKER_POS: Point = Point { x: 5, y: 5 };
+const DEFENDER_POS: Point = Point { x: 6, y: 5 };
+
+// enough that nobody dies partway through an exchange
+const FIGHTER_HEALTH: i32 = 100;
+
+/// What happened when an attack met a reaction
+#[derive(Copy, Clone)]
+pub struct ExchangeOutcome {
+    pub attacker_first: bool,
+    // whoever went second was stunned by the guard roll and never got to act
+    pub interrupted: bool,
+    pub damage_to_attacker: i32,
+    pub damage_to_defender: i32,
+}
+
+/// Plays out an attack and the defender's reaction to it in an otherwise empty world. This goes
+/// through the same attack system and event stack as a real game, so the results always match
+/// the current cards and ruleset. The defender stands in for the player, since only the player
+/// waits on the stack to pick a reaction.
+pub struct ExchangeSim {
+    rules: Ruleset,
+    rng: RandomNumberGenerator,
+}
+
+impl ExchangeSim {
+    pub fn new(rules: Ruleset, seed: u64) -> Self {
+        ExchangeSim {
+            rules,
+            rng: RandomNumberGenerator::seeded(seed),
+        }
+    }
+
+    /// Run a single exchange. Where the intents are aimed doesn't matter, each side aims their
+    /// card at the other, or out of the way of the attack for movement cards.
+    pub fn run(&mut self, attack: &AttackIntent, reaction: &AttackIntent) -> ExchangeOutcome {
+        self.play_out(attack, reaction, None)
+    }
+
+    /// Every way an exchange can go, along with how likely it is. Rather than sampling, this
+    /// plays the exchange out once for every combination of rolls.
+    pub fn every_outcome(
+        &mut self,
+        attack: &AttackIntent,
+        reaction: &AttackIntent,
+    ) -> Vec<(f32, ExchangeOutcome)> {
+        let speed_faces = self.rules.speed_faces();
+        let guard_faces = self.rules.guard_faces();
+        let combinations = speed_faces.len().pow(2) * guard_faces.len().pow(2);
+        let chance = 1.0 / combinations as f32;
+        let mut outcomes = Vec::with_capacity(combinations);
+
+        for atk_speed in speed_faces.clone() {
+            for def_speed in speed_faces.clone() {
+                for def_guard in guard_faces.clone() {
+                    for atk_power in guard_faces.clone() {
+                        let rolls = (atk_speed, def_speed, def_guard, atk_power);
+                        let outcome = self.play_out(attack, reaction, Some(rolls));
+                        outcomes.push((chance, outcome));
+                    }
+                }
+            }
+        }
+
+        outcomes
+    }
+
+    fn play_out(
+        &mut self,
+        attack: &AttackIntent,
+        reaction: &AttackIntent,
+        rolls: Option<IntentRolls>,
+    ) -> ExchangeOutcome {
+        let (mut ecs, attacker, defender) = self.build_exchange();
+        if let Some(rolls) = rolls {
+            ecs.insert(events::FixedRolls(rolls));
+        }
+
+        // the attack goes out first, which is what gives the defender a chance to react
+        let attack = aim(attack, ATTACKER_POS, DEFENDER_POS, None);
+        play(&mut ecs, attacker, attack);
+        let mut run_state = events::process_stack(&mut ecs);
+
+        if run_state == RunState::AwaitingInput {
+            ecs.write_storage::<CanActFlag>().clear();
+
+            let reaction = aim(reaction, DEFENDER_POS, ATTACKER_POS, Some(&attack));
+            play(&mut ecs, defender, reaction);
+            run_state = events::process_stack(&mut ecs);
+        }
+
+        // let anything the exchange set off finish resolving
+        while let RunState::HitPause { .. } = run_state {
+            run_state = events::process_stack(&mut ecs);
+        }
+
+        let outcome = {
+            let intents = ecs.fetch::<IntentData>();
+            let healths = ecs.read_storage::<Health>();
+            let damage_taken = |ent| {
+                let health = healths.get(ent).expect("Fighter lost its health");
+                health.max - health.current
+            };
+
+            let attacker_first = match intents.chain.as_slice() {
+                // the two cards were settled against each other, the same way the cards show it
+                [attack, answer, ..] => {
+                    attack.speed + self.rules.attacker_speed_bonus >= answer.speed
+                }
+                // nothing answered the attack, or the reaction took effect as soon as it was
+                // played, like a block, and the attack was all that went off
+                _ => true,
+            };
+
+            ExchangeOutcome {
+                attacker_first,
+                interrupted: intents.defender_was_interrupted,
+                damage_to_attacker: damage_taken(attacker),
+                damage_to_defender: damage_taken(defender),
+            }
+        };
+
+        // hand the rng back, so the next exchange continues the sequence
+        self.rng = ecs
+            .remove::<RandomNumberGenerator>()
+            .expect("Failed to take back the rng");
+
+        outcome
+    }
+
+    fn build_exchange(&mut self) -> (World, Entity, Entity) {
+        let rng = std::mem::replace(&mut self.rng, RandomNumberGenerator::seeded(0));
+        let mut ecs = build_world(self.rules.clone(), rng);
+
+        let attacker = build_fighter(&mut ecs, ATTACKER_POS);
+        let defender = build_fighter(&mut ecs, DEFENDER_POS);
+        ecs.write_storage::<CanReactFlag>()
+            .insert(defender, CanReactFlag)
+            .expect("Failed to insert CanReactFlag");
+        ecs.insert(defender);
+
+        (ecs, attacker, defender)
+    }
+}
+
+/// An empty room with everything an exchange needs to play out, apart from the fighters and a
+/// player. The exchange tests in the event stack start from here too.
+pub(crate) fn build_world(rules: Ruleset, rng: RandomNumberGenerator) -> World {
+    let mut ecs = World::new();
+    ecs.register::<Position>();
+    ecs.register::<Health>();
+    ecs.register::<CanActFlag>();
+    ecs.register::<CanReactFlag>();
+    ecs.register::<Schedulable>();
+    ecs.register::<AttackIntent>();
+    ecs.register::<AttackInProgress>();
+    ecs.register::<BlockAttack>();
+    ecs.register::<Viewable>();
+    ecs.register::<Renderable>();
+    ecs.register::<Viewshed>();
+    ecs.register::<StatusEffects>();
+    ecs.register::<EnemyDeck>();
+    ecs.register::<AiState>();
+
+    let hand_limit = rules.hand_limit;
+    ecs.insert(rng);
+    ecs.insert(rules);
+    ecs.insert(events::EventQueue::new());
+    ecs.insert(sys_noise::NoiseQueue::new());
+    ecs.insert(sys_particle::ParticleBuilder::new());
+    ecs.insert(gamelog::GameLog {
+        entries: Vec::new(),
+        killed_by: None,
+    });
+    ecs.insert(deck::Deck::new(Vec::new(), hand_limit));
+    ecs.insert(IntentData {
+        hidden: true,
+        incoming_went_first: false,
+        countered: false,
+        defender_was_interrupted: false,
+        incoming_blocked: false,
+        outgoing_blocked: false,
+        prev_incoming_intent: None,
+        prev_outgoing_intent: None,
+        rolls: (0, 0, 0, 0),
+        chain: Vec::new(),
+    });
+    ecs.insert(map::build_arena(ARENA_SIZE, ARENA_SIZE));
+
+    ecs
+}
+
+/// Someone to trade blows with, standing on the given tile
+pub(crate) fn build_fighter(ecs: &mut World, point: Point) -> Entity {
+    let fighter = ecs
+        .create_entity()
+        .with(Position {
+            x: point.x,
+            y: point.y,
+        })
+        .with(Health {
+            current: FIGHTER_HEALTH,
+            max: FIGHTER_HEALTH,
+        })
+        .build();
+
+    ecs.fetch_mut::<Map>().track_creature(fighter, point);
+    fighter
+}
+
+fn play(ecs: &mut World, ent: Entity, intent: AttackIntent) {
+    ecs.write_storage::<AttackIntent>()
+        .insert(ent, intent)
+        .expect("Failed to insert AttackIntent");
+
+    sys_attack::AttackSystem.run_now(ecs);
+}
+
+// pick the tile a card would be aimed at, the same way a player would click on it
+fn aim(
+    intent: &AttackIntent,
+    from: Point,
+    target: Point,
+    incoming: Option<&AttackIntent>,
+) -> AttackIntent {
+    let traits = move_type::get_attack_traits(&intent.main);
+
+    let loc = match incoming {
+        // get out of the way of whatever is coming in
+        Some(incoming) if traits.contains(&AttackTrait::Movement) => {
+            let incoming_shape = move_type::get_attack_shape(&incoming.main);
+            let hit_tiles = range_type::resolve_range_at(&incoming_shape, incoming.loc);
+            let range = move_type::get_attack_range(&intent.main);
+
+            range_type::resolve_range_at(&range, from)
+                .into_iter()
+                .find(|tile| *tile != target && !hit_tiles.contains(tile))
+                .unwrap_or(from)
+        }
+        _ => move_type::is_attack_valid(&intent.main, from, target).unwrap_or(target),
+    };
+
+    move_type::get_attack_intent(&intent.main, loc, intent.modifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intent(name: &str) -> AttackIntent {
+        move_type::get_attack_intent(&cards::card_named(name), Point::zero(), None)
+    }
+
+    #[test]
+    fn reactions_without_a_card_leave_the_attack_going_first() {
+        let mut sim = ExchangeSim::new(ruleset::default_ruleset(), 0);
+
+        for reaction in ["block", "brace"].iter() {
+            for (_, outcome) in sim.every_outcome(&intent("punch"), &intent(reaction)) {
+                assert!(outcome.attacker_first, "{} went before the punch", reaction);
+                assert!(!outcome.interrupted);
+                assert_eq!(outcome.damage_to_attacker, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn answered_attacks_go_in_speed_order() {
+        let mut sim = ExchangeSim::new(ruleset::default_ruleset(), 0);
+        let rules = ruleset::default_ruleset();
+        let outcomes = sim.every_outcome(&intent("punch"), &intent("punch"));
+
+        // the rolls are walked through in order, attacker speed first and defender speed second
+        let combinations = rules.speed_faces().len() * rules.speed_faces().len();
+        let per_speed = outcomes.len() / combinations;
+        for (index, (_, outcome)) in outcomes.iter().enumerate() {
+            let speeds = index / per_speed;
+            let attack_roll = (speeds / rules.speed_faces().len()) as i32;
+            let answer_roll = (speeds % rules.speed_faces().len()) as i32;
+
+            assert_eq!(
+                outcome.attacker_first,
+                attack_roll + rules.attacker_speed_bonus >= answer_roll
+            );
+        }
+    }
+}
@@ -0,0 +1,744 @@
+use crate::*;
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+
+// how long particles are allowed to live between decisions when nothing is being drawn
+const HEADLESS_FRAME_TIME: f32 = 1000.0;
+
+/// An abstract player decision, independent of how the input was collected.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum Command {
+    Move { dx: i32, dy: i32 },
+    // a slower step that hardly makes a sound
+    Sneak { dx: i32, dy: i32 },
+    // draw a card, or block if we are reacting
+    Pass,
+    SelectCard { index: usize },
+    // confirm the selected card, targetting the given point if the card needs one
+    Target { point: Option<Point> },
+    CancelTarget,
+    // pick one of the offered rewards, or skip with None
+    ChooseReward { index: Option<usize> },
+}
+
+/// The game rules wrapped around a World. Nothing in here needs a rendering context, so a game
+/// can be advanced from decision point to decision point with `step`.
+pub struct Game {
+    pub ecs: World,
+    tick: i32,
+    attack_modifier: Option<AttackType>,
+    recording: replay::Replay,
+}
+
+/// The seed that every random roll in a run is derived from
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct RunSeed(pub u64);
+
+impl Game {
+    pub fn new(seed: u64, rules: Ruleset) -> Self {
+        let mut game = Game {
+            ecs: World::new(),
+            tick: 0,
+            attack_modifier: None,
+            recording: replay::Replay::new(seed, rules.clone()),
+        };
+
+        game.new_game(seed, rules);
+        game
+    }
+
+    /// Resume a game from a save file
+    pub fn load(path: &str) -> Result<Self, saveload::SaveError> {
+        let mut game = Game {
+            ecs: World::new(),
+            tick: 0,
+            attack_modifier: None,
+            recording: replay::Replay::new(0, ruleset::default_ruleset()),
+        };
+
+        game.register_components();
+        game.ecs.insert(sys_particle::ParticleBuilder::new());
+        game.ecs.insert(sys_noise::NoiseQueue::new());
+
+        let (attack_modifier, recording) = saveload::load_game(&mut game.ecs, path)?;
+        game.attack_modifier = attack_modifier;
+        game.recording = recording;
+
+        Ok(game)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), saveload::SaveError> {
+        saveload::save_game(&self.ecs, self.attack_modifier, &self.recording, path)
+    }
+
+    /// Every command given so far this run
+    pub fn recording(&self) -> &replay::Replay {
+        &self.recording
+    }
+
+    /// How many turns the player has taken this run
+    pub fn turn(&self) -> u32 {
+        self.recording.turns
+    }
+
+    pub fn random_seed() -> u64 {
+        rltk::RandomNumberGenerator::new().next_u64()
+    }
+
+    fn register_components(&mut self) {
+        self.ecs.register::<Position>();
+        self.ecs.register::<Renderable>();
+        self.ecs.register::<Player>();
+        self.ecs.register::<Viewshed>();
+        self.ecs.register::<CanActFlag>();
+        self.ecs.register::<CanReactFlag>();
+        self.ecs.register::<Schedulable>();
+        self.ecs.register::<BlocksTile>();
+        self.ecs.register::<Viewable>();
+        self.ecs.register::<ViewableIndex>();
+
+        self.ecs.register::<Health>();
+        self.ecs.register::<DeathTrigger>();
+        self.ecs.register::<AttackIntent>();
+        self.ecs.register::<MoveIntent>();
+        self.ecs.register::<Moveset>();
+        self.ecs.register::<EnemyDeck>();
+
+        self.ecs.register::<AttackInProgress>();
+        self.ecs.register::<BlockAttack>();
+        self.ecs.register::<AiState>();
+        self.ecs.register::<Heal>();
+        self.ecs.register::<SkillChoice>();
+        self.ecs.register::<Item>();
+        self.ecs.register::<Openable>();
+        self.ecs.register::<StatusEffects>();
+        self.ecs.register::<Equipped>();
+        self.ecs.register::<EquipmentPickup>();
+    }
+
+    fn new_game(&mut self, seed: u64, rules: Ruleset) {
+        self.register_components();
+
+        self.ecs.insert(RunState::Running);
+        self.ecs.insert(rules);
+        self.ecs.insert(sys_particle::ParticleBuilder::new());
+        self.ecs.insert(events::EventQueue::new());
+        self.ecs.insert(sys_noise::NoiseQueue::new());
+
+        // everything random in a run needs to go through this rng, otherwise runs can't be replayed
+        let rng = rltk::RandomNumberGenerator::seeded(seed);
+        self.ecs.insert(rng);
+        self.ecs.insert(RunSeed(seed));
+
+        let mut map = map::build_level(&mut self.ecs, map::MAP_W, map::MAP_H, 1);
+        let player_pos = map.rooms[0].center();
+        let player = spawner::build_player(&mut self.ecs, player_pos);
+        map.track_creature(player, player_pos);
+
+        self.ecs.insert(map);
+        self.ecs.insert(player);
+
+        let log = gamelog::GameLog {
+            entries: vec!["Hello world!".to_string()],
+            killed_by: None,
+        };
+        self.ecs.insert(log);
+
+        let mut deck = deck::Deck::new_starting_hand(&self.ecs);
+        {
+            let mut rng = self.ecs.fetch_mut::<rltk::RandomNumberGenerator>();
+            deck.draw(&mut rng);
+            deck.draw(&mut rng);
+            deck.draw(&mut rng);
+        }
+        self.ecs.insert(deck);
+        // TODO: there really has to be a better way to maintain this info, but here we are
+        let data = IntentData {
+            hidden: true,
+            incoming_went_first: false,
+            countered: false,
+            defender_was_interrupted: false,
+            incoming_blocked: false,
+            outgoing_blocked: false,
+            prev_incoming_intent: None,
+            prev_outgoing_intent: None,
+            rolls: (0, 0, 0, 0),
+            chain: Vec::new(),
+        };
+        self.ecs.insert(data);
+    }
+
+    pub fn run_state(&self) -> RunState {
+        *self.ecs.fetch::<RunState>()
+    }
+
+    fn set_run_state(&mut self, next_status: RunState) {
+        let mut status_writer = self.ecs.write_resource::<RunState>();
+        *status_writer = next_status;
+    }
+
+    pub fn run_systems(&mut self) -> RunState {
+        self.tick += 1;
+
+        sys_ai::AiSystem.run_now(&self.ecs);
+        sys_turn::TurnSystem.run_now(&self.ecs);
+
+        sys_movement::MovementSystem.run_now(&self.ecs);
+        sys_attack::AttackSystem.run_now(&self.ecs);
+
+        // pickups happen after movement
+        sys_pickup::PickupSystem.run_now(&self.ecs);
+
+        // events are processed after everything relevant is added (only attacks currently)
+        let run_state = events::process_stack(&mut self.ecs);
+
+        // index needs to run after movement so blocked tiles are updated
+        sys_mapindex::MapIndexSystem.run_now(&self.ecs);
+
+        // death needs to run after attacks so bodies are cleaned up
+        sys_death::DeathSystem.run_now(&self.ecs);
+
+        // everything that made a noise this tick has done so by now
+        sys_noise::NoiseSystem.run_now(&self.ecs);
+
+        sys_visibility::VisibilitySystem.run_now(&self.ecs);
+        sys_particle::ParticleSpawnSystem.run_now(&self.ecs);
+
+        self.ecs.maintain();
+        run_state
+    }
+
+    /// Run the game until the player has to make a decision (or can't make any more)
+    pub fn advance(&mut self) -> RunState {
+        loop {
+            let next_status = match self.run_state() {
+                RunState::Running => {
+                    let next_status = self.run_systems();
+
+                    if next_status == RunState::Running {
+                        *self.ecs.fetch::<RunState>()
+                    } else {
+                        next_status
+                    }
+                }
+                RunState::HitPause { .. } => {
+                    {
+                        let mut intents = self.ecs.fetch_mut::<IntentData>();
+                        intents.hidden = false;
+                    }
+
+                    // there's nothing to wait on, so just flush the visuals
+                    events::process_stack_visual_only(&mut self.ecs);
+
+                    RunState::Running
+                }
+                RunState::GenerateMap => {
+                    self.change_level();
+                    RunState::AwaitingInput
+                }
+                // view states are purely visual, leave them if we somehow end up here
+                RunState::ViewEnemy { .. } | RunState::ViewCard => RunState::AwaitingInput,
+                decision => return decision,
+            };
+
+            sys_particle::cleanup_particles(&mut self.ecs, HEADLESS_FRAME_TIME);
+            self.set_run_state(next_status);
+        }
+    }
+
+    /// Apply a command and run the game until the next decision point
+    pub fn step(&mut self, command: Command) -> RunState {
+        self.apply(command);
+        self.advance()
+    }
+
+    /// Apply a command to the current decision point, without advancing the game
+    /// Commands that don't make sense in the current state are ignored
+    pub fn apply(&mut self, command: Command) -> RunState {
+        let curr_status = self.run_state();
+        let next_status = match (curr_status, command) {
+            (RunState::AwaitingInput, Command::Move { dx, dy }) => {
+                let (is_reaction, _) = self.reaction_status();
+
+                if is_reaction {
+                    RunState::AwaitingInput
+                } else {
+                    try_move_player(&mut self.ecs, dx, dy, false)
+                }
+            }
+            (RunState::AwaitingInput, Command::Sneak { dx, dy }) => {
+                let (is_reaction, _) = self.reaction_status();
+
+                if is_reaction {
+                    RunState::AwaitingInput
+                } else {
+                    try_move_player(&mut self.ecs, dx, dy, true)
+                }
+            }
+            (RunState::AwaitingInput, Command::Pass) => {
+                let (is_reaction, _) = self.reaction_status();
+
+                if !is_reaction {
+                    let mut deck = self.ecs.fetch_mut::<deck::Deck>();
+                    let mut rng = self.ecs.fetch_mut::<rltk::RandomNumberGenerator>();
+                    deck.draw(&mut rng);
+                } else {
+                    let mut blocking = self.ecs.write_storage::<BlockAttack>();
+                    let player = self.ecs.fetch::<Entity>();
+                    sys_attack::add_block(&mut blocking, *player, 1, 0);
+                }
+
+                RunState::Running
+            }
+            (RunState::AwaitingInput, Command::SelectCard { index }) => self.select_card(index),
+            (
+                RunState::Targetting {
+                    attack_type,
+                    ignore_targetting,
+                },
+                Command::Target { point },
+            ) => {
+                if ignore_targetting {
+                    self.play_card(attack_type, None)
+                } else {
+                    match point {
+                        Some(point) if self.valid_targets(&attack_type).contains(&point) => {
+                            self.play_card(attack_type, Some(point))
+                        }
+                        _ => self.cancel_card(),
+                    }
+                }
+            }
+            (RunState::Targetting { .. }, Command::CancelTarget) => self.cancel_card(),
+            (RunState::ChooseReward { choices }, Command::ChooseReward { index }) => match index {
+                None => RunState::Running,
+                Some(index) => match choices.get(index).copied().flatten() {
+                    None => RunState::ChooseReward { choices },
+                    Some(card) => {
+                        let mut deck = self.ecs.fetch_mut::<deck::Deck>();
+                        deck.add(card);
+                        RunState::Running
+                    }
+                },
+            },
+            (curr_status, _) => curr_status,
+        };
+
+        let ends_turn = matches!(
+            curr_status,
+            RunState::AwaitingInput | RunState::Targetting { .. }
+        ) && matches!(next_status, RunState::Running | RunState::GenerateMap);
+
+        // moving or passing ends the turn, playing a card cleans up on its own
+        if ends_turn && next_status == RunState::Running {
+            if let Command::Move { .. } | Command::Sneak { .. } | Command::Pass = command {
+                self.end_turn();
+            }
+        }
+
+        self.recording.record(command, ends_turn);
+        self.set_run_state(next_status);
+        next_status
+    }
+
+    // returns if the player is currently reacting, and who they are reacting to
+    pub fn reaction_status(&self) -> (bool, Option<Entity>) {
+        let can_act = self.ecs.read_storage::<CanActFlag>();
+        let player = self.ecs.fetch::<Entity>();
+        let player_can_act = can_act
+            .get(*player)
+            .expect("player_input called, but it is not your turn");
+
+        (player_can_act.is_reaction, player_can_act.reaction_target)
+    }
+
+    pub fn player_point(&self) -> Point {
+        let player = self.ecs.fetch::<Entity>();
+        let positions = self.ecs.read_storage::<Position>();
+        let player_pos = positions
+            .get(*player)
+            .expect("player didn't have a position");
+
+        player_pos.as_point()
+    }
+
+    /// The tiles the player can currently target with the given attack
+    pub fn valid_targets(&self, attack_type: &AttackType) -> Vec<Point> {
+        let range_type = move_type::get_attack_range(attack_type);
+        let tiles_in_range = range_type::resolve_range_at(&range_type, self.player_point());
+
+        let players = self.ecs.read_storage::<Player>();
+        let viewsheds = self.ecs.read_storage::<Viewshed>();
+        let mut available_cells = Vec::new();
+
+        for (_player, viewshed) in (&players, &viewsheds).join() {
+            for idx in viewshed.visible.iter() {
+                if tiles_in_range.contains(idx) {
+                    available_cells.push(*idx);
+                }
+            }
+        }
+
+        available_cells
+    }
+
+    /// Every command that would do something at the current decision point
+    pub fn legal_commands(&self) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.run_state() {
+            RunState::AwaitingInput => {
+                let (is_reaction, _) = self.reaction_status();
+                let hand_size = self.ecs.fetch::<deck::Deck>().hand.len();
+
+                if !is_reaction {
+                    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                        if self.can_move(dx, dy) {
+                            commands.push(Command::Move { dx, dy });
+                            commands.push(Command::Sneak { dx, dy });
+                        }
+                    }
+                }
+
+                commands.push(Command::Pass);
+                for index in 0..hand_size {
+                    if !is_reaction || self.can_react_with(index) {
+                        commands.push(Command::SelectCard { index });
+                    }
+                }
+            }
+            RunState::Targetting {
+                attack_type,
+                ignore_targetting,
+            } => {
+                if ignore_targetting {
+                    commands.push(Command::Target { point: None });
+                } else {
+                    for point in self.valid_targets(&attack_type) {
+                        commands.push(Command::Target { point: Some(point) });
+                    }
+                }
+
+                commands.push(Command::CancelTarget);
+            }
+            RunState::ChooseReward { choices } => {
+                for (index, choice) in choices.iter().enumerate() {
+                    if choice.is_some() {
+                        commands.push(Command::ChooseReward { index: Some(index) });
+                    }
+                }
+
+                commands.push(Command::ChooseReward { index: None });
+            }
+            _ => {}
+        }
+
+        commands
+    }
+
+    /// The modifier that will be applied to the next card played
+    pub fn attack_modifier(&self) -> Option<AttackType> {
+        self.attack_modifier
+    }
+
+    // same checks as try_move_player, without moving
+    fn can_move(&self, dx: i32, dy: i32) -> bool {
+        use std::cmp::{max, min};
+        let map = self.ecs.fetch::<Map>();
+        let openables = self.ecs.read_storage::<Openable>();
+        let player_pos = self.player_point();
+
+        let new_x = min(map.width, max(0, player_pos.x + dx));
+        let new_y = min(map.height, max(0, player_pos.y + dy));
+        let dest_index = map.get_index(new_x, new_y);
+
+        if dest_index == map.level_exit || !map.blocked_tiles[dest_index] {
+            return true;
+        }
+
+        !map.tiles[dest_index].blocks_movement()
+            && map
+                .creature_map
+                .get(&dest_index)
+                .map_or(false, |ent| openables.contains(*ent))
+    }
+
+    // same checks as select_card when reacting
+    fn can_react_with(&self, index: usize) -> bool {
+        let (_, reaction_target) = self.reaction_status();
+        let deck = self.ecs.fetch::<deck::Deck>();
+        let positions = self.ecs.read_storage::<Position>();
+        let attack_type = deck.hand[index];
+
+        if move_type::get_attack_timing(&attack_type) == AttackTiming::Slow {
+            return false;
+        }
+
+        // untargetted cards like blocks work against anything
+        let untargetted = move_type::get_attack_shape(&attack_type) == RangeType::Empty;
+
+        match reaction_target {
+            None => false,
+            Some(_) if untargetted => true,
+            Some(target) => match positions.get(target) {
+                None => true,
+                Some(target_pos) => move_type::is_attack_valid(
+                    &attack_type,
+                    self.player_point(),
+                    target_pos.as_point(),
+                )
+                .is_some(),
+            },
+        }
+    }
+
+    fn select_card(&mut self, index: usize) -> RunState {
+        let (is_reaction, reaction_target) = self.reaction_status();
+        let player_point = self.player_point();
+        let mut deck = self.ecs.fetch_mut::<deck::Deck>();
+        let positions = self.ecs.read_storage::<Position>();
+
+        // don't process the input if the selection doesn't exist
+        if deck.hand.len() <= index {
+            return RunState::AwaitingInput;
+        }
+
+        deck.selected = index as i32;
+        let attack_type = deck.hand[index];
+        // empty-shaped moves are not targetted
+        let ignore_targetting = move_type::get_attack_shape(&attack_type) == RangeType::Empty;
+
+        // if we are counter attacking, only allow moves that can hit
+        // unselect the card if we end up quitting
+        if is_reaction {
+            if move_type::get_attack_timing(&attack_type) == AttackTiming::Slow {
+                deck.selected = -1;
+                return RunState::AwaitingInput;
+            }
+
+            match reaction_target {
+                None => {
+                    deck.selected = -1;
+                    return RunState::AwaitingInput;
+                }
+                Some(_) if ignore_targetting => {}
+                Some(target) => {
+                    if let Some(target_pos) = positions.get(target) {
+                        if move_type::is_attack_valid(
+                            &attack_type,
+                            player_point,
+                            target_pos.as_point(),
+                        )
+                        .is_none()
+                        {
+                            deck.selected = -1;
+                            return RunState::AwaitingInput;
+                        }
+                    }
+                }
+            }
+        }
+
+        RunState::Targetting {
+            attack_type,
+            ignore_targetting,
+        }
+    }
+
+    fn cancel_card(&mut self) -> RunState {
+        let mut deck = self.ecs.fetch_mut::<deck::Deck>();
+        deck.selected = -1;
+        RunState::AwaitingInput
+    }
+
+    fn play_card(&mut self, attack_type: AttackType, target: Option<Point>) -> RunState {
+        {
+            let mut deck = self.ecs.fetch_mut::<deck::Deck>();
+            deck.discard_selected();
+
+            let att_traits = move_type::get_attack_traits(&attack_type);
+            if att_traits.contains(&AttackTrait::Modifier) {
+                self.attack_modifier = Some(attack_type);
+            } else {
+                // we should generally have a target at this point
+                // if we don't have a point, assume its because we won't need one later
+                let target = target.unwrap_or(Point::zero());
+                let mut intent =
+                    move_type::get_attack_intent(&attack_type, target, self.attack_modifier);
+                let player = self.ecs.fetch::<Entity>();
+                if let Some(equipped) = self.ecs.read_storage::<Equipped>().get(*player) {
+                    intent.bonus = equipped.bonus();
+                }
+
+                let mut attacks = self.ecs.write_storage::<AttackIntent>();
+                let mut intents = self.ecs.fetch_mut::<IntentData>();
+                intents.prev_outgoing_intent = Some(intent);
+                intents.outgoing_blocked = false;
+
+                attacks
+                    .insert(*player, intent)
+                    .expect("Failed to insert attack from Player");
+
+                self.attack_modifier = None;
+            }
+        }
+
+        end_turn_cleanup(&mut self.ecs);
+        RunState::Running
+    }
+
+    fn end_turn(&mut self) {
+        end_turn_cleanup(&mut self.ecs);
+
+        // clear out previously revealed intents
+        // this isn't in end_turn_cleanup because we don't want to clear intents from the targetting state
+        let mut intents = self.ecs.fetch_mut::<IntentData>();
+        if !intents.hidden {
+            intents.prev_incoming_intent = None;
+            intents.prev_outgoing_intent = None;
+            intents.chain.clear();
+        }
+    }
+
+    fn entities_need_cleanup(&mut self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let player = self.ecs.read_storage::<Player>();
+
+        let mut to_delete = Vec::new();
+        for entity in entities.join() {
+            let mut should_delete = true;
+
+            // Don't delete the player
+            let p = player.get(entity);
+            if let Some(_p) = p {
+                should_delete = false;
+            }
+
+            if should_delete {
+                to_delete.push(entity);
+            }
+        }
+
+        to_delete
+    }
+
+    pub fn change_level(&mut self) {
+        // Delete everything but the player, what they have equipped is stored on them
+        let to_delete = self.entities_need_cleanup();
+        for target in to_delete {
+            self.ecs
+                .delete_entity(target)
+                .expect("Unable to delete entity");
+        }
+
+        let curr_depth = {
+            let map = self.ecs.fetch::<Map>();
+            map.depth
+        };
+
+        let new_map = map::build_level(&mut self.ecs, map::MAP_W, map::MAP_H, curr_depth + 1);
+
+        // update player position
+        let player = self.ecs.fetch::<Entity>();
+        let mut positions = self.ecs.write_storage::<Position>();
+        let player_pos = positions
+            .get_mut(*player)
+            .expect("player didn't have a position");
+
+        let new_player_pos = new_map.rooms[0].center();
+        player_pos.x = new_player_pos.x;
+        player_pos.y = new_player_pos.y;
+
+        // replace map
+        let mut map_writer = self.ecs.write_resource::<Map>();
+        *map_writer = new_map;
+
+        // Mark the player's visibility as dirty
+        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
+        let vs = viewshed_components.get_mut(*player);
+        if let Some(vs) = vs {
+            vs.dirty = true;
+        }
+    }
+}
+
+fn try_move_player(ecs: &mut World, dx: i32, dy: i32, quiet: bool) -> RunState {
+    use std::cmp::{max, min};
+    let mut positions = ecs.write_storage::<Position>();
+    let players = ecs.read_storage::<Player>();
+    let mut movements = ecs.write_storage::<MoveIntent>();
+    let mut healths = ecs.write_storage::<Health>();
+    let mut scheds = ecs.write_storage::<Schedulable>();
+    let openables = ecs.read_storage::<Openable>();
+    let map = ecs.fetch::<Map>();
+    let player = ecs.fetch::<Entity>();
+    let mut noises = ecs.fetch_mut::<sys_noise::NoiseQueue>();
+
+    for (_player, pos) in (&players, &mut positions).join() {
+        let new_x = min(map.width, max(0, pos.x + dx));
+        let new_y = min(map.height, max(0, pos.y + dy));
+        let dest_index = map.get_index(new_x, new_y);
+
+        if dest_index == map.level_exit {
+            return RunState::GenerateMap;
+        }
+
+        if !map.blocked_tiles[dest_index] {
+            let new_move = MoveIntent {
+                loc: Point::new(new_x, new_y),
+            };
+            movements
+                .insert(*player, new_move)
+                .expect("Failed to insert new movement from player");
+
+            let step = if quiet {
+                // sneaking takes half again as long as a normal step
+                if let Some(sched) = scheds.get_mut(*player) {
+                    sched.current += sched.base / 2;
+                }
+
+                sys_noise::NoiseKind::QuietStep
+            } else {
+                sys_noise::NoiseKind::Step
+            };
+            // walking into a door only opens it, so the step is made from where the player stands
+            let landing = if map.tiles[dest_index] == TileType::Door {
+                Point::new(pos.x, pos.y)
+            } else {
+                Point::new(new_x, new_y)
+            };
+            sys_noise::make_noise(&mut noises, step, landing, Some(*player));
+
+            return RunState::Running;
+        } else if !map.tiles[dest_index].blocks_movement() {
+            if let Some(dest_ent) = map.creature_map.get(&dest_index) {
+                if let Some(_) = openables.get(*dest_ent) {
+                    if let Some(health) = healths.get_mut(*dest_ent) {
+                        health.current = 0;
+                    }
+
+                    return RunState::Running;
+                } else {
+                    // TODO: implement push
+                    let mut log = ecs.fetch_mut::<gamelog::GameLog>();
+                    log.entries
+                        .push(format!("You can't make it through this way"));
+                }
+            }
+
+            return RunState::AwaitingInput;
+        }
+    }
+
+    RunState::AwaitingInput
+}
+
+fn end_turn_cleanup(ecs: &mut World) {
+    // remove can act flag
+    let mut can_act = ecs.write_storage::<CanActFlag>();
+    can_act.clear();
+
+    // clear floating cards
+    let mut builder = ecs.fetch_mut::<ParticleBuilder>();
+    builder.cards.clear();
+}
@@ -1,3 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameLog {
     pub entries: Vec<String>,
+    // whatever landed the final blow on the player
+    #[serde(default)]
+    pub killed_by: Option<String>,
 }
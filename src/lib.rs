@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+pub mod autoplay;
+pub mod cards;
+pub mod colors;
+pub mod components;
+pub mod deck;
+pub mod equipment;
+pub mod events;
+pub mod exchange;
+pub mod game;
+pub mod gamelog;
+pub mod map;
+pub mod map_builders;
+pub mod move_type;
+pub mod observation;
+pub mod replay;
+pub mod ruleset;
+pub mod saveload;
+pub mod spawner;
+pub mod status;
+pub mod sys_ai;
+pub mod sys_attack;
+pub mod sys_death;
+pub mod sys_mapindex;
+pub mod sys_movement;
+pub mod sys_noise;
+pub mod sys_particle;
+pub mod sys_pickup;
+pub mod sys_turn;
+pub mod sys_visibility;
+pub mod templates;
+
+pub use colors::*;
+pub use components::*;
+pub use equipment::{EquipmentType, StatBonus};
+pub use events::*;
+pub use game::{Command, Game, RunSeed};
+pub use map::{Map, TileType};
+pub use move_type::*;
+pub use ruleset::Ruleset;
+pub use status::{StatusEffect, StatusKind};
+pub use sys_ai::Behavior;
+pub use sys_particle::{CardRequest, ParticleBuilder, ParticleRequest};
+
+/// Load every data file in resources/, reporting the first thing wrong with any of them
+/// Frontends call this before anything else, so that a broken file doesn't only show up partway
+/// into a run
+pub fn load_data() -> Result<(), String> {
+    // templates and equipment refer to cards, so those have to be loaded first
+    cards::load_cards().map_err(|err| err.to_string())?;
+    templates::load_templates().map_err(|err| err.to_string())?;
+    equipment::load_equipment().map_err(|err| err.to_string())?;
+    ruleset::load_rulesets().map_err(|err| err.to_string())?;
+
+    Ok(())
+}
+
+/// A hash of every data file that decides how a run plays out, the rules aren't included since
+/// replays keep a full copy of them
+pub fn data_hash() -> u64 {
+    hash_data(&format!(
+        "{} {} {}",
+        cards::data_hash(),
+        templates::data_hash(),
+        equipment::data_hash()
+    ))
+}
+
+// FNV-1a, which unlike the std hasher is guaranteed to give the same hash on every build
+fn hash_data(contents: &str) -> u64 {
+    contents.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum RunState {
+    AwaitingInput,
+    Targetting {
+        attack_type: AttackType,
+        ignore_targetting: bool,
+    },
+    ViewEnemy {
+        index: u32,
+    },
+    ViewCard,
+    Running,
+    HitPause {
+        remaining_time: f32,
+    },
+    ChooseReward {
+        choices: [Option<AttackType>; 4],
+    },
+    GenerateMap,
+    Dead,
+}
+
+pub type IntentRolls = (i32, i32, i32, i32);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IntentData {
+    pub hidden: bool,
+    pub incoming_went_first: bool,
+    // the player's card was the attack and the incoming card was a counter to it
+    #[serde(default)]
+    pub countered: bool,
+    pub defender_was_interrupted: bool,
+    // a block soaked up some of the damage from either card
+    pub incoming_blocked: bool,
+    pub outgoing_blocked: bool,
+    pub prev_incoming_intent: Option<AttackIntent>,
+    pub prev_outgoing_intent: Option<AttackIntent>,
+    pub rolls: IntentRolls,
+    // every card in the last exchange, the intents above only cover the player's part in it
+    #[serde(default)]
+    pub chain: Vec<ExchangeLink>,
+}
+
+/// One card played in an exchange
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExchangeLink {
+    pub name: String,
+    pub glyph: rltk::FontCharType,
+    pub intent: AttackIntent,
+    // the link this card answered, the card that started the exchange doesn't answer anything
+    pub reacting_to: Option<usize>,
+    pub speed: i32,
+    pub interrupted: bool,
+}
+
+impl IntentData {
+    pub fn reset(&mut self) {
+        self.hidden = false;
+        self.incoming_went_first = false;
+        self.countered = false;
+        self.defender_was_interrupted = false;
+        self.incoming_blocked = false;
+        self.outgoing_blocked = false;
+        self.rolls = (0, 0, 0, 0);
+        self.chain.clear();
+    }
+}
@@ -1,15 +1,54 @@
-use crate::spawner;
+use crate::{map_builders, spawner, templates};
 use rltk::{Algorithm2D, BaseMap, Point, Rect};
-use specs::Entity;
+use serde::{Deserialize, Serialize};
+use specs::{Entity, WorldExt};
 use std::collections::HashMap;
 use std::convert::TryInto;
 
-#[derive(PartialEq, Copy, Clone)]
+pub const MAP_W: i32 = 79;
+pub const MAP_H: i32 = 50;
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
+    // the way down to the next level, always at `Map.level_exit`
+    DownStairs,
+    // closed doors can be walked into to open them
+    Door,
+    OpenDoor,
+    ShallowWater,
+    Chasm,
+    Rubble,
+}
+
+impl TileType {
+    pub fn blocks_movement(self) -> bool {
+        match self {
+            TileType::Wall | TileType::Chasm | TileType::Rubble => true,
+            _ => false,
+        }
+    }
+
+    pub fn blocks_sight(self) -> bool {
+        match self {
+            TileType::Wall | TileType::Door => true,
+            _ => false,
+        }
+    }
+
+    /// How long it takes to get onto this tile, relative to a step across open floor
+    pub fn move_cost(self) -> f32 {
+        match self {
+            // a turn to open it and a turn to step through
+            TileType::Door => 2.0,
+            TileType::ShallowWater => 1.5,
+            _ => 1.0,
+        }
+    }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Map {
     pub tiles: Vec<TileType>,
     pub rooms: Vec<Rect>,
@@ -17,7 +56,10 @@ pub struct Map {
     pub height: i32,
     pub depth: i32,
     pub color_map: Vec<rltk::RGB>,
+    // entities can't be saved directly, these are restored when loading
+    #[serde(skip)]
     pub item_map: HashMap<usize, specs::Entity>,
+    #[serde(skip)]
     pub creature_map: HashMap<usize, specs::Entity>,
     pub known_tiles: Vec<bool>,
     pub visible_tiles: Vec<bool>,
@@ -27,7 +69,7 @@ pub struct Map {
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx] == TileType::Wall
+        self.tiles[idx].blocks_sight()
     }
 
     fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
@@ -38,16 +80,16 @@ impl BaseMap for Map {
 
         // Cardinal directions
         if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.0))
+            exits.push((idx - 1, self.tiles[idx - 1].move_cost()))
         };
         if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.0))
+            exits.push((idx + 1, self.tiles[idx + 1].move_cost()))
         };
         if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.0))
+            exits.push((idx - w, self.tiles[idx - w].move_cost()))
         };
         if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.0))
+            exits.push((idx + w, self.tiles[idx + w].move_cost()))
         };
 
         exits
@@ -68,13 +110,33 @@ impl Algorithm2D for Map {
 }
 
 impl Map {
+    /// A map of solid wall, for a builder to carve out
+    pub fn new(width: i32, height: i32, depth: i32, color_map: Vec<rltk::RGB>) -> Self {
+        let dim = (width * height).try_into().unwrap();
+
+        Map {
+            tiles: vec![TileType::Wall; dim],
+            rooms: vec![],
+            width,
+            height,
+            depth,
+            color_map,
+            item_map: HashMap::new(),
+            creature_map: HashMap::new(),
+            known_tiles: vec![false; dim],
+            visible_tiles: vec![false; dim],
+            blocked_tiles: vec![false; dim],
+            level_exit: 0,
+        }
+    }
+
     pub fn get_index(&self, x: i32, y: i32) -> usize {
         ((y * self.width) + x) as usize
     }
 
     pub fn set_blocked_tiles(&mut self) {
         for (index, tile) in self.tiles.iter_mut().enumerate() {
-            let is_blocked = *tile == TileType::Wall;
+            let is_blocked = tile.blocks_movement();
             self.blocked_tiles[index] = is_blocked;
         }
     }
@@ -87,12 +149,32 @@ impl Map {
         !self.blocked_tiles[self.get_index(x, y)]
     }
 
-    fn build_room(&mut self, room: Rect) {
+    pub fn carve_tile(&mut self, index: usize) {
+        self.tiles[index] = TileType::Floor;
+        self.color_map[index] = crate::map_floor_color();
+    }
+
+    pub fn fill_tile(&mut self, index: usize, rng: &mut rltk::RandomNumberGenerator) {
+        self.tiles[index] = TileType::Wall;
+        self.color_map[index] = crate::map_wall_color(rng);
+    }
+
+    /// Make the given tile the way down to the next level
+    pub fn place_exit(&mut self, index: usize) {
+        self.tiles[index] = TileType::DownStairs;
+        self.level_exit = index;
+    }
+
+    pub fn open_door(&mut self, index: usize) {
+        if self.tiles[index] == TileType::Door {
+            self.tiles[index] = TileType::OpenDoor;
+        }
+    }
+
+    pub fn build_room(&mut self, room: Rect) {
         for y in room.y1..=room.y2 {
             for x in room.x1..=room.x2 {
-                let index = self.get_index(x, y);
-                self.tiles[index] = TileType::Floor;
-                self.color_map[index] = crate::map_floor_color();
+                self.carve_tile(self.get_index(x, y));
             }
         }
 
@@ -101,7 +183,7 @@ impl Map {
 
     /// Create a hallway of TileType::Floor between the given start and end points
     /// The hallway will always be built horizontally from the start position and vertically from the end position
-    fn build_hallway(&mut self, start: Point, end: Point) {
+    pub fn build_hallway(&mut self, start: Point, end: Point) {
         let xrange;
         let yrange;
 
@@ -121,18 +203,14 @@ impl Map {
             let next_x = start.x + dx;
             let next_y = start.y;
 
-            let index = self.get_index(next_x, next_y);
-            self.tiles[index] = TileType::Floor;
-            self.color_map[index] = crate::map_floor_color();
+            self.carve_tile(self.get_index(next_x, next_y));
         }
 
         for dy in yrange {
             let next_x = end.x;
             let next_y = end.y + dy;
 
-            let index = self.get_index(next_x, next_y);
-            self.tiles[index] = TileType::Floor;
-            self.color_map[index] = crate::map_floor_color();
+            self.carve_tile(self.get_index(next_x, next_y));
         }
     }
 
@@ -166,7 +244,7 @@ impl Map {
 
     pub fn untrack_creature(&mut self, point: Point) -> Option<Entity> {
         let index = self.point2d_to_index(point);
-        self.blocked_tiles[index] = false;
+        self.blocked_tiles[index] = self.tiles[index].blocks_movement();
         self.creature_map.remove(&index)
     }
 
@@ -188,120 +266,73 @@ impl Map {
         // update blocking if needed
         if self.blocked_tiles[prev_index] {
             self.blocked_tiles[next_index] = true;
-            self.blocked_tiles[prev_index] = false;
+            self.blocked_tiles[prev_index] = self.tiles[prev_index].blocks_movement();
         }
 
         true
     }
 }
 
-pub fn build_rogue_map(
-    width: i32,
-    height: i32,
-    depth: i32,
-    rng: &mut rltk::RandomNumberGenerator,
-) -> Map {
+/// A single empty room filling the whole map, for trying things out away from a real level
+pub fn build_arena(width: i32, height: i32) -> Map {
     let dim = (width * height).try_into().unwrap();
-    let mut map = Map {
-        tiles: vec![TileType::Wall; dim],
-        rooms: vec![],
-        width,
-        height,
-        depth,
-        color_map: (0..dim).map(|_| crate::map_wall_color(rng)).collect(),
-        item_map: HashMap::new(),
-        creature_map: HashMap::new(),
-        known_tiles: vec![false; dim],
-        visible_tiles: vec![false; dim],
-        blocked_tiles: vec![false; dim],
-        level_exit: 0,
-    };
-
-    const MAX_ROOMS: i32 = 30;
-    const MIN_ROOM_WIDTH: i32 = 6;
-    const MAX_ROOM_WIDTH: i32 = 12;
-    const MIN_ROOM_HEIGHT: i32 = 6;
-    const MAX_ROOM_HEIGHT: i32 = 12;
-
-    for _ in 0..MAX_ROOMS {
-        let w = rng.range(MIN_ROOM_WIDTH, MAX_ROOM_WIDTH);
-        let h = rng.range(MIN_ROOM_HEIGHT, MAX_ROOM_HEIGHT);
-        let x = rng.range(1, map.width - w - 1);
-        let y = rng.range(1, map.height - h - 1);
-
-        let new_room = Rect::with_size(x, y, w, h);
-        let mut quit = false;
-
-        for other_rooms in map.rooms.iter() {
-            if other_rooms.intersect(&new_room) {
-                quit = true;
-            }
-        }
+    let mut map = Map::new(width, height, 0, vec![crate::map_floor_color(); dim]);
+
+    map.build_room(Rect::with_size(1, 1, width - 3, height - 3));
+    map.set_blocked_tiles();
+    map
+}
+
+// give up if this many levels in a row come out broken, something is wrong with the generators
+const MAX_LEVEL_ATTEMPTS: i32 = 20;
 
-        if quit {
+/// A single empty room with the exit in the far corner. It can't come out broken, so it's what
+/// the player gets if the generators keep failing.
+pub fn build_fallback_level(width: i32, height: i32, depth: i32) -> Map {
+    let mut map = build_arena(width, height);
+    map.depth = depth;
+
+    let room = map.rooms[0];
+    map.place_exit(map.get_index(room.x2, room.y2));
+    map.set_blocked_tiles();
+    map
+}
+
+/// Generate and populate a level, building it again if anything about it is broken
+/// If every attempt fails, the run carries on in an empty fallback level instead
+pub fn build_level(ecs: &mut specs::World, width: i32, height: i32, depth: i32) -> Map {
+    for _ in 0..MAX_LEVEL_ATTEMPTS {
+        let mut map = {
+            let mut rng = ecs.fetch_mut::<rltk::RandomNumberGenerator>();
+            let builder = map_builders::builder_for_depth(depth, &mut rng);
+            builder.build(width, height, depth, &mut rng)
+        };
+
+        if map_builders::validate_map(&map).is_err() {
             continue;
         }
 
-        map.build_room(new_room);
-        if map.rooms.len() > 1 {
-            let new_center = new_room.center();
-            let prev_center = map.rooms[map.rooms.len() - 2].center();
+        // we need to clone the list of rooms so that spawner can borrow the map
+        let cloned_rooms = map.rooms.clone();
+        let mut spawner = spawner::Spawner::new(ecs, &mut map, width);
 
-            if rng.rand::<f32>() > 0.5 {
-                map.build_hallway(prev_center, new_center);
-            } else {
-                map.build_hallway(new_center, prev_center);
+        for room in cloned_rooms.iter().skip(1) {
+            for table in templates::spawn_tables() {
+                spawner.build(&room, table, depth);
             }
         }
-    }
 
-    map.set_blocked_tiles();
-
-    let exit_room = map.rooms.len() - 1;
-    let exit_x = rng.range(map.rooms[exit_room].x1, map.rooms[exit_room].x2);
-    let exit_y = rng.range(map.rooms[exit_room].y1, map.rooms[exit_room].y2);
-    map.level_exit = map.get_index(exit_x, exit_y);
-    println!("{}", map.level_exit);
+        let spawned = spawner.into_spawned();
+        let spawn_points: Vec<Point> = spawned.iter().map(|(_, point)| *point).collect();
 
-    map
-}
+        if map_builders::validate_spawns(&map, &spawn_points).is_ok() {
+            return map;
+        }
 
-pub fn build_level(ecs: &mut specs::World, width: i32, height: i32, depth: i32) -> Map {
-    let mut map = {
-        let mut rng = ecs.fetch_mut::<rltk::RandomNumberGenerator>();
-        build_rogue_map(width, height, depth, &mut rng)
-    };
-
-    // we need to clone the list of rooms so that spawner can borrow the map
-    let cloned_rooms = map.rooms.clone();
-    let mut spawner = spawner::Spawner::new(ecs, &mut map, width);
-
-    for room in cloned_rooms.iter().skip(1) {
-        let quality = depth;
-        let mut spawn_ary = Vec::new();
-        spawn_ary.push(
-            spawner::build_mook as for<'r> fn(&'r mut specs::World, rltk::Point) -> specs::Entity,
-        );
-        spawn_ary.push(spawner::build_archer);
-        spawner.build(
-            &room,
-            0 + quality / 2,
-            2 + quality,
-            vec![0.7, 0.3],
-            spawn_ary,
-        );
-
-        let mut builder_ary = Vec::new();
-        builder_ary.push(
-            spawner::build_exploding_barrel
-                as for<'r> fn(&'r mut specs::World, rltk::Point, i32) -> specs::Entity,
-        );
-        builder_ary.push(spawner::build_health_barrel);
-        builder_ary.push(spawner::build_book_barrel);
-        builder_ary.push(spawner::build_empty_barrel);
-
-        spawner.build_with_quality(&room, 5, 10, depth, vec![0.2, 0.3, 0.1, 0.4], builder_ary);
+        for (ent, _) in spawned {
+            ecs.delete_entity(ent).expect("Unable to delete entity");
+        }
     }
 
-    map
+    build_fallback_level(width, height, depth)
 }
@@ -0,0 +1,101 @@
+use super::MapBuilder;
+use crate::Map;
+use rltk::{RandomNumberGenerator, Rect};
+
+/// Splits the map in two over and over, and puts a room in each of the pieces that are left.
+/// Rooms are joined in the order they were split out, so neighbours are always close together.
+pub struct BspBuilder;
+
+// pieces are never split smaller than this
+const MIN_LEAF_SIZE: i32 = 12;
+const MIN_ROOM_SIZE: i32 = 4;
+
+impl MapBuilder for BspBuilder {
+    fn name(&self) -> &'static str {
+        "bsp"
+    }
+
+    fn build(&self, width: i32, height: i32, depth: i32, rng: &mut RandomNumberGenerator) -> Map {
+        let mut map = super::solid_map(width, height, depth, rng);
+
+        let mut leaves = Vec::new();
+        split(
+            Rect::with_exact(1, 1, width - 2, height - 2),
+            rng,
+            &mut leaves,
+        );
+
+        for leaf in leaves {
+            // leave at least a tile of wall around the room, so rooms never run into each other
+            let w = rng.range(MIN_ROOM_SIZE, leaf.width() - 1);
+            let h = rng.range(MIN_ROOM_SIZE, leaf.height() - 1);
+            let x = leaf.x1 + rng.range(1, leaf.width() - w);
+            let y = leaf.y1 + rng.range(1, leaf.height() - h);
+
+            let new_room = Rect::with_size(x, y, w, h);
+            map.build_room(new_room);
+
+            if map.rooms.len() > 1 {
+                let new_center = new_room.center();
+                let prev_center = map.rooms[map.rooms.len() - 2].center();
+
+                if rng.rand::<f32>() > 0.5 {
+                    map.build_hallway(prev_center, new_center);
+                } else {
+                    map.build_hallway(new_center, prev_center);
+                }
+            }
+        }
+
+        if let Some(exit_room) = map.rooms.last().copied() {
+            super::place_exit_in_room(&mut map, exit_room, rng);
+        }
+
+        super::terrain::add_doors(&mut map, rng);
+        super::terrain::add_terrain(&mut map, rng);
+        map.set_blocked_tiles();
+
+        map
+    }
+}
+
+fn split(area: Rect, rng: &mut RandomNumberGenerator, leaves: &mut Vec<Rect>) {
+    let can_split_x = area.width() >= MIN_LEAF_SIZE * 2;
+    let can_split_y = area.height() >= MIN_LEAF_SIZE * 2;
+
+    // split across the longer side, so the pieces don't get too thin
+    let split_x = match (can_split_x, can_split_y) {
+        (false, false) => {
+            leaves.push(area);
+            return;
+        }
+        (true, true) => area.width() >= area.height(),
+        (split_x, _) => split_x,
+    };
+
+    if split_x {
+        let cut = area.x1 + rng.range(MIN_LEAF_SIZE, area.width() - MIN_LEAF_SIZE + 1);
+        split(
+            Rect::with_exact(area.x1, area.y1, cut, area.y2),
+            rng,
+            leaves,
+        );
+        split(
+            Rect::with_exact(cut, area.y1, area.x2, area.y2),
+            rng,
+            leaves,
+        );
+    } else {
+        let cut = area.y1 + rng.range(MIN_LEAF_SIZE, area.height() - MIN_LEAF_SIZE + 1);
+        split(
+            Rect::with_exact(area.x1, area.y1, area.x2, cut),
+            rng,
+            leaves,
+        );
+        split(
+            Rect::with_exact(area.x1, cut, area.x2, area.y2),
+            rng,
+            leaves,
+        );
+    }
+}
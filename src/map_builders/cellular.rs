@@ -0,0 +1,106 @@
+use super::MapBuilder;
+use crate::{Map, TileType};
+use rltk::{Point, RandomNumberGenerator};
+
+/// Caves grown from noise. Each pass, a tile becomes wall if most of the tiles around it are
+/// wall, which smooths the noise out into open caverns.
+pub struct CellularBuilder;
+
+const FLOOR_CHANCE: i32 = 55;
+const SMOOTHING_PASSES: i32 = 12;
+const PILLAR_PASSES: i32 = 4;
+
+impl MapBuilder for CellularBuilder {
+    fn name(&self) -> &'static str {
+        "cellular"
+    }
+
+    fn build(&self, width: i32, height: i32, depth: i32, rng: &mut RandomNumberGenerator) -> Map {
+        let mut map = super::solid_map(width, height, depth, rng);
+        let mut is_wall: Vec<bool> = (0..map.tiles.len())
+            .map(|index| {
+                let (x, y) = (index as i32 % width, index as i32 / width);
+                !super::is_interior(&map, x, y) || rng.range(0, 100) >= FLOOR_CHANCE
+            })
+            .collect();
+
+        for pass in 0..SMOOTHING_PASSES {
+            let prev = is_wall.clone();
+
+            for y in 1..height - 1 {
+                for x in 1..width - 1 {
+                    let mut walls = 0;
+                    for dy in -1..=1 {
+                        for dx in -1..=1 {
+                            if prev[map.get_index(x + dx, y + dy)] {
+                                walls += 1;
+                            }
+                        }
+                    }
+
+                    // early on, completely open spaces get a pillar so caves don't end up as one
+                    // big room, and the later passes smooth the pillars back out
+                    let pillar = pass < PILLAR_PASSES && walls == 0;
+                    is_wall[map.get_index(x, y)] = walls >= 5 || pillar;
+                }
+            }
+        }
+
+        for (index, wall) in is_wall.iter().enumerate() {
+            if !wall {
+                map.carve_tile(index);
+            }
+        }
+
+        let start = start_in_largest_cave(&map, Point::new(width / 2, height / 2));
+        super::finish_open_map(&mut map, start, rng);
+
+        map
+    }
+}
+
+// the smoothing leaves behind pockets that don't connect to anything, so start in the biggest
+// cave, as close to the given point as possible
+fn start_in_largest_cave(map: &Map, target: Point) -> Point {
+    let distance = |point: Point| (point.x - target.x).abs() + (point.y - target.y).abs();
+    let to_point = |index: usize| Point::new(index as i32 % map.width, index as i32 / map.width);
+    let mut in_cave = vec![false; map.tiles.len()];
+    let mut best: Option<(usize, Point)> = None;
+
+    for index in 0..map.tiles.len() {
+        if map.tiles[index] != TileType::Floor || in_cave[index] {
+            continue;
+        }
+
+        // flood fill the cave this tile is in
+        let mut size = 0;
+        let mut closest = to_point(index);
+        let mut frontier = vec![index];
+        in_cave[index] = true;
+
+        while let Some(curr) = frontier.pop() {
+            let point = to_point(curr);
+            size += 1;
+            if distance(point) < distance(closest) {
+                closest = point;
+            }
+
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let next = map.get_index(point.x + dx, point.y + dy);
+                if super::is_interior(map, point.x + dx, point.y + dy)
+                    && map.tiles[next] == TileType::Floor
+                    && !in_cave[next]
+                {
+                    in_cave[next] = true;
+                    frontier.push(next);
+                }
+            }
+        }
+
+        if best.map_or(true, |(best_size, _)| size > best_size) {
+            best = Some((size, closest));
+        }
+    }
+
+    best.map_or(target, |(_, start)| start)
+}
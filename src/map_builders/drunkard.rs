@@ -0,0 +1,54 @@
+use super::MapBuilder;
+use crate::{Map, TileType};
+use rltk::{Point, RandomNumberGenerator};
+
+/// Winding tunnels dug out by walkers stumbling around at random, each one starting somewhere
+/// already dug out
+pub struct DrunkardBuilder;
+
+const FLOOR_PERCENT: usize = 40;
+const WALK_LENGTH: i32 = 200;
+
+impl MapBuilder for DrunkardBuilder {
+    fn name(&self) -> &'static str {
+        "drunkard"
+    }
+
+    fn build(&self, width: i32, height: i32, depth: i32, rng: &mut RandomNumberGenerator) -> Map {
+        let mut map = super::solid_map(width, height, depth, rng);
+        let start = Point::new(width / 2, height / 2);
+        let target_floor = map.tiles.len() * FLOOR_PERCENT / 100;
+
+        let mut dug = vec![map.get_index(start.x, start.y)];
+        map.carve_tile(dug[0]);
+
+        while dug.len() < target_floor {
+            let walk_start = dug[rng.range(0, dug.len())];
+            let mut curr = Point::new(walk_start as i32 % width, walk_start as i32 / width);
+
+            for _ in 0..WALK_LENGTH {
+                let (dx, dy) = match rng.range(0, 4) {
+                    0 => (-1, 0),
+                    1 => (1, 0),
+                    2 => (0, -1),
+                    _ => (0, 1),
+                };
+
+                if !super::is_interior(&map, curr.x + dx, curr.y + dy) {
+                    continue;
+                }
+
+                curr = Point::new(curr.x + dx, curr.y + dy);
+                let index = map.get_index(curr.x, curr.y);
+                if map.tiles[index] == TileType::Wall {
+                    map.carve_tile(index);
+                    dug.push(index);
+                }
+            }
+        }
+
+        super::finish_open_map(&mut map, start, rng);
+
+        map
+    }
+}
@@ -0,0 +1,111 @@
+use super::MapBuilder;
+use crate::{Map, TileType};
+use rltk::{Point, RandomNumberGenerator, Rect};
+
+/// A maze of one-tile corridors, with a few rooms knocked into it to fight in. Some extra walls
+/// are opened up so that there is more than one way around.
+pub struct MazeBuilder;
+
+const ROOM_ATTEMPTS: i32 = 20;
+const MIN_ROOM_SIZE: i32 = 4;
+const MAX_ROOM_SIZE: i32 = 8;
+// out of every hundred maze cells, how many get an extra opening
+const LOOP_PERCENT: i32 = 10;
+
+impl MapBuilder for MazeBuilder {
+    fn name(&self) -> &'static str {
+        "maze"
+    }
+
+    fn build(&self, width: i32, height: i32, depth: i32, rng: &mut RandomNumberGenerator) -> Map {
+        let mut map = super::solid_map(width, height, depth, rng);
+
+        // maze cells sit on odd coordinates, with the walls between them on the even ones
+        let cols = (width - 1) / 2;
+        let rows = (height - 1) / 2;
+        carve_maze(&mut map, cols, rows, rng);
+
+        for _ in 0..cols * rows * LOOP_PERCENT / 100 {
+            open_wall(&mut map, rng);
+        }
+
+        for _ in 0..ROOM_ATTEMPTS {
+            let w = rng.range(MIN_ROOM_SIZE, MAX_ROOM_SIZE);
+            let h = rng.range(MIN_ROOM_SIZE, MAX_ROOM_SIZE);
+            let x = rng.range(1, width - w - 1);
+            let y = rng.range(1, height - h - 1);
+            let new_room = Rect::with_size(x, y, w, h);
+
+            if !map.rooms.iter().any(|other| other.intersect(&new_room)) {
+                map.build_room(new_room);
+            }
+        }
+
+        if let Some(start) = map.rooms.first().map(|room| room.center()) {
+            let distances = super::distances_from(&map, start);
+            super::place_exit_far_from(&mut map, &distances, rng);
+
+            super::terrain::add_doors(&mut map, rng);
+            super::terrain::add_terrain(&mut map, rng);
+        }
+
+        map.set_blocked_tiles();
+
+        map
+    }
+}
+
+// a depth first walk over the cells, knocking down the wall to each new cell it visits
+fn carve_maze(map: &mut Map, cols: i32, rows: i32, rng: &mut RandomNumberGenerator) {
+    let cell_point = |cell: i32| Point::new(cell % cols * 2 + 1, cell / cols * 2 + 1);
+    let mut visited = vec![false; (cols * rows) as usize];
+
+    let first = rng.range(0, cols * rows);
+    let mut stack = vec![first];
+    visited[first as usize] = true;
+    let first_point = cell_point(first);
+    map.carve_tile(map.get_index(first_point.x, first_point.y));
+
+    while let Some(&curr) = stack.last() {
+        let (cx, cy) = (curr % cols, curr / cols);
+        let unvisited: Vec<i32> = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .filter(|(dx, dy)| cx + dx >= 0 && cx + dx < cols && cy + dy >= 0 && cy + dy < rows)
+            .map(|(dx, dy)| (cy + dy) * cols + cx + dx)
+            .filter(|next| !visited[*next as usize])
+            .collect();
+
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let next = unvisited[rng.range(0, unvisited.len())];
+        let (curr_point, next_point) = (cell_point(curr), cell_point(next));
+        let between = Point::new(
+            (curr_point.x + next_point.x) / 2,
+            (curr_point.y + next_point.y) / 2,
+        );
+
+        map.carve_tile(map.get_index(between.x, between.y));
+        map.carve_tile(map.get_index(next_point.x, next_point.y));
+        visited[next as usize] = true;
+        stack.push(next);
+    }
+}
+
+// open up a random wall with corridor on either side of it
+fn open_wall(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    let x = rng.range(1, map.width - 1);
+    let y = rng.range(1, map.height - 1);
+    let is_floor = |x: i32, y: i32| {
+        super::is_interior(map, x, y) && map.tiles[map.get_index(x, y)] == TileType::Floor
+    };
+
+    let joins_corridors =
+        (is_floor(x - 1, y) && is_floor(x + 1, y)) || (is_floor(x, y - 1) && is_floor(x, y + 1));
+    if joins_corridors {
+        let index = map.get_index(x, y);
+        map.carve_tile(index);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{Map, TileType};
+use rltk::{Point, RandomNumberGenerator, Rect};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+
+mod bsp;
+mod cellular;
+mod drunkard;
+mod maze;
+mod rogue;
+mod terrain;
+mod validate;
+
+pub use bsp::BspBuilder;
+pub use cellular::CellularBuilder;
+pub use drunkard::DrunkardBuilder;
+pub use maze::MazeBuilder;
+pub use rogue::RogueBuilder;
+pub use validate::{validate_map, validate_spawns, MapError};
+
+/// Lays out a level. Whatever the algorithm, the map that comes back has to be usable by the rest
+/// of the game:
+/// - the player starts in the center of `rooms[0]`, which must be a floor tile
+/// - monsters are spawned into every other room
+/// - `level_exit` is a `DownStairs` tile that can be reached from the start
+/// - every tile that can be walked on can be reached from the start
+///
+/// Builders shouldn't panic if a layout doesn't work out, `validate_map` catches broken maps and
+/// the level is built again.
+pub trait MapBuilder {
+    fn name(&self) -> &'static str;
+    fn build(&self, width: i32, height: i32, depth: i32, rng: &mut RandomNumberGenerator) -> Map;
+}
+
+/// Every generator, for tools that want to look at or test all of them
+pub fn all_builders() -> Vec<Box<dyn MapBuilder>> {
+    vec![
+        Box::new(RogueBuilder),
+        Box::new(BspBuilder),
+        Box::new(CellularBuilder),
+        Box::new(DrunkardBuilder),
+        Box::new(MazeBuilder),
+    ]
+}
+
+pub fn find_builder(name: &str) -> Option<Box<dyn MapBuilder>> {
+    all_builders()
+        .into_iter()
+        .find(|builder| builder.name().eq_ignore_ascii_case(name))
+}
+
+/// Pick the generator for a level. The first floor is always rooms and hallways, and the layouts
+/// get stranger the deeper the player goes.
+pub fn builder_for_depth(depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    let mut choices: Vec<Box<dyn MapBuilder>> = match depth {
+        i32::MIN..=1 => vec![Box::new(RogueBuilder)],
+        2..=3 => vec![Box::new(RogueBuilder), Box::new(BspBuilder)],
+        4..=6 => vec![
+            Box::new(BspBuilder),
+            Box::new(CellularBuilder),
+            Box::new(DrunkardBuilder),
+        ],
+        _ => all_builders(),
+    };
+
+    // don't roll if there's nothing to choose from, so the first floor of a seed stays the same
+    let pick = if choices.len() > 1 {
+        rng.range(0, choices.len())
+    } else {
+        0
+    };
+
+    choices.swap_remove(pick)
+}
+
+// the random wall colors have to be rolled first, so that every map uses up the rng the same way
+fn solid_map(width: i32, height: i32, depth: i32, rng: &mut RandomNumberGenerator) -> Map {
+    let dim = (width * height).try_into().unwrap();
+    let color_map = (0..dim).map(|_| crate::map_wall_color(rng)).collect();
+
+    Map::new(width, height, depth, color_map)
+}
+
+// the edge of the map always stays as wall
+fn is_interior(map: &Map, x: i32, y: i32) -> bool {
+    x > 0 && x < map.width - 1 && y > 0 && y < map.height - 1
+}
+
+// put the exit somewhere in the given room
+fn place_exit_in_room(map: &mut Map, room: Rect, rng: &mut RandomNumberGenerator) {
+    let exit_x = rng.range(room.x1, room.x2);
+    let exit_y = rng.range(room.y1, room.y2);
+    let exit_index = map.get_index(exit_x, exit_y);
+    map.place_exit(exit_index);
+}
+
+// walking distance to each open tile from the start, None for anything that can't be reached
+fn distances_from(map: &Map, start: Point) -> Vec<Option<i32>> {
+    let mut distances = vec![None; map.tiles.len()];
+    let mut frontier = VecDeque::new();
+    let start_index = map.get_index(start.x, start.y);
+
+    distances[start_index] = Some(0);
+    frontier.push_back(start);
+
+    while let Some(curr) = frontier.pop_front() {
+        let curr_dist = distances[map.get_index(curr.x, curr.y)].unwrap_or(0);
+
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let next = Point::new(curr.x + dx, curr.y + dy);
+            if !is_interior(map, next.x, next.y) {
+                continue;
+            }
+
+            let next_index = map.get_index(next.x, next.y);
+            if !map.tiles[next_index].blocks_movement() && distances[next_index].is_none() {
+                distances[next_index] = Some(curr_dist + 1);
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    distances
+}
+
+/// For generators that carve out open space instead of laying out rooms. Anything that can't be
+/// reached from the start is filled back in, open areas are marked out as rooms for spawning,
+/// and the exit goes somewhere far away from the start.
+fn finish_open_map(map: &mut Map, start: Point, rng: &mut RandomNumberGenerator) {
+    const MAX_ROOMS: usize = 16;
+    const ROOM_ATTEMPTS: usize = 300;
+    // rooms smaller than this are mostly corridor, not enough space for a fight
+    const MIN_ROOM_SIZE: i32 = 3;
+
+    let start_index = map.get_index(start.x, start.y);
+    map.carve_tile(start_index);
+
+    let distances = distances_from(map, start);
+    for index in 0..map.tiles.len() {
+        if map.tiles[index] == TileType::Floor && distances[index].is_none() {
+            map.fill_tile(index, rng);
+        }
+    }
+
+    // the room the player starts in
+    map.rooms.push(grow_room(map, start));
+
+    let floor: Vec<usize> = (0..map.tiles.len())
+        .filter(|index| distances[*index].is_some())
+        .collect();
+
+    for _ in 0..ROOM_ATTEMPTS {
+        if map.rooms.len() >= MAX_ROOMS {
+            break;
+        }
+
+        let index = floor[rng.range(0, floor.len())];
+        let seed = Point::new(index as i32 % map.width, index as i32 / map.width);
+        let room = grow_room(map, seed);
+
+        if room.width() >= MIN_ROOM_SIZE
+            && room.height() >= MIN_ROOM_SIZE
+            && !map.rooms.iter().any(|other| other.intersect(&room))
+        {
+            map.rooms.push(room);
+        }
+    }
+
+    place_exit_far_from(map, &distances, rng);
+    terrain::add_terrain(map, rng);
+    map.set_blocked_tiles();
+}
+
+// the exit goes in the furthest part of the map, but not always on the very furthest tile
+fn place_exit_far_from(map: &mut Map, distances: &[Option<i32>], rng: &mut RandomNumberGenerator) {
+    let furthest = distances.iter().flatten().max().copied().unwrap_or(0);
+    let candidates: Vec<usize> = (0..distances.len())
+        .filter(|index| distances[*index].map_or(false, |dist| dist * 10 >= furthest * 9))
+        .collect();
+
+    map.place_exit(candidates[rng.range(0, candidates.len())]);
+}
+
+// expand a rectangle out from a floor tile for as long as it stays all floor
+fn grow_room(map: &Map, seed: Point) -> Rect {
+    const MAX_ROOM_SIZE: i32 = 10;
+
+    let is_floor_row = |x1: i32, x2: i32, y: i32| {
+        (x1..=x2)
+            .all(|x| is_interior(map, x, y) && map.tiles[map.get_index(x, y)] == TileType::Floor)
+    };
+    let is_floor_col = |x: i32, y1: i32, y2: i32| {
+        (y1..=y2)
+            .all(|y| is_interior(map, x, y) && map.tiles[map.get_index(x, y)] == TileType::Floor)
+    };
+
+    let mut room = Rect::with_exact(seed.x, seed.y, seed.x, seed.y);
+    loop {
+        let mut grew = false;
+
+        if room.width() < MAX_ROOM_SIZE && is_floor_col(room.x1 - 1, room.y1, room.y2) {
+            room.x1 -= 1;
+            grew = true;
+        }
+        if room.width() < MAX_ROOM_SIZE && is_floor_col(room.x2 + 1, room.y1, room.y2) {
+            room.x2 += 1;
+            grew = true;
+        }
+        if room.height() < MAX_ROOM_SIZE && is_floor_row(room.x1, room.x2, room.y1 - 1) {
+            room.y1 -= 1;
+            grew = true;
+        }
+        if room.height() < MAX_ROOM_SIZE && is_floor_row(room.x1, room.x2, room.y2 + 1) {
+            room.y2 += 1;
+            grew = true;
+        }
+
+        if !grew {
+            return room;
+        }
+    }
+}
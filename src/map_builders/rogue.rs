@@ -0,0 +1,64 @@
+use super::MapBuilder;
+use crate::Map;
+use rltk::{RandomNumberGenerator, Rect};
+
+/// Rooms scattered at random, each joined to the last by an L-shaped hallway
+pub struct RogueBuilder;
+
+impl MapBuilder for RogueBuilder {
+    fn name(&self) -> &'static str {
+        "rogue"
+    }
+
+    fn build(&self, width: i32, height: i32, depth: i32, rng: &mut RandomNumberGenerator) -> Map {
+        let mut map = super::solid_map(width, height, depth, rng);
+
+        const MAX_ROOMS: i32 = 30;
+        const MIN_ROOM_WIDTH: i32 = 6;
+        const MAX_ROOM_WIDTH: i32 = 12;
+        const MIN_ROOM_HEIGHT: i32 = 6;
+        const MAX_ROOM_HEIGHT: i32 = 12;
+
+        for _ in 0..MAX_ROOMS {
+            let w = rng.range(MIN_ROOM_WIDTH, MAX_ROOM_WIDTH);
+            let h = rng.range(MIN_ROOM_HEIGHT, MAX_ROOM_HEIGHT);
+            let x = rng.range(1, map.width - w - 1);
+            let y = rng.range(1, map.height - h - 1);
+
+            let new_room = Rect::with_size(x, y, w, h);
+            let mut quit = false;
+
+            for other_rooms in map.rooms.iter() {
+                if other_rooms.intersect(&new_room) {
+                    quit = true;
+                }
+            }
+
+            if quit {
+                continue;
+            }
+
+            map.build_room(new_room);
+            if map.rooms.len() > 1 {
+                let new_center = new_room.center();
+                let prev_center = map.rooms[map.rooms.len() - 2].center();
+
+                if rng.rand::<f32>() > 0.5 {
+                    map.build_hallway(prev_center, new_center);
+                } else {
+                    map.build_hallway(new_center, prev_center);
+                }
+            }
+        }
+
+        if let Some(exit_room) = map.rooms.last().copied() {
+            super::place_exit_in_room(&mut map, exit_room, rng);
+        }
+
+        super::terrain::add_doors(&mut map, rng);
+        super::terrain::add_terrain(&mut map, rng);
+        map.set_blocked_tiles();
+
+        map
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{Map, TileType};
+use rltk::{Point, RandomNumberGenerator, Rect};
+
+// out of every hundred rooms, how many get each kind of terrain
+const POOL_PERCENT: i32 = 25;
+const RUBBLE_PERCENT: i32 = 30;
+const CHASM_PERCENT: i32 = 20;
+// out of every hundred doorways, how many get a door
+const DOOR_PERCENT: i32 = 60;
+
+// chasms only open up this deep
+const CHASM_DEPTH: i32 = 3;
+const MAX_POOL_SIZE: i32 = 12;
+const MAX_CHASM_SIZE: i32 = 8;
+const MAX_RUBBLE: i32 = 4;
+
+/// Scatter water, rubble and chasms through the rooms, other than the one the player starts in.
+/// Anything that would cut off part of the level is taken back out.
+pub fn add_terrain(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    let rooms: Vec<Rect> = map.rooms.iter().skip(1).copied().collect();
+
+    for room in rooms {
+        if rng.range(0, 100) < POOL_PERCENT {
+            let size = rng.range(3, MAX_POOL_SIZE + 1);
+            add_patch(map, room, TileType::ShallowWater, size, rng);
+        }
+
+        if rng.range(0, 100) < RUBBLE_PERCENT {
+            let pieces = rng.range(1, MAX_RUBBLE + 1);
+            let mut changed = Vec::new();
+
+            for _ in 0..pieces {
+                let index = random_tile_in(map, room, rng);
+                if map.tiles[index] == TileType::Floor {
+                    map.tiles[index] = TileType::Rubble;
+                    changed.push(index);
+                }
+            }
+
+            keep_if_connected(map, &changed);
+        }
+
+        if map.depth >= CHASM_DEPTH && rng.range(0, 100) < CHASM_PERCENT {
+            let size = rng.range(2, MAX_CHASM_SIZE + 1);
+            let changed = add_patch(map, room, TileType::Chasm, size, rng);
+            keep_if_connected(map, &changed);
+        }
+    }
+}
+
+/// Put doors in some of the gaps where a hallway meets a room
+pub fn add_doors(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    let in_room = |x: i32, y: i32| {
+        map.rooms
+            .iter()
+            .any(|room| x >= room.x1 && x <= room.x2 && y >= room.y1 && y <= room.y2)
+    };
+    let is_wall = |x: i32, y: i32| map.tiles[map.get_index(x, y)] == TileType::Wall;
+
+    let doorways: Vec<usize> = (0..map.tiles.len())
+        .filter(|index| {
+            let (x, y) = (*index as i32 % map.width, *index as i32 / map.width);
+            if !super::is_interior(map, x, y)
+                || map.tiles[*index] != TileType::Floor
+                || in_room(x, y)
+            {
+                return false;
+            }
+
+            // walls on either side, with a room on one end
+            (is_wall(x - 1, y) && is_wall(x + 1, y) && (in_room(x, y - 1) || in_room(x, y + 1)))
+                || (is_wall(x, y - 1)
+                    && is_wall(x, y + 1)
+                    && (in_room(x - 1, y) || in_room(x + 1, y)))
+        })
+        .collect();
+
+    for index in doorways {
+        // a door right behind another one doesn't add anything
+        let next_to_door = [1, map.width]
+            .iter()
+            .flat_map(|offset| vec![index - *offset as usize, index + *offset as usize])
+            .any(|next| map.tiles[next] == TileType::Door);
+
+        if !next_to_door && rng.range(0, 100) < DOOR_PERCENT {
+            map.tiles[index] = TileType::Door;
+        }
+    }
+}
+
+// a random walk through the room, turning the floor it crosses into the given tile
+fn add_patch(
+    map: &mut Map,
+    room: Rect,
+    tile: TileType,
+    size: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Vec<usize> {
+    let start = random_tile_in(map, room, rng);
+    let mut curr = Point::new(start as i32 % map.width, start as i32 / map.width);
+    let mut changed = Vec::new();
+
+    for _ in 0..size {
+        let index = map.get_index(curr.x, curr.y);
+        if map.tiles[index] == TileType::Floor {
+            map.tiles[index] = tile;
+            changed.push(index);
+        }
+
+        let (dx, dy) = match rng.range(0, 4) {
+            0 => (-1, 0),
+            1 => (1, 0),
+            2 => (0, -1),
+            _ => (0, 1),
+        };
+        let next = Point::new(curr.x + dx, curr.y + dy);
+        if next.x >= room.x1 && next.x <= room.x2 && next.y >= room.y1 && next.y <= room.y2 {
+            curr = next;
+        }
+    }
+
+    changed
+}
+
+fn random_tile_in(map: &Map, room: Rect, rng: &mut RandomNumberGenerator) -> usize {
+    let x = rng.range(room.x1, room.x2 + 1);
+    let y = rng.range(room.y1, room.y2 + 1);
+    map.get_index(x, y)
+}
+
+// turn the tiles back into floor if they leave anywhere that can't be walked to from the start
+fn keep_if_connected(map: &mut Map, changed: &[usize]) {
+    if changed.is_empty() {
+        return;
+    }
+
+    let start = map.rooms[0].center();
+    let distances = super::distances_from(map, start);
+    let connected = (0..map.tiles.len())
+        .all(|index| map.tiles[index].blocks_movement() || distances[index].is_some());
+
+    if !connected {
+        for index in changed {
+            map.tiles[*index] = TileType::Floor;
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{Map, TileType};
+use rltk::Point;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Something about a generated level that would break the game
+pub enum MapError {
+    NoRooms,
+    StartNotFloor(Point),
+    ExitNotStairs(Point),
+    ExitOnStart,
+    ExitUnreachable,
+    // somewhere open that the player can never get to
+    Disconnected(Point),
+    RoomOutOfBounds(usize),
+    // too small for the spawner to pick a tile in
+    RoomTooSmall(usize),
+    SpawnOutOfBounds(Point),
+    SpawnNotFloor(Point),
+    SpawnOnExit(Point),
+    SpawnOnStart(Point),
+    SpawnsOverlap(Point),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::NoRooms => write!(f, "there are no rooms"),
+            MapError::StartNotFloor(point) => {
+                write!(f, "the start at {:?} isn't floor", (point.x, point.y))
+            }
+            MapError::ExitNotStairs(point) => {
+                write!(f, "the exit at {:?} isn't stairs", (point.x, point.y))
+            }
+            MapError::ExitOnStart => write!(f, "the exit is on the start"),
+            MapError::ExitUnreachable => write!(f, "the exit can't be reached from the start"),
+            MapError::Disconnected(point) => write!(
+                f,
+                "the tile at {:?} can't be reached from the start",
+                (point.x, point.y)
+            ),
+            MapError::RoomOutOfBounds(index) => write!(f, "room {} is out of bounds", index),
+            MapError::RoomTooSmall(index) => write!(f, "room {} is too small to spawn in", index),
+            MapError::SpawnOutOfBounds(point) => {
+                write!(
+                    f,
+                    "something spawned out of bounds at {:?}",
+                    (point.x, point.y)
+                )
+            }
+            MapError::SpawnNotFloor(point) => {
+                write!(f, "something spawned in a wall at {:?}", (point.x, point.y))
+            }
+            MapError::SpawnOnExit(point) => {
+                write!(
+                    f,
+                    "something spawned on the exit at {:?}",
+                    (point.x, point.y)
+                )
+            }
+            MapError::SpawnOnStart(point) => {
+                write!(
+                    f,
+                    "something spawned on the start at {:?}",
+                    (point.x, point.y)
+                )
+            }
+            MapError::SpawnsOverlap(point) => {
+                write!(f, "more than one thing spawned at {:?}", (point.x, point.y))
+            }
+        }
+    }
+}
+
+/// Check that a freshly generated map keeps the promises made by `MapBuilder`, and that every
+/// open tile can be walked to from the start
+pub fn validate_map(map: &Map) -> Result<(), MapError> {
+    let start = match map.rooms.first() {
+        None => return Err(MapError::NoRooms),
+        Some(room) => room.center(),
+    };
+
+    for (index, room) in map.rooms.iter().enumerate() {
+        if room.x1 < 1 || room.y1 < 1 || room.x2 > map.width - 2 || room.y2 > map.height - 2 {
+            return Err(MapError::RoomOutOfBounds(index));
+        }
+
+        // the starting room is never spawned into
+        if index > 0 && (room.width() < 2 || room.height() < 2) {
+            return Err(MapError::RoomTooSmall(index));
+        }
+    }
+
+    let start_index = map.get_index(start.x, start.y);
+    if map.tiles[start_index] != TileType::Floor {
+        return Err(MapError::StartNotFloor(start));
+    }
+
+    if map.level_exit >= map.tiles.len() {
+        return Err(MapError::ExitUnreachable);
+    }
+
+    let exit = index_to_point(map, map.level_exit);
+    if map.tiles[map.level_exit] != TileType::DownStairs {
+        return Err(MapError::ExitNotStairs(exit));
+    }
+    if map.level_exit == start_index {
+        return Err(MapError::ExitOnStart);
+    }
+
+    let distances = super::distances_from(map, start);
+    if distances[map.level_exit].is_none() {
+        return Err(MapError::ExitUnreachable);
+    }
+
+    let unreachable = (0..map.tiles.len())
+        .find(|index| !map.tiles[*index].blocks_movement() && distances[*index].is_none());
+    if let Some(index) = unreachable {
+        return Err(MapError::Disconnected(index_to_point(map, index)));
+    }
+
+    Ok(())
+}
+
+/// Check where everything on a level was spawned, the player aside
+pub fn validate_spawns(map: &Map, spawns: &[Point]) -> Result<(), MapError> {
+    let start = map.rooms.first().map(|room| room.center());
+    let mut taken = HashSet::new();
+
+    for point in spawns {
+        if point.x < 0 || point.x >= map.width || point.y < 0 || point.y >= map.height {
+            return Err(MapError::SpawnOutOfBounds(*point));
+        }
+
+        let index = map.get_index(point.x, point.y);
+        if map.tiles[index] != TileType::Floor {
+            return Err(MapError::SpawnNotFloor(*point));
+        }
+        if index == map.level_exit {
+            return Err(MapError::SpawnOnExit(*point));
+        }
+        if start == Some(*point) {
+            return Err(MapError::SpawnOnStart(*point));
+        }
+        if !taken.insert(index) {
+            return Err(MapError::SpawnsOverlap(*point));
+        }
+    }
+
+    Ok(())
+}
+
+fn index_to_point(map: &Map, index: usize) -> Point {
+    Point::new(index as i32 % map.width, index as i32 / map.width)
+}
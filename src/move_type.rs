@@ -1,37 +1,58 @@
+use crate::cards::get_card;
 use crate::{AttackIntent, RangeType};
 use rltk::Point;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Copy, Clone, PartialEq)]
-pub enum AttackType {
-    Sweep,
-    Punch,
-    Super,
-    Stun,
-    Quick,
-    Push,
-    Dodge,
-    Ponder,
-    Recover,
-    // enemy specific attacks
-    Haymaker,
-    Ranged,
-}
-
-#[derive(PartialEq, Copy, Clone)]
+/// One of the cards defined in the card file
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AttackType(pub(crate) usize);
+
+// cards are saved by name, so that saves survive cards being added or reordered
+impl Serialize for AttackType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&get_card(self).name)
+    }
+}
+
+impl<'de> Deserialize<'de> for AttackType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        crate::cards::find_card(&name)
+            .ok_or_else(|| de::Error::custom(format!("unknown card \"{}\"", name)))
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum AttackTiming {
     Slow,
     Fast,
 }
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum AttackTrait {
     Damage,
-    Knockback { amount: i32 },
+    Knockback {
+        amount: i32,
+    },
     Movement,
     Modifier,
-    Equipment,
-    Draw { amount: i32 },
-    Heal { amount: i32 },
+    Draw {
+        amount: i32,
+    },
+    Heal {
+        amount: i32,
+    },
+    Status {
+        kind: crate::StatusKind,
+        duration: i32,
+    },
+    Block {
+        amount: i32,
+    },
+    Parry,
+    Brace {
+        amount: i32,
+    },
 }
 
 // check if an attack is can be executed
@@ -65,6 +86,7 @@ pub fn get_attack_intent(
         main: *attack_type,
         modifier: attack_modifier,
         loc,
+        bonus: crate::StatBonus::default(),
     }
 }
 
@@ -89,15 +111,16 @@ pub fn get_intent_name(intent: &AttackIntent) -> String {
 }
 
 pub fn get_intent_power(intent: &AttackIntent) -> i32 {
-    get_intent_stat(intent, get_attack_power, |x, y| std::cmp::max(x + y, 0))
+    let power = get_intent_stat(intent, get_attack_power, |x, y| std::cmp::max(x + y, 0));
+    std::cmp::max(power + intent.bonus.power, 0)
 }
 
 pub fn get_intent_speed(intent: &AttackIntent) -> i32 {
-    get_intent_stat(intent, get_attack_speed, |x, y| x + y)
+    get_intent_stat(intent, get_attack_speed, |x, y| x + y) + intent.bonus.speed
 }
 
 pub fn get_intent_guard(intent: &AttackIntent) -> i32 {
-    get_intent_stat(intent, get_attack_guard, |x, y| x + y)
+    get_intent_stat(intent, get_attack_guard, |x, y| x + y) + intent.bonus.guard
 }
 
 pub fn get_intent_traits(intent: &AttackIntent) -> Vec<AttackTrait> {
@@ -113,131 +136,33 @@ pub fn get_intent_traits(intent: &AttackIntent) -> Vec<AttackTrait> {
 }
 
 pub fn get_attack_range(attack_type: &AttackType) -> RangeType {
-    match attack_type {
-        AttackType::Sweep => RangeType::Single,
-        AttackType::Punch => RangeType::Square { size: 1 },
-        AttackType::Super => RangeType::Empty,
-        AttackType::Stun => RangeType::Square { size: 1 },
-        AttackType::Quick => RangeType::Empty,
-        AttackType::Push => RangeType::Square { size: 1 },
-        AttackType::Dodge => RangeType::Square { size: 2 },
-        AttackType::Ponder => RangeType::Empty,
-        AttackType::Recover => RangeType::Single,
-        AttackType::Haymaker => RangeType::Square { size: 1 },
-        AttackType::Ranged => RangeType::Square { size: 3 },
-    }
+    get_card(attack_type).range.clone()
 }
 
 pub fn get_attack_power(attack_type: &AttackType) -> i32 {
-    match attack_type {
-        AttackType::Sweep => 1,
-        AttackType::Punch => 1,
-        AttackType::Super => 2,
-        AttackType::Stun => 0,
-        AttackType::Quick => -1,
-        AttackType::Push => 0,
-        AttackType::Dodge => 0,
-        AttackType::Ponder => 0,
-        AttackType::Recover => 0,
-        AttackType::Haymaker => 3,
-        AttackType::Ranged => 1,
-    }
+    get_card(attack_type).power
 }
 
 pub fn get_attack_shape(attack_type: &AttackType) -> RangeType {
-    match attack_type {
-        AttackType::Sweep => RangeType::Square { size: 1 },
-        AttackType::Punch => RangeType::Single,
-        AttackType::Super => RangeType::Empty,
-        AttackType::Stun => RangeType::Single,
-        AttackType::Quick => RangeType::Empty,
-        AttackType::Push => RangeType::Single,
-        AttackType::Dodge => RangeType::Single,
-        AttackType::Ponder => RangeType::Empty,
-        AttackType::Recover => RangeType::Single,
-        AttackType::Haymaker => RangeType::Single,
-        AttackType::Ranged => RangeType::Single,
-    }
+    get_card(attack_type).shape.clone()
 }
 
 pub fn get_attack_speed(attack_type: &AttackType) -> i32 {
-    match attack_type {
-        AttackType::Sweep => 0,
-        AttackType::Punch => 1,
-        AttackType::Super => -2,
-        AttackType::Stun => 2,
-        AttackType::Quick => 4,
-        AttackType::Push => 0,
-        AttackType::Dodge => 2,
-        AttackType::Ponder => 0,
-        AttackType::Recover => 0,
-        AttackType::Haymaker => -4,
-        AttackType::Ranged => 0,
-    }
+    get_card(attack_type).speed
 }
 
 pub fn get_attack_guard(attack_type: &AttackType) -> i32 {
-    match attack_type {
-        AttackType::Sweep => 0,
-        AttackType::Punch => 0,
-        AttackType::Super => 1,
-        AttackType::Stun => 0,
-        AttackType::Quick => -2,
-        AttackType::Push => 0,
-        AttackType::Dodge => -2,
-        AttackType::Ponder => 0,
-        AttackType::Recover => 0,
-        AttackType::Haymaker => 2,
-        AttackType::Ranged => -4,
-    }
+    get_card(attack_type).guard
 }
 
 pub fn get_attack_name(attack_type: &AttackType) -> String {
-    let name = match attack_type {
-        AttackType::Sweep => "sweep",
-        AttackType::Punch => "punch",
-        AttackType::Super => "super",
-        AttackType::Stun => "stun",
-        AttackType::Quick => "quick",
-        AttackType::Push => "push",
-        AttackType::Dodge => "dodge",
-        AttackType::Ponder => "ponder",
-        AttackType::Recover => "recover",
-        AttackType::Haymaker => "haymaker",
-        AttackType::Ranged => "shoot",
-    };
-
-    name.to_string()
+    get_card(attack_type).name.clone()
 }
 
 pub fn get_attack_timing(attack_type: &AttackType) -> AttackTiming {
-    match attack_type {
-        AttackType::Sweep => AttackTiming::Fast,
-        AttackType::Punch => AttackTiming::Fast,
-        AttackType::Super => AttackTiming::Slow,
-        AttackType::Stun => AttackTiming::Fast,
-        AttackType::Quick => AttackTiming::Slow,
-        AttackType::Push => AttackTiming::Slow,
-        AttackType::Dodge => AttackTiming::Fast,
-        AttackType::Ponder => AttackTiming::Slow,
-        AttackType::Recover => AttackTiming::Slow,
-        AttackType::Haymaker => AttackTiming::Slow,
-        AttackType::Ranged => AttackTiming::Slow,
-    }
+    get_card(attack_type).timing
 }
 
 pub fn get_attack_traits(attack_type: &AttackType) -> Vec<AttackTrait> {
-    match attack_type {
-        AttackType::Sweep => vec![AttackTrait::Damage],
-        AttackType::Punch => vec![AttackTrait::Damage],
-        AttackType::Super => vec![AttackTrait::Damage, AttackTrait::Modifier],
-        AttackType::Stun => vec![AttackTrait::Damage],
-        AttackType::Quick => vec![AttackTrait::Modifier],
-        AttackType::Push => vec![AttackTrait::Knockback { amount: 2 }],
-        AttackType::Dodge => vec![AttackTrait::Movement],
-        AttackType::Ponder => vec![AttackTrait::Draw { amount: 2 }],
-        AttackType::Recover => vec![AttackTrait::Heal { amount: 2 }],
-        AttackType::Haymaker => vec![AttackTrait::Damage],
-        AttackType::Ranged => vec![AttackTrait::Damage],
-    }
+    get_card(attack_type).traits.clone()
 }
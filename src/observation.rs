@@ -0,0 +1,310 @@
+use crate::*;
+use serde::Serialize;
+
+/// Everything the player could know about the game at a decision point, in a form that can be
+/// handed to an agent outside of the game. Only what the player can see is included.
+#[derive(Serialize)]
+pub struct Observation {
+    pub state: RunState,
+    pub done: bool,
+    pub turn: u32,
+    pub player: PlayerView,
+    // the same stealth indicator the player sees
+    pub stealth: sys_noise::Stealth,
+    // set while the player is reacting, matches the id of one of the creatures
+    pub reacting_to: Option<u32>,
+    pub map: MapView,
+    pub creatures: Vec<CreatureView>,
+    pub hand: Vec<CardView>,
+    pub selected: Option<usize>,
+    pub pending_modifier: Option<CardView>,
+    pub cards_remaining: i32,
+    pub cards_discarded: i32,
+    pub incoming: Option<IntentView>,
+    pub outgoing: Option<IntentView>,
+    // the rolls are only known once both intents are revealed
+    pub rolls: Option<IntentRolls>,
+    // the incoming card was played in answer to the outgoing one, so the outgoing card attacked
+    pub countered: bool,
+    // every card in the last exchange, in the order they were played
+    pub exchange: Vec<LinkView>,
+    pub messages: Vec<String>,
+    pub killed_by: Option<String>,
+    pub legal_actions: Vec<Command>,
+}
+
+#[derive(Serialize)]
+pub struct PlayerView {
+    pub x: i32,
+    pub y: i32,
+    pub hp: i32,
+    pub max_hp: i32,
+    pub status: Vec<StatusEffect>,
+    pub equipment: Vec<EquipmentType>,
+}
+
+/// Tiles are listed row by row, so tile (x, y) is at index y * width + x
+#[derive(Serialize)]
+pub struct MapView {
+    pub width: i32,
+    pub height: i32,
+    pub depth: i32,
+    // None for tiles that haven't been seen yet
+    pub tiles: Vec<Option<TileType>>,
+    pub visible: Vec<bool>,
+    // only set once the exit has been seen
+    pub exit: Option<(i32, i32)>,
+}
+
+#[derive(Serialize)]
+pub struct CreatureView {
+    pub id: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub hp: i32,
+    pub max_hp: i32,
+    // barrels and the like have health, but won't fight back
+    pub hostile: bool,
+    pub status: Vec<StatusEffect>,
+    // only for enemies that play from a deck
+    pub deck: Option<DeckView>,
+}
+
+/// What can be told about an enemy's deck from the outside, the hand itself stays hidden
+#[derive(Serialize)]
+pub struct DeckView {
+    pub cards_remaining: i32,
+    pub hand_size: usize,
+    pub discard: Vec<AttackType>,
+}
+
+#[derive(Serialize)]
+pub struct CardView {
+    pub name: String,
+    pub power: i32,
+    pub speed: i32,
+    pub guard: i32,
+    pub timing: AttackTiming,
+    pub traits: Vec<AttackTrait>,
+}
+
+#[derive(Serialize)]
+pub struct IntentView {
+    pub card: CardView,
+    pub modifier: Option<CardView>,
+    pub target: (i32, i32),
+    pub power: i32,
+    pub speed: i32,
+    pub guard: i32,
+}
+
+#[derive(Serialize)]
+pub struct LinkView {
+    pub name: String,
+    pub intent: IntentView,
+    // index of the card this one answered
+    pub reacting_to: Option<usize>,
+    pub speed: i32,
+    pub interrupted: bool,
+}
+
+impl Observation {
+    /// Observe the game as it currently stands. `seen_messages` is how much of the log has
+    /// already been passed along, only newer messages are included.
+    pub fn new(game: &Game, seen_messages: usize) -> Self {
+        let state = game.run_state();
+        let player_pos = game.player_point();
+
+        let player = {
+            let player = game.ecs.fetch::<Entity>();
+            let healths = game.ecs.read_storage::<Health>();
+            let health = healths.get(*player).expect("Player didn't have health");
+            let statuses = game.ecs.read_storage::<StatusEffects>();
+            let equipped = game.ecs.read_storage::<Equipped>();
+
+            PlayerView {
+                x: player_pos.x,
+                y: player_pos.y,
+                hp: health.current,
+                max_hp: health.max,
+                status: status_of(&statuses, *player),
+                equipment: equipped
+                    .get(*player)
+                    .map(|worn| worn.items())
+                    .unwrap_or_default(),
+            }
+        };
+
+        let reacting_to = match state {
+            RunState::AwaitingInput => game.reaction_status().1.map(|ent| ent.id()),
+            _ => None,
+        };
+
+        let (hand, selected, cards_remaining, cards_discarded) = {
+            let deck = game.ecs.fetch::<deck::Deck>();
+            let selected = if deck.selected >= 0 {
+                Some(deck.selected as usize)
+            } else {
+                None
+            };
+
+            (
+                deck.hand.iter().map(CardView::new).collect(),
+                selected,
+                deck.cards_remaining(),
+                deck.cards_discarded(),
+            )
+        };
+
+        let (incoming, outgoing, rolls, countered, exchange) = {
+            let intents = game.ecs.fetch::<IntentData>();
+
+            // a hidden incoming intent is drawn face down, so the agent doesn't get to see it either
+            let incoming = intents
+                .prev_incoming_intent
+                .filter(|_| !intents.hidden)
+                .map(|intent| IntentView::new(&intent));
+            let outgoing = intents
+                .prev_outgoing_intent
+                .map(|intent| IntentView::new(&intent));
+            let rolls = Some(intents.rolls).filter(|_| !intents.hidden);
+            let exchange = intents
+                .chain
+                .iter()
+                .filter(|_| !intents.hidden)
+                .map(|link| LinkView {
+                    name: link.name.clone(),
+                    intent: IntentView::new(&link.intent),
+                    reacting_to: link.reacting_to,
+                    speed: link.speed,
+                    interrupted: link.interrupted,
+                })
+                .collect();
+
+            (incoming, outgoing, rolls, intents.countered, exchange)
+        };
+
+        let (messages, killed_by) = {
+            let log = game.ecs.fetch::<gamelog::GameLog>();
+            let messages = log.entries.iter().skip(seen_messages).cloned().collect();
+
+            (messages, log.killed_by.clone())
+        };
+
+        Observation {
+            state,
+            done: state == RunState::Dead,
+            turn: game.turn(),
+            player,
+            stealth: sys_noise::player_stealth(&game.ecs),
+            reacting_to,
+            map: MapView::new(&game.ecs),
+            creatures: visible_creatures(&game.ecs),
+            hand,
+            selected,
+            pending_modifier: game.attack_modifier().as_ref().map(CardView::new),
+            cards_remaining,
+            cards_discarded,
+            incoming,
+            outgoing,
+            rolls,
+            countered,
+            exchange,
+            messages,
+            killed_by,
+            legal_actions: game.legal_commands(),
+        }
+    }
+}
+
+impl MapView {
+    fn new(ecs: &World) -> Self {
+        let map = ecs.fetch::<Map>();
+        let tiles = map
+            .tiles
+            .iter()
+            .zip(map.known_tiles.iter())
+            .map(|(tile, known)| Some(*tile).filter(|_| *known))
+            .collect();
+        let exit = Some(map.level_exit)
+            .filter(|exit| map.known_tiles[*exit])
+            .map(|exit| (exit as i32 % map.width, exit as i32 / map.width));
+
+        MapView {
+            width: map.width,
+            height: map.height,
+            depth: map.depth,
+            tiles,
+            visible: map.visible_tiles.clone(),
+            exit,
+        }
+    }
+}
+
+impl CardView {
+    fn new(attack_type: &AttackType) -> Self {
+        CardView {
+            name: move_type::get_attack_name(attack_type),
+            power: move_type::get_attack_power(attack_type),
+            speed: move_type::get_attack_speed(attack_type),
+            guard: move_type::get_attack_guard(attack_type),
+            timing: move_type::get_attack_timing(attack_type),
+            traits: move_type::get_attack_traits(attack_type),
+        }
+    }
+}
+
+impl IntentView {
+    fn new(intent: &AttackIntent) -> Self {
+        IntentView {
+            card: CardView::new(&intent.main),
+            modifier: intent.modifier.as_ref().map(CardView::new),
+            target: (intent.loc.x, intent.loc.y),
+            power: move_type::get_intent_power(intent),
+            speed: move_type::get_intent_speed(intent),
+            guard: move_type::get_intent_guard(intent),
+        }
+    }
+}
+
+// everything with health on a tile the player can see, other than the player
+fn visible_creatures(ecs: &World) -> Vec<CreatureView> {
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let healths = ecs.read_storage::<Health>();
+    let viewables = ecs.read_storage::<Viewable>();
+    let ais = ecs.read_storage::<AiState>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let enemy_decks = ecs.read_storage::<EnemyDeck>();
+    let map = ecs.fetch::<Map>();
+    let player = ecs.fetch::<Entity>();
+
+    (&entities, &positions, &healths)
+        .join()
+        .filter(|(ent, pos, _)| *ent != *player && map.visible_tiles[map.get_index(pos.x, pos.y)])
+        .map(|(ent, pos, health)| CreatureView {
+            id: ent.id(),
+            name: viewables
+                .get(ent)
+                .map_or_else(|| "something".to_string(), |viewable| viewable.name.clone()),
+            x: pos.x,
+            y: pos.y,
+            hp: health.current,
+            max_hp: health.max,
+            hostile: ais.contains(ent),
+            status: status_of(&statuses, ent),
+            deck: enemy_decks.get(ent).map(|enemy_deck| DeckView {
+                cards_remaining: enemy_deck.deck.cards_remaining(),
+                hand_size: enemy_deck.deck.hand.len(),
+                discard: enemy_deck.deck.discard_pile().to_vec(),
+            }),
+        })
+        .collect()
+}
+
+fn status_of(statuses: &ReadStorage<StatusEffects>, ent: Entity) -> Vec<StatusEffect> {
+    statuses
+        .get(ent)
+        .map_or_else(Vec::new, |status| status.effects.clone())
+}
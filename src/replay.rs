@@ -0,0 +1,166 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const REPLAY_PATH: &str = "./replay.json";
+
+pub enum ReplayError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    // the cards, templates or equipment have changed since the replay was recorded
+    DataMismatch,
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(err: serde_json::Error) -> Self {
+        ReplayError::Format(err)
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "couldn't access replay file: {}", err),
+            ReplayError::Format(err) => write!(f, "replay file is malformed: {}", err),
+            ReplayError::DataMismatch => write!(
+                f,
+                "replay was recorded with different cards, templates or equipment, so it \
+                 wouldn't play back the same way"
+            ),
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    // the turn the command was given on
+    pub turn: u32,
+    pub command: Command,
+}
+
+/// Every decision the player made in a run. Since all the randomness comes from the seed,
+/// feeding the commands back into a new game with the same seed plays out the same run.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: String,
+    pub seed: u64,
+    // the rules are kept in full, so that tweaking a preset doesn't break old replays
+    pub rules: Ruleset,
+    // the rest of the data can't be kept that way, so it has to match to play the replay back
+    #[serde(default)]
+    pub data_hash: u64,
+    pub turns: u32,
+    pub commands: Vec<RecordedCommand>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, rules: Ruleset) -> Self {
+        Replay {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            rules,
+            data_hash: crate::data_hash(),
+            turns: 0,
+            commands: Vec::new(),
+        }
+    }
+
+    // looking around doesn't change the game, so only commands need to be recorded
+    pub fn record(&mut self, command: Command, ends_turn: bool) {
+        self.commands.push(RecordedCommand {
+            turn: self.turns,
+            command,
+        });
+
+        if ends_turn {
+            self.turns += 1;
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ReplayError> {
+        let contents = serde_json::to_string(self)?;
+        std::fs::write(path, contents)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, ReplayError> {
+        let contents = std::fs::read_to_string(path)?;
+        let replay = serde_json::from_str(&contents)?;
+
+        Ok(replay)
+    }
+
+    /// Replays recorded on another version may not play out the same way
+    pub fn is_current_version(&self) -> bool {
+        self.version == env!("CARGO_PKG_VERSION")
+    }
+
+    /// Replays recorded with other data files won't play out the same way
+    pub fn is_current_data(&self) -> bool {
+        self.data_hash == crate::data_hash()
+    }
+}
+
+/// Plays a replay back into a game, one command at a time
+pub struct Playback {
+    pub replay: Replay,
+    next: usize,
+}
+
+impl Playback {
+    /// Replays that were recorded with other data files are turned down, rather than playing
+    /// out some other run
+    pub fn new(replay: Replay) -> Result<Self, ReplayError> {
+        if !replay.is_current_data() {
+            return Err(ReplayError::DataMismatch);
+        }
+
+        Ok(Playback { replay, next: 0 })
+    }
+
+    /// A fresh game at the start of the replay
+    pub fn start(&mut self) -> Game {
+        self.next = 0;
+
+        let mut game = Game::new(self.replay.seed, self.replay.rules.clone());
+        game.advance();
+        game
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.replay.commands.len()
+    }
+
+    /// Feed the next command into the game without advancing it, so that a frontend can draw
+    /// the result. Returns false once the replay has run out of commands.
+    pub fn apply_next(&mut self, game: &mut Game) -> bool {
+        match self.replay.commands.get(self.next) {
+            None => false,
+            Some(recorded) => {
+                game.apply(recorded.command);
+                self.next += 1;
+                true
+            }
+        }
+    }
+
+    /// Play commands until the game reaches the start of the given turn, or the replay ends
+    /// Going backwards restarts the run from the seed
+    pub fn jump_to_turn(&mut self, game: &mut Game, turn: u32) {
+        if turn <= game.turn() {
+            *game = self.start();
+        }
+
+        game.advance();
+        while game.turn() < turn && self.apply_next(game) {
+            game.advance();
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+// like the card file, edits are picked up the next time the game starts
+pub const RULESETS_PATH: &str = "resources/rulesets.ron";
+const BUILTIN_RULESETS: &str = include_str!("../resources/rulesets.ron");
+
+static RULESETS: OnceLock<Vec<Ruleset>> = OnceLock::new();
+
+pub enum RulesetError {
+    Io(std::io::Error),
+    Format(ron::error::SpannedError),
+    Invalid { ruleset: String, reason: String },
+    Empty,
+}
+
+impl fmt::Display for RulesetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RulesetError::Io(err) => write!(f, "couldn't read {}: {}", RULESETS_PATH, err),
+            RulesetError::Format(err) => write!(f, "{}:{}", RULESETS_PATH, err),
+            RulesetError::Invalid { ruleset, reason } => {
+                write!(f, "{}: ruleset \"{}\" {}", RULESETS_PATH, ruleset, reason)
+            }
+            RulesetError::Empty => write!(f, "{}: there are no rulesets", RULESETS_PATH),
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct TurnSpeed {
+    pub base: i32,
+    pub delta: i32,
+}
+
+/// The numbers behind an exchange, chosen when a game starts
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+pub struct Ruleset {
+    pub name: String,
+    pub attacker_speed_bonus: i32,
+    pub defender_guard_bonus: i32,
+    pub speed_roll: i32,
+    pub guard_roll: i32,
+    pub hand_limit: usize,
+    pub turn: TurnSpeed,
+}
+
+impl Ruleset {
+    pub fn roll_speed(&self, rng: &mut rltk::RandomNumberGenerator) -> i32 {
+        roll(rng, self.speed_roll)
+    }
+
+    pub fn roll_guard(&self, rng: &mut rltk::RandomNumberGenerator) -> i32 {
+        roll(rng, self.guard_roll)
+    }
+
+    // every value the rolls can come up as, each one equally likely
+    pub fn speed_faces(&self) -> Range<i32> {
+        0..self.speed_roll.max(1)
+    }
+
+    pub fn guard_faces(&self) -> Range<i32> {
+        0..self.guard_roll.max(1)
+    }
+}
+
+// small dice don't touch the rng at all, so that turning rolls off doesn't shift other rolls
+fn roll(rng: &mut rltk::RandomNumberGenerator, size: i32) -> i32 {
+    if size > 1 {
+        rng.range(0, size)
+    } else {
+        0
+    }
+}
+
+/// Load the rulesets, reporting anything wrong with the file
+/// Rulesets are otherwise loaded the first time they are needed, and a bad file is quietly
+/// swapped for the built-in rulesets then
+pub fn load_rulesets() -> Result<(), RulesetError> {
+    if RULESETS.get().is_none() {
+        let rulesets = read_rulesets()?;
+        let _ = RULESETS.set(rulesets);
+    }
+
+    Ok(())
+}
+
+fn ruleset_list() -> &'static [Ruleset] {
+    RULESETS.get_or_init(|| {
+        read_rulesets()
+            .or_else(|_| parse_rulesets(BUILTIN_RULESETS))
+            .unwrap_or_else(|err| panic!("built-in rulesets are broken, {}", err))
+    })
+}
+
+fn read_rulesets() -> Result<Vec<Ruleset>, RulesetError> {
+    let contents = if std::path::Path::new(RULESETS_PATH).exists() {
+        std::fs::read_to_string(RULESETS_PATH).map_err(RulesetError::Io)?
+    } else {
+        BUILTIN_RULESETS.to_string()
+    };
+
+    parse_rulesets(&contents)
+}
+
+fn parse_rulesets(contents: &str) -> Result<Vec<Ruleset>, RulesetError> {
+    let rulesets: Vec<Ruleset> = ron::de::from_str(contents).map_err(RulesetError::Format)?;
+    let mut names = HashSet::new();
+
+    if rulesets.is_empty() {
+        return Err(RulesetError::Empty);
+    }
+
+    for ruleset in rulesets.iter() {
+        let invalid = |reason: &str| RulesetError::Invalid {
+            ruleset: ruleset.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        if !names.insert(ruleset.name.as_str()) {
+            return Err(invalid("is defined more than once"));
+        }
+        if ruleset.speed_roll < 0 || ruleset.guard_roll < 0 {
+            return Err(invalid("has a negative roll"));
+        }
+        if ruleset.hand_limit == 0 {
+            return Err(invalid("needs a hand_limit of at least 1"));
+        }
+        if ruleset.turn.base <= 0 || ruleset.turn.delta <= 0 {
+            return Err(invalid("needs a turn base and delta above 0"));
+        }
+    }
+
+    Ok(rulesets)
+}
+
+pub fn default_ruleset() -> Ruleset {
+    ruleset_list()[0].clone()
+}
+
+pub fn find_ruleset(name: &str) -> Option<Ruleset> {
+    ruleset_list()
+        .iter()
+        .find(|ruleset| ruleset.name == name)
+        .cloned()
+}
+
+pub fn ruleset_names() -> Vec<String> {
+    ruleset_list()
+        .iter()
+        .map(|ruleset| ruleset.name.clone())
+        .collect()
+}
+
+// the preset after the given one, for cycling through them
+pub fn next_ruleset(name: &str) -> Ruleset {
+    let rulesets = ruleset_list();
+    let index = rulesets
+        .iter()
+        .position(|ruleset| ruleset.name == name)
+        .map_or(0, |index| (index + 1) % rulesets.len());
+
+    rulesets[index].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ruleset(name: &str, speed_roll: i32, hand_limit: usize) -> String {
+        format!(
+            "(name: \"{}\", attacker_speed_bonus: 0, defender_guard_bonus: 1, \
+             speed_roll: {}, guard_roll: 6, hand_limit: {}, turn: (base: 24, delta: 4))",
+            name, speed_roll, hand_limit
+        )
+    }
+
+    fn ruleset_file(rulesets: &[String]) -> String {
+        format!("[{}]", rulesets.join(", "))
+    }
+
+    // the message the player would see for a ruleset file that should be turned down
+    fn rejection(contents: &str) -> String {
+        match parse_rulesets(contents) {
+            Ok(_) => panic!("ruleset file should have been rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn builtin_rulesets_parse() {
+        if let Err(err) = parse_rulesets(BUILTIN_RULESETS) {
+            panic!("{}", err);
+        }
+    }
+
+    #[test]
+    fn valid_file_parses() {
+        let contents = ruleset_file(&[ruleset("classic", 6, 7), ruleset("calm", 0, 5)]);
+
+        match parse_rulesets(&contents) {
+            Ok(rulesets) => {
+                assert_eq!(rulesets.len(), 2);
+                assert_eq!(rulesets[1].name, "calm");
+                assert_eq!(rulesets[1].speed_faces(), 0..1);
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn missing_fields_are_rejected() {
+        let contents = "[(name: \"classic\", attacker_speed_bonus: 0)]";
+        let message = rejection(contents);
+
+        // ron points at the ruleset, and names the field it wanted
+        assert!(
+            message.starts_with("resources/rulesets.ron:1:"),
+            "{}",
+            message
+        );
+        assert!(message.contains("defender_guard_bonus"), "{}", message);
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let contents = ruleset_file(&[ruleset("classic", 6, 7), ruleset("classic", 0, 7)]);
+        assert_eq!(
+            rejection(&contents),
+            "resources/rulesets.ron: ruleset \"classic\" is defined more than once"
+        );
+    }
+
+    #[test]
+    fn bad_numbers_are_rejected() {
+        let contents = ruleset_file(&[ruleset("classic", -1, 7)]);
+        assert_eq!(
+            rejection(&contents),
+            "resources/rulesets.ron: ruleset \"classic\" has a negative roll"
+        );
+
+        let contents = ruleset_file(&[ruleset("classic", 6, 0)]);
+        assert_eq!(
+            rejection(&contents),
+            "resources/rulesets.ron: ruleset \"classic\" needs a hand_limit of at least 1"
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_not_found() {
+        assert!(find_ruleset("no such rules").is_none());
+        assert!(next_ruleset("no such rules") == default_ruleset());
+    }
+
+    #[test]
+    fn empty_ruleset_list_is_rejected() {
+        assert_eq!(
+            rejection("[]"),
+            "resources/rulesets.ron: there are no rulesets"
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use crate::*;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+pub const SAVE_PATH: &str = "./savegame.json";
+
+pub enum SaveError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    Version(String),
+    // an entity id in the save that doesn't belong to any saved entity
+    MissingEntity(usize),
+    // a card in a saved exchange answering one that isn't before it in the exchange
+    MissingLink(usize),
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> Self {
+        SaveError::Format(err)
+    }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "couldn't access save file: {}", err),
+            SaveError::Format(err) => write!(f, "save file is malformed: {}", err),
+            SaveError::Version(version) => {
+                write!(f, "save file is from an incompatible version {}", version)
+            }
+            SaveError::MissingEntity(id) => {
+                write!(f, "save file refers to entity {}, which isn't in it", id)
+            }
+            SaveError::MissingLink(index) => write!(
+                f,
+                "save file has an exchange answering card {}, which isn't in it",
+                index
+            ),
+        }
+    }
+}
+
+// CanActFlag points at another entity, so it needs to be converted by hand
+#[derive(Serialize, Deserialize)]
+struct SavedCanAct {
+    is_reaction: bool,
+    reaction_target: Option<usize>,
+}
+
+// marker components are written out as null, which serde would otherwise read back as a missing
+// component, so anything that is in the file counts as present
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+// list every component that should survive a save
+macro_rules! saved_components {
+    ($($field:ident: $comp:ty),* $(,)?) => {
+        #[derive(Default, Serialize, Deserialize)]
+        struct SavedEntity {
+            $(
+                #[serde(
+                    default,
+                    deserialize_with = "present",
+                    skip_serializing_if = "Option::is_none"
+                )]
+                $field: Option<$comp>,
+            )*
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            can_act: Option<SavedCanAct>,
+        }
+
+        fn save_entity(ecs: &World, ent: Entity) -> SavedEntity {
+            SavedEntity {
+                $($field: ecs.read_storage::<$comp>().get(ent).cloned(),)*
+                ..SavedEntity::default()
+            }
+        }
+
+        fn load_entity(ecs: &mut World, ent: Entity, saved: SavedEntity) {
+            $(
+                if let Some(comp) = saved.$field {
+                    ecs.write_storage::<$comp>()
+                        .insert(ent, comp)
+                        .expect("Failed to insert loaded component");
+                }
+            )*
+        }
+    };
+}
+
+saved_components!(
+    position: Position,
+    renderable: Renderable,
+    player: Player,
+    viewshed: Viewshed,
+    can_react: CanReactFlag,
+    schedulable: Schedulable,
+    blocks_tile: BlocksTile,
+    viewable: Viewable,
+    viewable_index: ViewableIndex,
+    health: Health,
+    death_trigger: DeathTrigger,
+    attack_intent: AttackIntent,
+    move_intent: MoveIntent,
+    moveset: Moveset,
+    enemy_deck: EnemyDeck,
+    attack_in_progress: AttackInProgress,
+    block_attack: BlockAttack,
+    ai_state: AiState,
+    heal: Heal,
+    skill_choice: SkillChoice,
+    item: Item,
+    openable: Openable,
+    status_effects: StatusEffects,
+    equipped: Equipped,
+    equipment_pickup: EquipmentPickup,
+);
+
+#[derive(Serialize, Deserialize)]
+struct SaveData {
+    version: String,
+    entities: Vec<SavedEntity>,
+    player: usize,
+    map: Map,
+    // (tile index, entity index) pairs
+    creatures: Vec<(usize, usize)>,
+    items: Vec<(usize, usize)>,
+    deck: deck::Deck,
+    intents: IntentData,
+    log: gamelog::GameLog,
+    rng: rltk::RandomNumberGenerator,
+    seed: RunSeed,
+    rules: Ruleset,
+    run_state: RunState,
+    events: events::SavedEvents,
+    attack_modifier: Option<AttackType>,
+    recording: replay::Replay,
+}
+
+fn remap_tiles(
+    tiles: &HashMap<usize, Entity>,
+    entity_ids: &HashMap<Entity, usize>,
+) -> Vec<(usize, usize)> {
+    let mut remapped: Vec<(usize, usize)> = tiles
+        .iter()
+        .filter_map(|(index, ent)| entity_ids.get(ent).map(|id| (*index, *id)))
+        .collect();
+
+    // keep the output stable between saves
+    remapped.sort();
+    remapped
+}
+
+pub fn save_game(
+    ecs: &World,
+    attack_modifier: Option<AttackType>,
+    recording: &replay::Replay,
+    path: &str,
+) -> Result<(), SaveError> {
+    let mut entity_ids = HashMap::new();
+    let mut entities = Vec::new();
+
+    for ent in ecs.entities().join() {
+        entity_ids.insert(ent, entities.len());
+        entities.push(ent);
+    }
+
+    let mut saved_entities = Vec::new();
+    {
+        let can_act = ecs.read_storage::<CanActFlag>();
+
+        for ent in entities.iter() {
+            let mut saved = save_entity(ecs, *ent);
+            saved.can_act = can_act.get(*ent).map(|flag| SavedCanAct {
+                is_reaction: flag.is_reaction,
+                reaction_target: flag
+                    .reaction_target
+                    .and_then(|target| entity_ids.get(&target).copied()),
+            });
+
+            saved_entities.push(saved);
+        }
+    }
+
+    let map = ecs.fetch::<Map>();
+    let player = ecs.fetch::<Entity>();
+
+    let data = SaveData {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        entities: saved_entities,
+        player: entity_ids[&*player],
+        map: (*map).clone(),
+        creatures: remap_tiles(&map.creature_map, &entity_ids),
+        items: remap_tiles(&map.item_map, &entity_ids),
+        deck: (*ecs.fetch::<deck::Deck>()).clone(),
+        intents: (*ecs.fetch::<IntentData>()).clone(),
+        log: (*ecs.fetch::<gamelog::GameLog>()).clone(),
+        rng: (*ecs.fetch::<rltk::RandomNumberGenerator>()).clone(),
+        seed: *ecs.fetch::<RunSeed>(),
+        rules: (*ecs.fetch::<Ruleset>()).clone(),
+        run_state: *ecs.fetch::<RunState>(),
+        events: events::save_events(&ecs.fetch::<EventQueue>(), &entity_ids),
+        attack_modifier,
+        recording: recording.clone(),
+    };
+
+    let contents = serde_json::to_string(&data)?;
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
+
+// restore a save into an empty world, returning the player's pending modifier card and the
+// commands recorded so far
+pub fn load_game(
+    ecs: &mut World,
+    path: &str,
+) -> Result<(Option<AttackType>, replay::Replay), SaveError> {
+    let contents = std::fs::read_to_string(path)?;
+    let data: SaveData = serde_json::from_str(&contents)?;
+
+    if data.version != env!("CARGO_PKG_VERSION") {
+        return Err(SaveError::Version(data.version));
+    }
+
+    // create everything first so that references between entities can be restored
+    let entities: Vec<Entity> = data
+        .entities
+        .iter()
+        .map(|_| ecs.create_entity().build())
+        .collect();
+
+    let entity = |id: usize| {
+        entities
+            .get(id)
+            .copied()
+            .ok_or(SaveError::MissingEntity(id))
+    };
+
+    for (ent, saved) in entities.iter().zip(data.entities.into_iter()) {
+        if let Some(flag) = &saved.can_act {
+            let reaction_target = match flag.reaction_target {
+                Some(id) => Some(entity(id)?),
+                None => None,
+            };
+
+            ecs.write_storage::<CanActFlag>()
+                .insert(
+                    *ent,
+                    CanActFlag {
+                        is_reaction: flag.is_reaction,
+                        reaction_target,
+                    },
+                )
+                .expect("Failed to insert loaded CanActFlag");
+        }
+
+        load_entity(ecs, *ent, saved);
+    }
+
+    let mut map = data.map;
+    for (index, id) in data.creatures {
+        map.creature_map.insert(index, entity(id)?);
+    }
+    for (index, id) in data.items {
+        map.item_map.insert(index, entity(id)?);
+    }
+
+    ecs.insert(map);
+    ecs.insert(entity(data.player)?);
+    ecs.insert(data.deck);
+    ecs.insert(data.intents);
+    ecs.insert(data.log);
+    ecs.insert(data.rng);
+    ecs.insert(data.seed);
+    ecs.insert(data.rules);
+    ecs.insert(data.run_state);
+    ecs.insert(events::load_events(data.events, &entities)?);
+
+    Ok((data.attack_modifier, data.recording))
+}
+
+pub fn save_exists(path: &str) -> bool {
+    std::path::Path::new(path).exists()
+}
+
+pub fn delete_save(path: &str) {
+    if save_exists(path) {
+        std::fs::remove_file(path).expect("Failed to delete save file");
+    }
+}
@@ -1,10 +1,11 @@
 use crate::*;
-use rltk::{Point, RandomNumberGenerator, Rect};
+use rltk::{Point, RandomNumberGenerator, Rect, RGB};
 
 pub struct Spawner<'a> {
     ecs: &'a mut World,
     map: &'a mut Map,
     map_width: i32,
+    spawned: Vec<(Entity, Point)>,
 }
 
 impl<'a> Spawner<'a> {
@@ -13,21 +14,28 @@ impl<'a> Spawner<'a> {
             ecs,
             map,
             map_width,
+            spawned: Vec::new(),
         }
     }
 
-    pub fn build(
-        &mut self,
-        room: &Rect,
-        min: i32,
-        max: i32,
-        chance: Vec<f32>,
-        builder: Vec<impl Fn(&mut World, Point) -> Entity>,
-    ) {
+    /// Everything this spawner has put on the map
+    pub fn into_spawned(self) -> Vec<(Entity, Point)> {
+        self.spawned
+    }
+
+    // roll a spawn table for this room
+    pub fn build(&mut self, room: &Rect, table: &templates::SpawnTable, quality: i32) {
         let mut spawn_points = Vec::new();
+        let mut asleep = false;
         {
             let mut rng = self.ecs.fetch_mut::<RandomNumberGenerator>();
-            let spawn_count = rng.range(min, max);
+            if table.asleep > 0.0 {
+                asleep = rng.rand::<f32>() < table.asleep;
+            }
+
+            let min = table.min.at(quality);
+            let max = table.max.at(quality);
+            let spawn_count = if max > min { rng.range(min, max) } else { min };
 
             for _ in 0..spawn_count {
                 let dx = rng.range(1, room.width());
@@ -37,83 +45,49 @@ impl<'a> Spawner<'a> {
                 let index = ((ypos * self.map_width) + xpos) as usize;
 
                 // don't spawn over something else
-                if !self.map.blocked_tiles[index] && index != self.map.level_exit {
+                let taken = spawn_points
+                    .iter()
+                    .any(|(_, x, y)| *x == xpos && *y == ypos);
+                let is_floor = self.map.tiles[index] == TileType::Floor;
+                if is_floor && !self.map.blocked_tiles[index] && !taken {
                     let roll = rng.rand::<f32>();
                     let mut cumul_prob = 0.0;
-                    let mut builder_index = 0;
+                    let mut spawn_index = 0;
 
-                    for index in 0..chance.len() {
-                        cumul_prob += chance[index];
+                    for (index, (_, chance)) in table.spawns.iter().enumerate() {
+                        cumul_prob += chance;
 
                         if roll < cumul_prob {
-                            builder_index = index;
+                            spawn_index = index;
                             break;
                         }
                     }
 
-                    spawn_points.push((builder_index, xpos, ypos));
+                    spawn_points.push((spawn_index, xpos, ypos));
                 }
             }
         }
 
-        for (builder_index, xpos, ypos) in spawn_points {
+        for (spawn_index, xpos, ypos) in spawn_points {
             let point = Point::new(xpos, ypos);
-            let enemy = builder[builder_index](self.ecs, point);
-            self.map.track_creature(enemy, point);
-        }
-    }
-
-    pub fn build_with_quality(
-        &mut self,
-        room: &Rect,
-        min: i32,
-        max: i32,
-        quality: i32,
-        chance: Vec<f32>,
-        builder: Vec<impl Fn(&mut World, Point, i32) -> Entity>,
-    ) {
-        let mut spawn_points = Vec::new();
-        {
-            let mut rng = self.ecs.fetch_mut::<RandomNumberGenerator>();
-            let spawn_count = rng.range(min, max);
-
-            for _ in 0..spawn_count {
-                let dx = rng.range(1, room.width());
-                let dy = rng.range(1, room.height());
-                let xpos = room.x1 + dx;
-                let ypos = room.y1 + dy;
-                let index = ((ypos * self.map_width) + xpos) as usize;
-
-                // don't spawn over something else
-                if !self.map.blocked_tiles[index] && index != self.map.level_exit {
-                    let roll = rng.rand::<f32>();
-                    let mut cumul_prob = 0.0;
-                    let mut builder_index = 0;
-
-                    for index in 0..chance.len() {
-                        cumul_prob += chance[index];
-
-                        if roll < cumul_prob {
-                            builder_index = index;
-                            break;
-                        }
-                    }
-
-                    spawn_points.push((builder_index, xpos, ypos));
+            let (name, _) = &table.spawns[spawn_index];
+            let spawned = spawn_named(self.ecs, name, point, quality);
+            if asleep {
+                if let Some(state) = self.ecs.write_storage::<AiState>().get_mut(spawned) {
+                    state.status = Behavior::Sleep;
                 }
             }
-        }
 
-        for (builder_index, xpos, ypos) in spawn_points {
-            let point = Point::new(xpos, ypos);
-            let enemy = builder[builder_index](self.ecs, point, quality);
-            self.map.track_creature(enemy, point);
+            self.map.track_creature(spawned, point);
+            self.spawned.push((spawned, point));
         }
     }
 }
 
 // #region Player
 pub fn build_player(ecs: &mut World, point: Point) -> Entity {
+    let turn = ecs.fetch::<Ruleset>().turn;
+
     ecs.create_entity()
         .with(Position {
             x: point.x,
@@ -133,8 +107,8 @@ pub fn build_player(ecs: &mut World, point: Point) -> Entity {
         .with(Player)
         .with(Schedulable {
             current: 0,
-            base: 24,
-            delta: 4,
+            base: turn.base,
+            delta: turn.delta,
         })
         .with(Viewshed {
             visible: Vec::new(),
@@ -147,162 +121,152 @@ pub fn build_player(ecs: &mut World, point: Point) -> Entity {
             current: 10,
             max: 10,
         })
+        .with(Equipped::default())
         .build()
 }
 // #endregion
 
-// #region Enemies
-pub fn build_mook(ecs: &mut World, point: Point) -> Entity {
-    ecs.create_entity()
-        .with(Position {
-            x: point.x,
-            y: point.y,
-        })
-        .with(Renderable {
-            symbol: rltk::to_cp437('x'),
-            fg: RGB::named(rltk::LIGHT_BLUE),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Viewable {
-            name: "Mook".to_string(),
-            description: vec![
-                "A lowly grunt,".to_string(),
-                "unskilled, but".to_string(),
-                "can still pack".to_string(),
-                "a wallop".to_string(),
-            ],
-            seen: false,
-        })
-        .with(ViewableIndex { list_index: None })
-        .with(Schedulable {
-            current: 0,
-            base: 24,
-            delta: 4,
-        })
-        .with(Viewshed {
-            visible: Vec::new(),
-            dirty: true,
-            range: 8,
-        })
-        .with(BlocksTile)
-        .with(Health { current: 5, max: 5 })
-        .with(Moveset {
-            moves: vec![(AttackType::Haymaker, 0.25), (AttackType::Punch, 0.75)],
-        })
-        .with(AiState {
-            status: Behavior::Wander,
-            tracking: None,
-        })
-        .build()
+// #region Templates
+pub fn spawn_named(ecs: &mut World, name: &str, point: Point, quality: i32) -> Entity {
+    let template = templates::get_template(name).unwrap_or_else(|| {
+        panic!(
+            "{}: template \"{}\" isn't defined",
+            templates::TEMPLATES_PATH,
+            name
+        )
+    });
+
+    spawn_template(ecs, template, point, quality)
 }
 
-pub fn build_archer(ecs: &mut World, point: Point) -> Entity {
-    ecs.create_entity()
-        .with(Position {
-            x: point.x,
-            y: point.y,
-        })
-        .with(Renderable {
-            symbol: rltk::to_cp437('y'),
-            fg: RGB::named(rltk::LIGHT_GREEN),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Viewable {
-            name: "Archer".to_string(),
-            description: vec!["A grunt with a bow".to_string()],
+pub fn spawn_template(
+    ecs: &mut World,
+    template: &templates::Template,
+    point: Point,
+    quality: i32,
+) -> Entity {
+    let turn = ecs.fetch::<Ruleset>().turn;
+    let deck = template.deck.as_ref().map(|deck| {
+        let mut rng = ecs.fetch_mut::<RandomNumberGenerator>();
+        let cards = deck
+            .cards
+            .iter()
+            .map(|card| cards::card_named(card))
+            .collect();
+        EnemyDeck {
+            deck: crate::deck::Deck::new_shuffled(cards, deck.hand_size, &mut rng),
+        }
+    });
+    let mut builder = ecs.create_entity().with(Position {
+        x: point.x,
+        y: point.y,
+    });
+
+    if let Some(renderable) = &template.renderable {
+        builder = builder.with(Renderable {
+            symbol: rltk::to_cp437(renderable.glyph),
+            fg: RGB::named(renderable.fg),
+            bg: RGB::named(renderable.bg),
+        });
+    }
+
+    if let Some(viewable) = &template.viewable {
+        builder = builder.with(Viewable {
+            name: viewable.name.clone(),
+            description: viewable.description.clone(),
             seen: false,
-        })
-        .with(ViewableIndex { list_index: None })
-        .with(Schedulable {
+        });
+
+        if viewable.listed {
+            builder = builder.with(ViewableIndex { list_index: None });
+        }
+    }
+
+    if let Some(schedulable) = &template.schedulable {
+        builder = builder.with(Schedulable {
             current: 0,
-            base: 24,
-            delta: 4,
-        })
-        .with(Viewshed {
+            base: schedulable.base.unwrap_or(turn.base),
+            delta: schedulable.delta.unwrap_or(turn.delta),
+        });
+    }
+
+    if let Some(range) = template.viewshed {
+        builder = builder.with(Viewshed {
             visible: Vec::new(),
             dirty: true,
-            range: 8,
-        })
-        .with(BlocksTile)
-        .with(Health { current: 2, max: 2 })
-        .with(Moveset {
-            moves: vec![(AttackType::Punch, 0.25), (AttackType::Ranged, 0.75)],
-        })
-        .with(AiState {
-            status: Behavior::Wander,
-            tracking: None,
-        })
-        .build()
-}
-// #endregion
+            range,
+        });
+    }
 
-// #region Objects
-fn barrel_builder(ecs: &mut World, point: Point) -> EntityBuilder {
-    ecs.create_entity()
-        .with(Position {
-            x: point.x,
-            y: point.y,
-        })
-        .with(Renderable {
-            symbol: rltk::to_cp437('#'),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Viewable {
-            name: "Barrel".to_string(),
-            description: vec![
-                "A barrel, what".to_string(),
-                "could be".to_string(),
-                "inside?".to_string(),
-            ],
-            seen: false,
-        })
-        .with(BlocksTile)
-        .with(Openable)
-        .with(Health { current: 2, max: 2 })
-}
+    if template.blocks_tile {
+        builder = builder.with(BlocksTile);
+    }
 
-pub fn build_empty_barrel(ecs: &mut World, point: Point, _quality: i32) -> Entity {
-    barrel_builder(ecs, point).build()
-}
+    if template.openable {
+        builder = builder.with(Openable);
+    }
+
+    if let Some(health) = template.health {
+        builder = builder.with(Health {
+            current: health,
+            max: health,
+        });
+    }
 
-pub fn build_exploding_barrel(ecs: &mut World, point: Point, quality: i32) -> Entity {
-    barrel_builder(ecs, point)
-        .with(DeathTrigger {
-            event: EventType::Damage {
-                amount: 1 + quality / 2,
+    if let Some(moveset) = &template.moveset {
+        builder = builder.with(Moveset {
+            moves: moveset
+                .iter()
+                .map(|(card, chance)| (cards::card_named(card), *chance))
+                .collect(),
+        });
+    }
+
+    if let Some(deck) = deck {
+        builder = builder.with(deck);
+    }
+
+    if template.counter {
+        builder = builder.with(CanReactFlag);
+    }
+
+    if let Some(ai) = &template.ai {
+        builder = builder.with(AiState {
+            status: ai.clone(),
+            tracking: None,
+            flee_health: template.flee,
+            heard: 0.0,
+        });
+    }
+
+    if let Some(effect) = &template.death_trigger {
+        let trigger = match effect {
+            templates::DeathEffect::Explode { damage, size } => DeathTrigger {
+                event: EventType::Damage {
+                    amount: damage.at(quality),
+                    status: None,
+                },
+                range: RangeType::Square {
+                    size: size.at(quality),
+                },
             },
-            range: RangeType::Square {
-                size: 1 + quality / 3,
+            templates::DeathEffect::Drop { item } => DeathTrigger {
+                event: EventType::ItemDrop {
+                    drop_type: *item,
+                    quality,
+                },
+                range: RangeType::Single,
             },
-        })
-        .build()
-}
+        };
 
-pub fn build_health_barrel(ecs: &mut World, point: Point, quality: i32) -> Entity {
-    barrel_builder(ecs, point)
-        .with(DeathTrigger {
-            event: EventType::ItemDrop {
-                drop_type: crate::events::DropType::Health,
-                quality,
-            },
-            range: RangeType::Single,
-        })
-        .build()
-}
+        builder = builder.with(trigger);
+    }
 
-pub fn build_book_barrel(ecs: &mut World, point: Point, quality: i32) -> Entity {
-    barrel_builder(ecs, point)
-        .with(DeathTrigger {
-            event: EventType::ItemDrop {
-                drop_type: crate::events::DropType::Skill,
-                quality,
-            },
-            range: RangeType::Single,
-        })
-        .build()
+    builder.build()
 }
+// #endregion
 
+// #region Items
 pub fn build_health_pickup(ecs: &mut World, point: Point, quality: i32) -> Entity {
     ecs.create_entity()
         .with(crate::Position {
@@ -350,4 +314,39 @@ pub fn build_skill_pickup(
         })
         .build()
 }
+
+pub fn build_equipment_pickup(ecs: &mut World, point: Point, item: crate::EquipmentType) -> Entity {
+    ecs.create_entity()
+        .with(crate::Position {
+            x: point.x,
+            y: point.y,
+        })
+        .with(equipment_renderable(item))
+        .with(crate::EquipmentPickup { item })
+        .with(equipment_viewable(item))
+        .build()
+}
+
+// also used to turn a pickup into whatever was swapped out for it
+pub fn equipment_renderable(item: crate::EquipmentType) -> crate::Renderable {
+    let def = crate::equipment::get_equipment(&item);
+
+    crate::Renderable {
+        symbol: rltk::to_cp437(def.glyph),
+        fg: RGB::from_u8(def.fg.0, def.fg.1, def.fg.2),
+        bg: crate::bg_color(),
+    }
+}
+
+pub fn equipment_viewable(item: crate::EquipmentType) -> crate::Viewable {
+    let def = crate::equipment::get_equipment(&item);
+    let mut description = def.description.clone();
+    description.push(def.labels().join(" "));
+
+    crate::Viewable {
+        name: def.name.clone(),
+        description,
+        seen: false,
+    }
+}
 // #endregion
@@ -0,0 +1,247 @@
+use crate::StatusEffects;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+// how much each effect shifts the rolls in an exchange
+const HASTENED_SPEED: i32 = 2;
+const GUARDED_GUARD: i32 = 2;
+const OFF_BALANCE_GUARD: i32 = -2;
+// how fast a hastened holder's turns come around, as a percentage of the usual speed
+const HASTENED_TURN_RATE: i32 = 150;
+const NORMAL_TURN_RATE: i32 = 100;
+
+#[derive(PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum StatusKind {
+    // loses their next turn, and can't react
+    Stunned,
+    OffBalance,
+    // loses a point of health at the start of every turn
+    Bleeding,
+    // gets turns more often, and is quicker in an exchange
+    Hastened,
+    Guarded,
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Stunned => "Stunned",
+            StatusKind::OffBalance => "Off balance",
+            StatusKind::Bleeding => "Bleeding",
+            StatusKind::Hastened => "Hastened",
+            StatusKind::Guarded => "Guarded",
+        }
+    }
+
+    pub fn icon(&self) -> char {
+        match self {
+            StatusKind::Stunned => '*',
+            StatusKind::OffBalance => '/',
+            StatusKind::Bleeding => '!',
+            StatusKind::Hastened => '»',
+            StatusKind::Guarded => '♦',
+        }
+    }
+
+    fn speed_bonus(&self) -> i32 {
+        match self {
+            StatusKind::Hastened => HASTENED_SPEED,
+            _ => 0,
+        }
+    }
+
+    fn guard_bonus(&self) -> i32 {
+        match self {
+            StatusKind::Guarded => GUARDED_GUARD,
+            StatusKind::OffBalance => OFF_BALANCE_GUARD,
+            _ => 0,
+        }
+    }
+
+    fn turn_rate(&self) -> i32 {
+        match self {
+            StatusKind::Hastened => HASTENED_TURN_RATE,
+            _ => NORMAL_TURN_RATE,
+        }
+    }
+}
+
+/// An effect that wears off once enough ticks have passed, counted the same way as `Schedulable`
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub remaining: i32,
+}
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    // the same effect doesn't stack, it just lasts as long as the longer of the two
+    pub fn add(&mut self, kind: StatusKind, duration: i32) {
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect.remaining = std::cmp::max(effect.remaining, duration),
+            None => self.effects.push(StatusEffect {
+                kind,
+                remaining: duration,
+            }),
+        }
+    }
+
+    // let some time pass, dropping anything that has worn off
+    pub fn tick(&mut self, amount: i32) {
+        for effect in self.effects.iter_mut() {
+            effect.remaining -= amount;
+        }
+
+        self.effects.retain(|effect| effect.remaining > 0);
+    }
+
+    pub fn speed_bonus(&self) -> i32 {
+        self.effects
+            .iter()
+            .map(|effect| effect.kind.speed_bonus())
+            .sum()
+    }
+
+    pub fn guard_bonus(&self) -> i32 {
+        self.effects
+            .iter()
+            .map(|effect| effect.kind.guard_bonus())
+            .sum()
+    }
+
+    fn turn_rate(&self) -> i32 {
+        self.effects
+            .iter()
+            .map(|effect| effect.kind.turn_rate())
+            .max()
+            .unwrap_or(NORMAL_TURN_RATE)
+    }
+
+    /// How far the holder's next turn comes along when the given number of ticks pass
+    pub fn turn_ticks(&self, ticks: i32) -> i32 {
+        ticks * self.turn_rate() / NORMAL_TURN_RATE
+    }
+
+    /// A line for each effect, with how many of the holder's turns it has left
+    pub fn describe(&self, turn_length: i32) -> Vec<String> {
+        self.effects
+            .iter()
+            .map(|effect| {
+                let turns = (effect.remaining + turn_length - 1) / turn_length;
+                if turns == 1 {
+                    format!("{}, 1 turn", effect.kind.name())
+                } else {
+                    format!("{}, {} turns", effect.kind.name(), turns)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Give an entity a status effect, or top up the one it already has
+pub fn inflict(
+    statuses: &mut WriteStorage<StatusEffects>,
+    ent: Entity,
+    kind: StatusKind,
+    duration: i32,
+) {
+    match statuses.get_mut(ent) {
+        Some(status) => status.add(kind, duration),
+        None => {
+            let mut status = StatusEffects {
+                effects: Vec::new(),
+            };
+            status.add(kind, duration);
+
+            statuses
+                .insert(ent, status)
+                .expect("Failed to insert StatusEffects");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effects(list: &[(StatusKind, i32)]) -> StatusEffects {
+        let mut status = StatusEffects {
+            effects: Vec::new(),
+        };
+        for (kind, duration) in list.iter() {
+            status.add(*kind, *duration);
+        }
+
+        status
+    }
+
+    fn remaining(status: &StatusEffects, kind: StatusKind) -> Option<i32> {
+        status
+            .effects
+            .iter()
+            .find(|effect| effect.kind == kind)
+            .map(|effect| effect.remaining)
+    }
+
+    #[test]
+    fn the_same_effect_keeps_the_longer_duration() {
+        let mut status = effects(&[(StatusKind::Bleeding, 48), (StatusKind::Bleeding, 24)]);
+        assert_eq!(status.effects.len(), 1);
+        assert_eq!(remaining(&status, StatusKind::Bleeding), Some(48));
+
+        status.add(StatusKind::Bleeding, 72);
+        assert_eq!(status.effects.len(), 1);
+        assert_eq!(remaining(&status, StatusKind::Bleeding), Some(72));
+    }
+
+    #[test]
+    fn different_effects_stack() {
+        let status = effects(&[(StatusKind::Guarded, 24), (StatusKind::OffBalance, 24)]);
+        assert_eq!(status.effects.len(), 2);
+        assert_eq!(status.guard_bonus(), GUARDED_GUARD + OFF_BALANCE_GUARD);
+        assert_eq!(status.speed_bonus(), 0);
+    }
+
+    #[test]
+    fn effects_tick_down_and_wear_off() {
+        let mut status = effects(&[(StatusKind::Stunned, 8), (StatusKind::Hastened, 24)]);
+
+        status.tick(4);
+        assert_eq!(remaining(&status, StatusKind::Stunned), Some(4));
+        assert_eq!(remaining(&status, StatusKind::Hastened), Some(20));
+
+        // an effect is gone as soon as it runs out, not a tick later
+        status.tick(4);
+        assert!(!status.has(StatusKind::Stunned));
+        assert!(status.has(StatusKind::Hastened));
+
+        status.tick(20);
+        assert!(status.effects.is_empty());
+        assert_eq!(status.speed_bonus(), 0);
+    }
+
+    #[test]
+    fn haste_speeds_up_turns_while_it_lasts() {
+        let mut status = effects(&[(StatusKind::Hastened, 4), (StatusKind::Bleeding, 24)]);
+        assert_eq!(status.turn_ticks(4), 6);
+        assert_eq!(status.speed_bonus(), HASTENED_SPEED);
+
+        status.tick(4);
+        assert_eq!(status.turn_ticks(4), 4);
+    }
+
+    #[test]
+    fn descriptions_count_whole_turns_left() {
+        let status = effects(&[(StatusKind::Stunned, 24), (StatusKind::Bleeding, 50)]);
+        assert_eq!(
+            status.describe(24),
+            vec![
+                "Stunned, 1 turn".to_string(),
+                "Bleeding, 3 turns".to_string()
+            ]
+        );
+    }
+}
@@ -1,7 +1,18 @@
-use super::{AiState, AttackIntent, CanActFlag, Map, MoveIntent, Moveset, Position, Viewshed};
-use rltk::{Algorithm2D, BaseMap};
+use super::{
+    sys_noise::{make_noise, NoiseKind, NoiseQueue},
+    AiState, AttackIntent, AttackType, CanActFlag, EnemyDeck, Health, Map, MoveIntent, Moveset,
+    Position, Viewshed,
+};
+use rltk::{Algorithm2D, BaseMap, Point};
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 
+// how much quieter a remembered noise gets every turn, until something new drowns it out
+const NOISE_FADE: f32 = 1.0;
+// how far out the safety map is worked out when running away
+const FLEE_DEPTH: f32 = 20.0;
+
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
 pub enum Behavior {
     Sleep,
     Wander,
@@ -9,6 +20,37 @@ pub enum Behavior {
     Flee,
 }
 
+impl Behavior {
+    /// A line for the look box, wandering is the usual state so it doesn't get one
+    pub fn describe(&self) -> Option<&'static str> {
+        match self {
+            Behavior::Sleep => Some("Asleep"),
+            Behavior::Wander => None,
+            Behavior::Chase => Some("Hunting"),
+            Behavior::Flee => Some("Fleeing"),
+        }
+    }
+
+    pub fn icon(&self) -> Option<char> {
+        match self {
+            Behavior::Sleep => Some('z'),
+            Behavior::Wander => None,
+            Behavior::Chase => Some('‼'),
+            Behavior::Flee => Some('«'),
+        }
+    }
+}
+
+impl AiState {
+    /// Anything asleep wakes up and goes to look at the point
+    pub fn wake(&mut self, toward: Point) {
+        if self.status == Behavior::Sleep {
+            self.status = Behavior::Chase;
+            self.tracking = Some(toward);
+        }
+    }
+}
+
 pub struct AiSystem;
 
 impl<'a> System<'a> for AiSystem {
@@ -19,11 +61,14 @@ impl<'a> System<'a> for AiSystem {
         WriteStorage<'a, MoveIntent>,
         WriteStorage<'a, AttackIntent>,
         WriteStorage<'a, AiState>,
+        WriteStorage<'a, Health>,
         ReadStorage<'a, Viewshed>,
         ReadStorage<'a, Moveset>,
+        WriteStorage<'a, EnemyDeck>,
         ReadExpect<'a, Map>,
         ReadExpect<'a, Entity>,
         WriteExpect<'a, rltk::RandomNumberGenerator>,
+        WriteExpect<'a, NoiseQueue>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -34,40 +79,59 @@ impl<'a> System<'a> for AiSystem {
             mut moves,
             mut attacks,
             mut states,
+            mut healths,
             viewsheds,
             movesets,
+            mut decks,
             map,
             player,
             mut rng,
+            mut noises,
         ) = data;
         let mut turn_done = Vec::new();
         let player_pos = positions.get(*player).unwrap();
 
-        for (ent, _turn, pos, state, viewshed, moveset) in (
+        for (ent, _turn, pos, state, mut health, viewshed, moveset, mut deck) in (
             &entities,
             &can_act,
             &positions,
             &mut states,
+            (&mut healths).maybe(),
             &viewsheds,
-            &movesets,
+            (&movesets).maybe(),
+            (&mut decks).maybe(),
         )
             .join()
         {
+            // with a deck, anything in hand is equally likely to be picked
+            let options: Vec<(AttackType, f32)> = match (&deck, moveset) {
+                (Some(deck), _) => {
+                    let hand = &deck.deck.hand;
+                    let chance = 1.0 / hand.len() as f32;
+                    hand.iter().map(|card| (*card, chance)).collect()
+                }
+                (None, Some(moveset)) => moveset.moves.clone(),
+                (None, None) => Vec::new(),
+            };
+            let mut played = None;
+
             let curr_index = map.get_index(pos.x, pos.y);
             let can_see_player = viewshed
                 .visible
                 .iter()
                 .any(|pos| pos.x == player_pos.x && pos.y == player_pos.y);
+            let player_point = rltk::Point::new(player_pos.x, player_pos.y);
+            state.heard = f32::max(state.heard - NOISE_FADE, 0.0);
 
             match state.status {
                 Behavior::Sleep => {
-                    // the do nothing state
-                    // TODO: trigger wake up
+                    // the do nothing state, until something wakes it up
                 }
                 Behavior::Wander => {
                     if can_see_player {
                         state.status = Behavior::Chase;
-                        state.tracking = Some(rltk::Point::new(player_pos.x, player_pos.y));
+                        state.tracking = Some(player_point);
+                        make_noise(&mut noises, NoiseKind::Shout, pos.as_point(), Some(ent));
                     } else {
                         // pick a random tile we can move to
                         let exits = map.get_available_exits(curr_index);
@@ -89,24 +153,52 @@ impl<'a> System<'a> for AiSystem {
                         // check if we have any attacks that can hit
                         let mut attack = None;
                         let orig_point = rltk::Point::new(pos.x, pos.y);
-                        let player_point = rltk::Point::new(player_pos.x, player_pos.y);
 
                         // track the player's current position
                         state.tracking = Some(player_point);
 
-                        let rolled_prob: f32 = rng.rand();
+                        // a parry does nothing unless it meets an attack, so those are kept for
+                        // countering
+                        let options: Vec<&(AttackType, f32)> = options
+                            .iter()
+                            .filter(|(card, _)| {
+                                !crate::move_type::get_attack_traits(card)
+                                    .contains(&crate::AttackTrait::Parry)
+                            })
+                            .collect();
+                        let total: f32 = options.iter().map(|(_, chance)| chance).sum();
+
+                        let rolled_prob: f32 = rng.rand::<f32>() * total;
                         let mut cumul_prob: f32 = 0.0;
 
+                        // moves without a shape, like blocks, are used on ourselves, which is
+                        // only worth doing once the player is close enough to fight
+                        let in_reach = options.iter().any(|(potential_attack, _)| {
+                            crate::move_type::is_attack_valid(
+                                potential_attack,
+                                orig_point,
+                                player_point,
+                            )
+                            .is_some()
+                        });
+
                         // TODO: smarter attack selection
                         // this is fine when all of the attacks have similar attack ranges
                         // however, we might run into cases where we are in range to attack, but we decided to use an attack thats not valid
-                        for (potential_attack, chance) in moveset.moves.iter() {
+                        for (potential_attack, chance) in options.iter() {
                             cumul_prob += chance;
                             if rolled_prob > cumul_prob {
                                 continue;
                             }
 
-                            if crate::move_type::is_attack_valid(
+                            if crate::move_type::get_attack_shape(potential_attack)
+                                == crate::RangeType::Empty
+                            {
+                                if in_reach {
+                                    attack = Some(potential_attack);
+                                    break;
+                                }
+                            } else if crate::move_type::is_attack_valid(
                                 &potential_attack,
                                 orig_point,
                                 player_point,
@@ -131,6 +223,10 @@ impl<'a> System<'a> for AiSystem {
                                         state.status = Behavior::Wander;
                                         state.tracking = None;
                                     }
+                                    Some(movement) if movement.loc == player_point => {
+                                        // already next to the player with nothing in hand that
+                                        // reaches, so hold still rather than walk into them
+                                    }
                                     Some(movement) => {
                                         moves
                                             .insert(ent, movement)
@@ -148,6 +244,7 @@ impl<'a> System<'a> for AiSystem {
                                 attacks
                                     .insert(ent, intent)
                                     .expect("Failed to insert attack from AI");
+                                played = Some(*attack);
                             }
                         }
                     } else {
@@ -179,7 +276,47 @@ impl<'a> System<'a> for AiSystem {
                     }
                 }
                 Behavior::Flee => {
-                    // TODO
+                    let healed = health
+                        .as_ref()
+                        .map_or(true, |health| health.current >= health.max);
+
+                    if healed {
+                        // back to full, so regroup and go after the player again
+                        state.status = Behavior::Chase;
+                    } else {
+                        if can_see_player {
+                            state.tracking = Some(player_point);
+                        } else if let Some(health) = &mut health {
+                            // catch its breath while it's out of sight
+                            health.current += 1;
+                        }
+
+                        let escape = state
+                            .tracking
+                            .and_then(|from| flee_from(&*map, curr_index, from));
+
+                        match escape {
+                            Some(movement) => {
+                                moves
+                                    .insert(ent, movement)
+                                    .expect("Failed to insert movement from AI");
+                            }
+                            None if can_see_player => {
+                                // cornered, so the only thing left to do is fight, until the
+                                // next bad hit sends it running again
+                                state.status = Behavior::Chase;
+                            }
+                            None => {}
+                        }
+                    }
+                }
+            }
+
+            // like the player passing, a turn without a card draws one
+            if let Some(deck) = &mut deck {
+                match played {
+                    Some(card) => deck.deck.discard_card(card),
+                    None => deck.deck.draw(&mut rng),
                 }
             }
 
@@ -192,6 +329,29 @@ impl<'a> System<'a> for AiSystem {
     }
 }
 
+// step to whichever neighbouring tile is the furthest walk from the danger, as long as it
+// gets us further away than we are now
+fn flee_from(map: &Map, curr_index: usize, danger: Point) -> Option<MoveIntent> {
+    let danger_index = map.point2d_to_index(danger);
+    let safety = rltk::DijkstraMap::new(map.width, map.height, &[danger_index], map, FLEE_DEPTH);
+
+    // our own tile is blocked by us, so work out its distance from the neighbours
+    let exits = map.get_available_exits(curr_index);
+    let curr_distance = exits
+        .iter()
+        .map(|(index, cost)| safety.map[*index] + cost)
+        .fold(std::f32::MAX, f32::min);
+    let best = rltk::DijkstraMap::find_highest_exit(&safety, curr_index, map)?;
+
+    if safety.map[best] > curr_distance {
+        Some(MoveIntent {
+            loc: map.index_to_point2d(best),
+        })
+    } else {
+        None
+    }
+}
+
 fn move_towards(map: &Map, curr_index: usize, target_index: usize) -> Option<MoveIntent> {
     let path = rltk::a_star_search(curr_index, target_index, &*map);
 
@@ -202,3 +362,74 @@ fn move_towards(map: &Map, curr_index: usize, target_index: usize) -> Option<Mov
         return None;
     }
 }
+
+/// Pick a card for a counter fighter to answer an attack with, if it has anything fast enough.
+/// A card from a deck is discarded, the same as one played on its turn.
+pub fn choose_counter(ecs: &World, ent: Entity, attacker: Entity) -> Option<AttackIntent> {
+    let positions = ecs.read_storage::<Position>();
+    let states = ecs.read_storage::<AiState>();
+    let movesets = ecs.read_storage::<Moveset>();
+    let mut decks = ecs.write_storage::<EnemyDeck>();
+    let mut rng = ecs.fetch_mut::<rltk::RandomNumberGenerator>();
+
+    // caught napping
+    if let Some(AiState {
+        status: Behavior::Sleep,
+        ..
+    }) = states.get(ent)
+    {
+        return None;
+    }
+
+    let orig_point = positions.get(ent)?.as_point();
+    let attacker_point = positions.get(attacker)?.as_point();
+
+    let options: Vec<(AttackType, f32)> = match (decks.get(ent), movesets.get(ent)) {
+        (Some(deck), _) => deck.deck.hand.iter().map(|card| (*card, 1.0)).collect(),
+        (None, Some(moveset)) => moveset.moves.clone(),
+        (None, None) => Vec::new(),
+    };
+
+    // dodging would need aiming out of the way, so enemies stick to hitting back or covering up
+    // each counter is aimed at whichever tile gets the attacker caught in its shape
+    let counters: Vec<(AttackType, f32, Point)> = options
+        .into_iter()
+        .filter_map(|(card, chance)| {
+            if crate::move_type::get_attack_timing(&card) != crate::AttackTiming::Fast
+                || crate::move_type::get_attack_traits(&card)
+                    .contains(&crate::AttackTrait::Movement)
+            {
+                return None;
+            }
+
+            let loc = if crate::move_type::get_attack_shape(&card) == crate::RangeType::Empty {
+                attacker_point
+            } else {
+                crate::move_type::is_attack_valid(&card, orig_point, attacker_point)?
+            };
+
+            Some((card, chance, loc))
+        })
+        .collect();
+
+    let total: f32 = counters.iter().map(|(_, chance, _)| chance).sum();
+    if counters.is_empty() || total <= 0.0 {
+        return None;
+    }
+
+    let rolled_prob = rng.rand::<f32>() * total;
+    let mut cumul_prob = 0.0;
+    let (counter, _, loc) = *counters
+        .iter()
+        .find(|(_, chance, _)| {
+            cumul_prob += chance;
+            rolled_prob <= cumul_prob
+        })
+        .unwrap_or(&counters[counters.len() - 1]);
+
+    if let Some(deck) = decks.get_mut(ent) {
+        deck.deck.discard_card(counter);
+    }
+
+    Some(crate::move_type::get_attack_intent(&counter, loc, None))
+}
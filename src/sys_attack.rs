@@ -1,4 +1,8 @@
-use super::{deck::Deck, AttackIntent, Health, Position};
+use super::{
+    deck::Deck,
+    sys_noise::{make_noise, NoiseKind, NoiseQueue},
+    AttackIntent, BlockAttack, EnemyDeck, EventQueue, Health, Position, StatusEffects,
+};
 use crate::move_type;
 use specs::prelude::*;
 
@@ -9,16 +13,48 @@ impl<'a> System<'a> for AttackSystem {
         Entities<'a>,
         ReadExpect<'a, Entity>,
         WriteExpect<'a, Deck>,
+        WriteExpect<'a, rltk::RandomNumberGenerator>,
+        WriteExpect<'a, EventQueue>,
+        WriteExpect<'a, NoiseQueue>,
         ReadStorage<'a, Position>,
         WriteStorage<'a, AttackIntent>,
         WriteStorage<'a, Health>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, BlockAttack>,
+        WriteStorage<'a, EnemyDeck>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, player, mut deck, positions, mut attacks, mut healths) = data;
+        let (
+            entities,
+            player,
+            mut deck,
+            mut rng,
+            mut queue,
+            mut noises,
+            positions,
+            mut attacks,
+            mut healths,
+            mut statuses,
+            mut blocks,
+            mut enemy_decks,
+        ) = data;
 
         for (ent, intent) in (&entities, &attacks).join() {
             let trait_list = move_type::get_intent_traits(&intent);
+            let deals_damage = trait_list.contains(&crate::AttackTrait::Damage);
+
+            // swinging at something makes a racket, whether or not it lands
+            if deals_damage {
+                if let Some(ent_pos) = positions.get(ent) {
+                    make_noise(
+                        &mut noises,
+                        NoiseKind::Attack,
+                        ent_pos.as_point(),
+                        Some(ent),
+                    );
+                }
+            }
 
             for att_trait in trait_list {
                 match att_trait {
@@ -30,6 +66,7 @@ impl<'a> System<'a> for AttackSystem {
                             };
                             let range = &move_type::get_attack_shape(&intent.main);
                             crate::add_event(
+                                &mut queue,
                                 &event,
                                 Some(*intent),
                                 Some(ent),
@@ -40,18 +77,29 @@ impl<'a> System<'a> for AttackSystem {
                         }
                     }
                     crate::AttackTrait::Damage => {
-                        crate::add_damage_event(intent, Some(ent), true);
+                        crate::add_damage_event(&mut queue, intent, Some(ent), true);
                     }
                     crate::AttackTrait::Movement => {
                         let event = crate::EventType::Movement;
                         let range = &move_type::get_attack_shape(&intent.main);
-                        crate::add_event(&event, Some(*intent), Some(ent), range, intent.loc, false)
+                        crate::add_event(
+                            &mut queue,
+                            &event,
+                            Some(*intent),
+                            Some(ent),
+                            range,
+                            intent.loc,
+                            false,
+                        )
                     }
                     crate::AttackTrait::Draw { amount } => {
                         if ent == *player {
-                            // TODO: enemies don't have a deck for now
                             for _ in 0..amount {
-                                deck.draw();
+                                deck.draw(&mut rng);
+                            }
+                        } else if let Some(enemy_deck) = enemy_decks.get_mut(ent) {
+                            for _ in 0..amount {
+                                enemy_deck.deck.draw(&mut rng);
                             }
                         }
                     }
@@ -61,12 +109,35 @@ impl<'a> System<'a> for AttackSystem {
                             health.current = std::cmp::min(health.current, health.max);
                         }
                     }
+                    crate::AttackTrait::Status { kind, duration } => {
+                        // statuses on an attack ride along with the hit, otherwise they're for
+                        // whoever played the card
+                        if !deals_damage {
+                            crate::status::inflict(&mut statuses, ent, kind, duration);
+                        }
+                    }
+                    crate::AttackTrait::Block { amount } => {
+                        add_block(&mut blocks, ent, amount as u32, 0);
+                    }
+                    crate::AttackTrait::Brace { amount } => {
+                        add_block(&mut blocks, ent, 0, amount);
+                    }
+                    crate::AttackTrait::Parry => {
+                        // the parry has to meet the attack head on to do anything
+                        let range = &move_type::get_attack_shape(&intent.main);
+                        crate::add_event(
+                            &mut queue,
+                            &crate::EventType::Parry,
+                            Some(*intent),
+                            Some(ent),
+                            range,
+                            intent.loc,
+                            false,
+                        );
+                    }
                     crate::AttackTrait::Modifier => {
                         // this is just a marker, modified attacks don't do anything special (yet?)
                     }
-                    crate::AttackTrait::Equipment => {
-                        // this is another marker
-                    }
                 }
             }
         }
@@ -74,3 +145,29 @@ impl<'a> System<'a> for AttackSystem {
         attacks.clear();
     }
 }
+
+/// Put up a block, on top of whatever is already waiting for the next hit
+pub fn add_block(
+    blocks: &mut WriteStorage<BlockAttack>,
+    ent: Entity,
+    amount: u32,
+    guard_bonus: i32,
+) {
+    match blocks.get_mut(ent) {
+        Some(block) => {
+            block.block_amount += amount;
+            block.guard_bonus += guard_bonus;
+        }
+        None => {
+            blocks
+                .insert(
+                    ent,
+                    BlockAttack {
+                        block_amount: amount,
+                        guard_bonus,
+                    },
+                )
+                .expect("Failed to insert BlockAttack");
+        }
+    }
+}
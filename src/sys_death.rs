@@ -1,4 +1,7 @@
-use super::{DeathTrigger, Health, Map, Position, RunState};
+use super::{
+    sys_noise::{make_noise, NoiseKind, NoiseQueue},
+    DeathTrigger, EventQueue, EventType, Health, Map, Position, RunState,
+};
 use specs::prelude::*;
 
 pub struct DeathSystem;
@@ -9,13 +12,25 @@ impl<'a> System<'a> for DeathSystem {
         ReadExpect<'a, Entity>,
         WriteExpect<'a, Map>,
         WriteExpect<'a, RunState>,
+        WriteExpect<'a, EventQueue>,
+        WriteExpect<'a, NoiseQueue>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, DeathTrigger>,
         ReadStorage<'a, Health>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, player, mut map, mut run_state, positions, death_triggers, healths) = data;
+        let (
+            entities,
+            player,
+            mut map,
+            mut run_state,
+            mut queue,
+            mut noises,
+            positions,
+            death_triggers,
+            healths,
+        ) = data;
         let mut dead = Vec::new();
 
         for (ent, pos, health, effect) in
@@ -23,7 +38,12 @@ impl<'a> System<'a> for DeathSystem {
         {
             if health.current <= 0 {
                 if let Some(effect) = effect {
+                    if let EventType::Damage { .. } = effect.event {
+                        make_noise(&mut noises, NoiseKind::Explosion, pos.as_point(), None);
+                    }
+
                     crate::add_event(
+                        &mut queue,
                         &effect.event,
                         None,
                         None,
@@ -1,4 +1,4 @@
-use super::{Map, MoveIntent, Position, Viewshed};
+use super::{Map, MoveIntent, Position, Schedulable, TileType, Viewshed};
 use specs::prelude::*;
 
 pub struct MovementSystem;
@@ -10,22 +10,33 @@ impl<'a> System<'a> for MovementSystem {
         WriteStorage<'a, Position>,
         WriteStorage<'a, MoveIntent>,
         WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, Schedulable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut map, mut positions, mut movements, mut viewsheds) = data;
+        let (entities, mut map, mut positions, mut movements, mut viewsheds, mut schedulables) =
+            data;
+        let mut opened_door = false;
 
-        for (ent, pos, movement, viewshed) in (
+        for (ent, pos, movement, viewshed, sched) in (
             &entities,
             &mut positions,
             &movements,
             (&mut viewsheds).maybe(),
+            (&mut schedulables).maybe(),
         )
             .join()
         {
             let new_pos = movement.loc;
             let new_index = map.get_index(new_pos.x, new_pos.y);
 
+            // walking into a closed door opens it, which takes the whole move
+            if map.tiles[new_index] == TileType::Door {
+                map.open_door(new_index);
+                opened_door = true;
+                continue;
+            }
+
             // check if the tile is blocked, since it may have changed
             if !map.blocked_tiles[new_index] {
                 map.move_creature(ent, rltk::Point::new(pos.x, pos.y), new_pos);
@@ -36,6 +47,19 @@ impl<'a> System<'a> for MovementSystem {
                 if let Some(viewshed) = viewshed {
                     viewshed.dirty = true;
                 }
+
+                // rough ground pushes back the mover's next turn
+                if let Some(sched) = sched {
+                    let extra_cost = map.tiles[new_index].move_cost() - 1.0;
+                    sched.current += (sched.base as f32 * extra_cost) as i32;
+                }
+            }
+        }
+
+        // anyone could be looking through the doorway now
+        if opened_door {
+            for viewshed in (&mut viewsheds).join() {
+                viewshed.dirty = true;
             }
         }
 
@@ -0,0 +1,212 @@
+use super::{AiState, Behavior, Map, Position, Viewshed};
+use rltk::{Algorithm2D, Point};
+use serde::Serialize;
+use specs::prelude::*;
+use std::collections::VecDeque;
+
+// anything asleep needs a noise at least this loud to wake up
+const WAKE_LOUDNESS: f32 = 2.0;
+// anything awake notices a noise at least this loud
+const NOTICE_LOUDNESS: f32 = 1.0;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum NoiseKind {
+    Step,
+    // a step taken carefully, slower but hard to hear
+    QuietStep,
+    Attack,
+    Explosion,
+    // something pushed into a wall or someone else
+    Slam,
+    // an enemy calling out that it has spotted the player
+    Shout,
+}
+
+impl NoiseKind {
+    // how many tiles it carries
+    fn radius(&self) -> i32 {
+        match self {
+            NoiseKind::Step => 5,
+            NoiseKind::QuietStep => 2,
+            NoiseKind::Attack => 8,
+            NoiseKind::Explosion => 12,
+            NoiseKind::Slam => 6,
+            NoiseKind::Shout => 8,
+        }
+    }
+
+    // how loud it is where it's made, it fades away to nothing past the radius
+    fn intensity(&self) -> f32 {
+        match self {
+            NoiseKind::Step => 3.0,
+            // loud enough for whatever is right next to the player, but never enough to wake it
+            NoiseKind::QuietStep => 2.0,
+            NoiseKind::Attack => 5.0,
+            NoiseKind::Explosion => 8.0,
+            NoiseKind::Slam => 4.0,
+            NoiseKind::Shout => 6.0,
+        }
+    }
+}
+
+struct Noise {
+    kind: NoiseKind,
+    source: Point,
+    // whoever made it doesn't need to go looking for it
+    maker: Option<Entity>,
+}
+
+/// Noises made since the last time the noise system ran
+pub struct NoiseQueue {
+    noises: Vec<Noise>,
+}
+
+impl NoiseQueue {
+    pub fn new() -> Self {
+        NoiseQueue { noises: Vec::new() }
+    }
+}
+
+pub fn make_noise(queue: &mut NoiseQueue, kind: NoiseKind, source: Point, maker: Option<Entity>) {
+    queue.noises.push(Noise {
+        kind,
+        source,
+        maker,
+    });
+}
+
+/// How well hidden the player is, from the point of view of the enemies
+#[derive(PartialEq, Copy, Clone, Serialize)]
+pub enum Stealth {
+    // nothing awake is after the player
+    Hidden,
+    // something is looking for the player, but can't see them right now
+    Hunted,
+    // something awake can see the player
+    Seen,
+}
+
+impl Stealth {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stealth::Hidden => "HIDDEN",
+            Stealth::Hunted => "HUNTED",
+            Stealth::Seen => "SEEN",
+        }
+    }
+}
+
+pub fn player_stealth(ecs: &World) -> Stealth {
+    let positions = ecs.read_storage::<Position>();
+    let states = ecs.read_storage::<AiState>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let player = ecs.fetch::<Entity>();
+    let player_point = match positions.get(*player) {
+        Some(pos) => pos.as_point(),
+        None => return Stealth::Hidden,
+    };
+
+    let mut stealth = Stealth::Hidden;
+    for (state, viewshed) in (&states, &viewsheds).join() {
+        if state.status == Behavior::Sleep {
+            continue;
+        }
+
+        if viewshed.visible.contains(&player_point) {
+            return Stealth::Seen;
+        }
+
+        if state.status == Behavior::Chase {
+            stealth = Stealth::Hunted;
+        }
+    }
+
+    stealth
+}
+
+pub struct NoiseSystem;
+
+impl<'a> System<'a> for NoiseSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, NoiseQueue>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, AiState>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, map, mut queue, positions, mut states) = data;
+
+        for noise in queue.noises.drain(..) {
+            let distances = spread(&map, &noise);
+            let radius = noise.kind.radius();
+
+            for (ent, pos, state) in (&entities, &positions, &mut states).join() {
+                if noise.maker == Some(ent) {
+                    continue;
+                }
+
+                let index = map.point2d_to_index(pos.as_point());
+                if let Some(distance) = distances[index] {
+                    let falloff = 1.0 - distance as f32 / (radius + 1) as f32;
+                    state.hear(noise.source, noise.kind.intensity() * falloff);
+                }
+            }
+        }
+    }
+}
+
+// how many steps the noise takes to reach each tile, walls and closed doors keep it out
+fn spread(map: &Map, noise: &Noise) -> Vec<Option<i32>> {
+    let mut distances = vec![None; map.tiles.len()];
+    if !map.in_bounds(noise.source) {
+        return distances;
+    }
+
+    let start = map.point2d_to_index(noise.source);
+    let mut open = VecDeque::new();
+    distances[start] = Some(0);
+    open.push_back((noise.source, 0));
+
+    while let Some((point, distance)) = open.pop_front() {
+        if distance >= noise.kind.radius() {
+            continue;
+        }
+
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+            let next = Point::new(point.x + dx, point.y + dy);
+            if !map.in_bounds(next) {
+                continue;
+            }
+
+            let index = map.point2d_to_index(next);
+            if distances[index].is_none() && !map.tiles[index].blocks_sight() {
+                distances[index] = Some(distance + 1);
+                open.push_back((next, distance + 1));
+            }
+        }
+    }
+
+    distances
+}
+
+impl AiState {
+    /// Anything asleep only wakes for a loud enough noise, anything awake goes to look at the
+    /// loudest thing it's heard lately
+    pub fn hear(&mut self, source: Point, loudness: f32) {
+        if self.status == Behavior::Sleep {
+            if loudness >= WAKE_LOUDNESS {
+                self.wake(source);
+                self.heard = loudness;
+            }
+        } else if loudness >= NOTICE_LOUDNESS && loudness > self.heard {
+            if self.status == Behavior::Wander {
+                self.status = Behavior::Chase;
+            }
+
+            self.tracking = Some(source);
+            self.heard = loudness;
+        }
+    }
+}
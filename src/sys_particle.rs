@@ -1,39 +1,24 @@
-use super::{CardLifetime, ParticleLifetime, Position, Renderable};
-use rltk::{FontCharType, Point, Rltk};
+use rltk::{FontCharType, Point};
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 
-pub fn cleanup_particles(ecs: &mut World, ctx: &Rltk) {
-    let dead_particles = update_lifetimes(ecs, ctx);
+pub fn cleanup_particles(ecs: &mut World, frame_time_ms: f32) {
+    let mut builder = ecs.fetch_mut::<ParticleBuilder>();
 
-    for dead in dead_particles.iter() {
-        ecs.delete_entity(*dead).expect("Failed to delete particle");
+    for particle in builder.particles.iter_mut() {
+        particle.remaining -= frame_time_ms;
     }
-}
-
-fn update_lifetimes(ecs: &mut World, ctx: &Rltk) -> Vec<Entity> {
-    let mut dead_particles = Vec::new();
-    let mut particles = ecs.write_storage::<ParticleLifetime>();
-    let mut cards = ecs.write_storage::<CardLifetime>();
-    let entities = ecs.entities();
+    builder
+        .particles
+        .retain(|particle| particle.remaining >= 0.0);
 
-    for (ent, mut lifetime) in (&entities, &mut particles).join() {
-        lifetime.remaining -= ctx.frame_time_ms;
-        if lifetime.remaining < 0.0 {
-            dead_particles.push(ent);
-        }
+    for card in builder.cards.iter_mut() {
+        card.remaining -= frame_time_ms;
     }
-
-    for (ent, mut lifetime) in (&entities, &mut cards).join() {
-        lifetime.remaining -= ctx.frame_time_ms;
-        if lifetime.remaining < 0.0 {
-            dead_particles.push(ent);
-        }
-    }
-
-    dead_particles
+    builder.cards.retain(|card| card.remaining >= 0.0);
 }
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub struct ParticleRequest {
     pub position: Point,
     pub color: rltk::RGB,
@@ -48,9 +33,27 @@ pub struct CardRequest {
     pub affected: std::sync::Arc<Vec<rltk::Point>>,
 }
 
+// particles and floating cards are kept out of the ecs, so that how long they stay on screen
+// can't change which entities the game creates (replays depend on this)
+pub struct Particle {
+    pub position: Point,
+    pub color: rltk::RGB,
+    pub symbol: FontCharType,
+    pub base: f32,
+    pub remaining: f32,
+    pub should_fade: bool,
+}
+
+pub struct FloatingCard {
+    pub remaining: f32,
+    pub data: CardRequest,
+}
+
 pub struct ParticleBuilder {
     requests: Vec<ParticleRequest>,
     card_stack: Vec<CardRequest>,
+    pub particles: Vec<Particle>,
+    pub cards: Vec<FloatingCard>,
 }
 
 impl ParticleBuilder {
@@ -58,6 +61,8 @@ impl ParticleBuilder {
         ParticleBuilder {
             requests: Vec::new(),
             card_stack: Vec::new(),
+            particles: Vec::new(),
+            cards: Vec::new(),
         }
     }
 
@@ -74,65 +79,30 @@ impl ParticleBuilder {
 pub struct ParticleSpawnSystem;
 
 impl<'a> System<'a> for ParticleSpawnSystem {
-    type SystemData = (
-        Entities<'a>,
-        WriteStorage<'a, Position>,
-        WriteStorage<'a, Renderable>,
-        WriteStorage<'a, ParticleLifetime>,
-        WriteStorage<'a, CardLifetime>,
-        WriteExpect<'a, ParticleBuilder>,
-    );
+    type SystemData = WriteExpect<'a, ParticleBuilder>;
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut positions, mut renderables, mut lifetimes, mut cards, mut builder) =
-            data;
+        let mut builder = data;
+        let builder = &mut *builder;
 
         for request in builder.requests.drain(..) {
-            let particle = entities.create();
-            positions
-                .insert(
-                    particle,
-                    Position {
-                        x: request.position.x,
-                        y: request.position.y,
-                    },
-                )
-                .expect("Failed to insert Position for particle");
-            renderables
-                .insert(
-                    particle,
-                    Renderable {
-                        symbol: request.symbol,
-                        fg: request.color,
-                        bg: crate::bg_color(),
-                    },
-                )
-                .expect("Failed to insert Renderable for particle");
-            lifetimes
-                .insert(
-                    particle,
-                    ParticleLifetime {
-                        base: request.lifetime,
-                        remaining: request.lifetime,
-                        should_fade: true,
-                    },
-                )
-                .expect("Failed to insert ParticleLifetime for particle");
+            builder.particles.push(Particle {
+                position: request.position,
+                color: request.color,
+                symbol: request.symbol,
+                base: request.lifetime,
+                remaining: request.lifetime,
+                should_fade: true,
+            });
         }
 
         for (i, mut request) in builder.card_stack.drain(..).enumerate() {
             request.offset += i as i32;
 
-            let card = entities.create();
-            cards
-                .insert(
-                    card,
-                    CardLifetime {
-                        remaining: 400.0,
-                        data: request,
-                    },
-                )
-                .expect("Failed to insert CardLifetime for card");
+            builder.cards.push(FloatingCard {
+                remaining: 400.0,
+                data: request,
+            });
         }
     }
 }
@@ -1,4 +1,7 @@
-use super::{Heal, Health, Map, Position, RunState, Schedulable, SkillChoice};
+use super::{
+    deck::Deck, equipment, gamelog::GameLog, EquipmentPickup, Equipped, Heal, Health, Map,
+    Position, Renderable, RunState, Schedulable, SkillChoice, TileType, Viewable, Viewshed,
+};
 use specs::prelude::*;
 
 pub struct PickupSystem;
@@ -9,11 +12,18 @@ impl<'a> System<'a> for PickupSystem {
         ReadExpect<'a, Entity>,
         WriteExpect<'a, Map>,
         WriteExpect<'a, RunState>,
-        ReadStorage<'a, Position>,
+        WriteStorage<'a, Position>,
         ReadStorage<'a, Schedulable>,
         ReadStorage<'a, Heal>,
         ReadStorage<'a, SkillChoice>,
         WriteStorage<'a, Health>,
+        WriteStorage<'a, EquipmentPickup>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, Viewable>,
+        WriteExpect<'a, Deck>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -22,13 +32,21 @@ impl<'a> System<'a> for PickupSystem {
             player,
             mut map,
             mut run_state,
-            positions,
+            mut positions,
             scheds,
             heals,
             skills,
             mut healths,
+            mut pickups,
+            mut equipped,
+            mut viewsheds,
+            mut renderables,
+            mut viewables,
+            mut deck,
+            mut log,
         ) = data;
         let mut consumed = Vec::new();
+        let mut equipping = Vec::new();
 
         for (ent, pos, mut health, _) in (&entities, &positions, &mut healths, &scheds).join() {
             let point = rltk::Point::new(pos.x, pos.y);
@@ -53,11 +71,88 @@ impl<'a> System<'a> for PickupSystem {
                         }
 
                         consumed.push(item_ent);
+                    } else if pickups.get(item_ent).is_some() && *player == ent {
+                        equipping.push((point, item_ent));
+                    } else {
+                        // only the player wears anything, so leave it for them
+                        map.track_item(item_ent, point);
                     }
                 }
             }
         }
 
+        for (point, item_ent) in equipping {
+            let item = pickups
+                .get(item_ent)
+                .expect("Equipment pickup went missing")
+                .item;
+            let def = equipment::get_equipment(&item);
+            let worn = equipped
+                .entry(*player)
+                .expect("Failed to get player equipment")
+                .or_insert_with(Equipped::default);
+
+            // whatever was in the slot goes on the floor next to the player, it can't go under
+            // them or it would be swapped straight back
+            let old_item = worn.in_slot(def.slot);
+            let drop_point = old_item.and_then(|_| free_tile_near(&map, point));
+            if old_item.is_some() && drop_point.is_none() {
+                // nowhere to put it, so the new piece stays where it is
+                map.track_item(item_ent, point);
+                continue;
+            }
+
+            worn.equip(item);
+            equipment::put_on(
+                item,
+                healths.get_mut(*player),
+                viewsheds.get_mut(*player),
+                Some(&mut deck),
+            );
+
+            match (old_item, drop_point) {
+                (Some(old_item), Some(drop_point)) => {
+                    equipment::take_off(
+                        old_item,
+                        healths.get_mut(*player),
+                        viewsheds.get_mut(*player),
+                        Some(&mut deck),
+                    );
+
+                    // reuse the pickup for the piece that came off
+                    pickups
+                        .insert(item_ent, EquipmentPickup { item: old_item })
+                        .expect("Failed to swap equipment pickup");
+                    renderables
+                        .insert(item_ent, crate::spawner::equipment_renderable(old_item))
+                        .expect("Failed to swap equipment renderable");
+                    viewables
+                        .insert(item_ent, crate::spawner::equipment_viewable(old_item))
+                        .expect("Failed to swap equipment viewable");
+                    positions
+                        .insert(
+                            item_ent,
+                            Position {
+                                x: drop_point.x,
+                                y: drop_point.y,
+                            },
+                        )
+                        .expect("Failed to move equipment pickup");
+                    map.track_item(item_ent, drop_point);
+
+                    log.entries.push(format!(
+                        "You swap your {} for the {}",
+                        equipment::get_equipment(&old_item).name,
+                        def.name
+                    ));
+                }
+                _ => {
+                    consumed.push(item_ent);
+                    log.entries.push(format!("You put on the {}", def.name));
+                }
+            }
+        }
+
         for item in consumed {
             entities
                 .delete(item)
@@ -65,3 +160,34 @@ impl<'a> System<'a> for PickupSystem {
         }
     }
 }
+
+// an empty floor tile next to the point, where something can be dropped
+fn free_tile_near(map: &Map, point: rltk::Point) -> Option<rltk::Point> {
+    for (dx, dy) in [
+        (-1, 0),
+        (1, 0),
+        (0, -1),
+        (0, 1),
+        (-1, -1),
+        (1, -1),
+        (-1, 1),
+        (1, 1),
+    ]
+    .iter()
+    {
+        let (x, y) = (point.x + dx, point.y + dy);
+        if x < 0 || y < 0 || x >= map.width || y >= map.height {
+            continue;
+        }
+
+        let index = map.get_index(x, y);
+        if map.tiles[index] == TileType::Floor
+            && !map.blocked_tiles[index]
+            && map.item_map.get(&index).is_none()
+        {
+            return Some(rltk::Point::new(x, y));
+        }
+    }
+
+    None
+}
@@ -1,4 +1,7 @@
-use super::{CanActFlag, Position, RunState, Schedulable};
+use super::{
+    gamelog::GameLog, CanActFlag, Health, Position, RunState, Schedulable, StatusEffects,
+    StatusKind,
+};
 use specs::prelude::*;
 
 pub struct TurnSystem;
@@ -11,10 +14,23 @@ impl<'a> System<'a> for TurnSystem {
         WriteStorage<'a, Schedulable>,
         ReadStorage<'a, Position>,
         ReadExpect<'a, Entity>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, Health>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut game_state, entities, mut can_act, mut schedulables, pos, player) = data;
+        let (
+            mut game_state,
+            entities,
+            mut can_act,
+            mut schedulables,
+            pos,
+            player,
+            mut statuses,
+            mut healths,
+            mut log,
+        ) = data;
         assert!(*game_state == RunState::Running);
 
         if can_act.get(*player).is_some() {
@@ -22,13 +38,59 @@ impl<'a> System<'a> for TurnSystem {
             return;
         }
 
-        for (ent, sched, _pos) in (&entities, &mut schedulables, &pos).join() {
-            sched.current -= sched.delta;
-            if sched.current > 0 {
+        for (ent, sched, _pos, status) in
+            (&entities, &mut schedulables, &pos, (&mut statuses).maybe()).join()
+        {
+            // haste brings turns around sooner, but effects still wear off at the usual pace
+            sched.current -= status
+                .as_ref()
+                .map_or(sched.delta, |status| status.turn_ticks(sched.delta));
+            let turn_ready = sched.current <= 0;
+
+            // effects are checked before they tick down, so that one lasting exactly a turn
+            // still catches the turn it was meant for
+            let (stunned, bleeding) = match status {
+                None => (false, false),
+                Some(status) => {
+                    let active = (
+                        status.has(StatusKind::Stunned),
+                        status.has(StatusKind::Bleeding),
+                    );
+                    status.tick(sched.delta);
+                    active
+                }
+            };
+
+            if !turn_ready {
                 continue;
             }
 
             sched.current += sched.base;
+
+            if bleeding {
+                if let Some(health) = healths.get_mut(ent) {
+                    let was_alive = health.current > 0;
+                    health.current -= 1;
+
+                    if ent == *player && was_alive && health.current <= 0 {
+                        log.entries.push("You bled out".to_string());
+                        log.killed_by = Some("bleeding".to_string());
+                    }
+
+                    if health.current <= 0 {
+                        continue;
+                    }
+                }
+            }
+
+            if stunned {
+                if ent == *player {
+                    log.entries.push("You are stunned".to_string());
+                }
+
+                continue;
+            }
+
             can_act
                 .insert(
                     ent,
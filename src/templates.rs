@@ -0,0 +1,425 @@
+use crate::Behavior;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+// like the card file, edits are picked up the next time the game starts
+pub const TEMPLATES_PATH: &str = "resources/spawns.ron";
+const BUILTIN_TEMPLATES: &str = include_str!("../resources/spawns.ron");
+
+// along with a hash of the file it came from
+static TEMPLATES: OnceLock<(TemplateList, u64)> = OnceLock::new();
+
+pub enum TemplateError {
+    Io(std::io::Error),
+    Format(ron::error::SpannedError),
+    Invalid { template: String, reason: String },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::Io(err) => write!(f, "couldn't read {}: {}", TEMPLATES_PATH, err),
+            TemplateError::Format(err) => write!(f, "{}:{}", TEMPLATES_PATH, err),
+            TemplateError::Invalid { template, reason } => {
+                write!(f, "{}: \"{}\" {}", TEMPLATES_PATH, template, reason)
+            }
+        }
+    }
+}
+
+/// A number that grows with the quality of whatever is being spawned
+#[derive(Copy, Clone, Deserialize)]
+pub struct Scaled {
+    pub base: i32,
+    // gain 1 for every `step` points of quality, 0 means it doesn't scale
+    #[serde(default)]
+    pub step: i32,
+}
+
+impl Scaled {
+    pub fn at(&self, quality: i32) -> i32 {
+        if self.step <= 0 {
+            self.base
+        } else {
+            self.base + quality / self.step
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RenderableTemplate {
+    pub glyph: char,
+    pub fg: (u8, u8, u8),
+    #[serde(default)]
+    pub bg: (u8, u8, u8),
+}
+
+#[derive(Deserialize)]
+pub struct ViewableTemplate {
+    pub name: String,
+    pub description: Vec<String>,
+    // whether this can be looked at with the view command
+    #[serde(default)]
+    pub listed: bool,
+}
+
+// the cards are shuffled at spawn, and a full hand is drawn
+#[derive(Deserialize)]
+pub struct DeckTemplate {
+    pub cards: Vec<String>,
+    pub hand_size: usize,
+}
+
+// leaving either value out uses the ruleset's turn speed
+#[derive(Deserialize)]
+pub struct SchedulableTemplate {
+    #[serde(default)]
+    pub base: Option<i32>,
+    #[serde(default)]
+    pub delta: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub enum DeathEffect {
+    Explode { damage: Scaled, size: Scaled },
+    Drop { item: crate::DropType },
+}
+
+#[derive(Deserialize)]
+pub struct Template {
+    pub name: String,
+    pub renderable: Option<RenderableTemplate>,
+    pub viewable: Option<ViewableTemplate>,
+    pub schedulable: Option<SchedulableTemplate>,
+    pub viewshed: Option<i32>,
+    #[serde(default)]
+    pub blocks_tile: bool,
+    #[serde(default)]
+    pub openable: bool,
+    pub health: Option<i32>,
+    pub moveset: Option<Vec<(String, f32)>>,
+    pub deck: Option<DeckTemplate>,
+    // counter fighters hit back with a fast card when the player attacks them
+    #[serde(default)]
+    pub counter: bool,
+    pub ai: Option<Behavior>,
+    // health at which it runs away, 0 fights to the end
+    #[serde(default)]
+    pub flee: i32,
+    pub death_trigger: Option<DeathEffect>,
+}
+
+#[derive(Deserialize)]
+pub struct SpawnTable {
+    pub min: Scaled,
+    pub max: Scaled,
+    // (template name, chance) pairs
+    pub spawns: Vec<(String, f32)>,
+    // chance that everything this table puts in a room starts out asleep
+    #[serde(default)]
+    pub asleep: f32,
+}
+
+#[derive(Deserialize)]
+struct TemplateList {
+    templates: Vec<Template>,
+    spawn_tables: Vec<SpawnTable>,
+}
+
+/// Load the templates, reporting anything wrong with the file
+/// Cards need to be loadable first, since movesets and decks refer to them
+/// Templates are otherwise loaded the first time they are needed, and a bad file is quietly
+/// swapped for the built-in templates then
+pub fn load_templates() -> Result<(), TemplateError> {
+    if TEMPLATES.get().is_none() {
+        let templates = read_templates()?;
+        let _ = TEMPLATES.set(templates);
+    }
+
+    Ok(())
+}
+
+fn loaded() -> &'static (TemplateList, u64) {
+    TEMPLATES.get_or_init(|| {
+        read_templates()
+            .or_else(|_| {
+                parse_templates(BUILTIN_TEMPLATES)
+                    .map(|list| (list, crate::hash_data(BUILTIN_TEMPLATES)))
+            })
+            .unwrap_or_else(|err| panic!("built-in templates are broken, {}", err))
+    })
+}
+
+fn template_list() -> &'static TemplateList {
+    &loaded().0
+}
+
+/// A hash of the template file that was loaded, so replays can tell when it has changed
+pub fn data_hash() -> u64 {
+    loaded().1
+}
+
+fn read_templates() -> Result<(TemplateList, u64), TemplateError> {
+    let contents = if std::path::Path::new(TEMPLATES_PATH).exists() {
+        std::fs::read_to_string(TEMPLATES_PATH).map_err(TemplateError::Io)?
+    } else {
+        BUILTIN_TEMPLATES.to_string()
+    };
+
+    let list = parse_templates(&contents)?;
+    Ok((list, crate::hash_data(&contents)))
+}
+
+fn parse_templates(contents: &str) -> Result<TemplateList, TemplateError> {
+    let list: TemplateList = ron::de::from_str(contents).map_err(TemplateError::Format)?;
+    let mut names = HashSet::new();
+
+    for template in list.templates.iter() {
+        let invalid = |reason: String| TemplateError::Invalid {
+            template: template.name.clone(),
+            reason,
+        };
+
+        if template.name.is_empty() {
+            return Err(invalid("has no name".to_string()));
+        }
+        if !names.insert(template.name.as_str()) {
+            return Err(invalid("is defined more than once".to_string()));
+        }
+        if let Some(health) = template.health {
+            if health <= 0 {
+                return Err(invalid("needs at least 1 health".to_string()));
+            }
+        }
+
+        if let Some(moveset) = &template.moveset {
+            for (card, _) in moveset.iter() {
+                if crate::cards::find_card(card).is_none() {
+                    return Err(invalid(format!("uses the undefined card \"{}\"", card)));
+                }
+            }
+
+            // enemies pick moves based on these, so they need to cover every roll
+            let total: f32 = moveset.iter().map(|(_, chance)| chance).sum();
+            if (total - 1.0).abs() > 0.001 {
+                return Err(invalid(format!(
+                    "has move chances that add up to {} instead of 1",
+                    total
+                )));
+            }
+        }
+
+        if let Some(deck) = &template.deck {
+            if template.moveset.is_some() {
+                return Err(invalid(
+                    "has both a moveset and a deck, only one can be used".to_string(),
+                ));
+            }
+            if deck.cards.is_empty() || deck.hand_size == 0 {
+                return Err(invalid(
+                    "needs at least one card and a hand size above 0".to_string(),
+                ));
+            }
+
+            for card in deck.cards.iter() {
+                if crate::cards::find_card(card).is_none() {
+                    return Err(invalid(format!("uses the undefined card \"{}\"", card)));
+                }
+            }
+        }
+    }
+
+    for (index, table) in list.spawn_tables.iter().enumerate() {
+        let invalid = |reason: String| TemplateError::Invalid {
+            template: format!("spawn table {}", index + 1),
+            reason,
+        };
+
+        for (name, _) in table.spawns.iter() {
+            if !names.contains(name.as_str()) {
+                return Err(invalid(format!(
+                    "spawns the undefined template \"{}\"",
+                    name
+                )));
+            }
+        }
+
+        // the max has to keep up with the min at every quality, so it can't scale slower
+        let (min, max) = (table.min, table.max);
+        let max_falls_behind = min.step > 0 && (max.step <= 0 || max.step > min.step);
+        if min.base < 0 || min.base > max.base || max_falls_behind {
+            return Err(invalid(
+                "needs a min of at least 0 that never goes over its max".to_string(),
+            ));
+        }
+
+        if table.spawns.is_empty() {
+            return Err(invalid("doesn't spawn anything".to_string()));
+        }
+
+        // rooms pick what to spawn based on these, so they need to cover every roll
+        let total: f32 = table.spawns.iter().map(|(_, chance)| chance).sum();
+        if (total - 1.0).abs() > 0.001 {
+            return Err(invalid(format!(
+                "has spawn chances that add up to {} instead of 1",
+                total
+            )));
+        }
+    }
+
+    Ok(list)
+}
+
+pub fn get_template(name: &str) -> Option<&'static Template> {
+    template_list()
+        .templates
+        .iter()
+        .find(|template| template.name == name)
+}
+
+pub fn all_templates() -> &'static [Template] {
+    &template_list().templates
+}
+
+pub fn spawn_tables() -> &'static [SpawnTable] {
+    &template_list().spawn_tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(name: &str, moveset: &str) -> String {
+        format!(
+            "(name: \"{}\", health: 5, moveset: [{}], ai: Wander)",
+            name, moveset
+        )
+    }
+
+    fn spawn_table(min: &str, max: &str, spawns: &str) -> String {
+        format!("(min: {}, max: {}, spawns: [{}])", min, max, spawns)
+    }
+
+    fn template_file(templates: &[String], spawn_tables: &[String]) -> String {
+        format!(
+            "#![enable(implicit_some)]\n(templates: [{}], spawn_tables: [{}])",
+            templates.join(", "),
+            spawn_tables.join(", ")
+        )
+    }
+
+    // the message the player would see for a template file that should be turned down
+    fn rejection(contents: &str) -> String {
+        match parse_templates(contents) {
+            Ok(_) => panic!("template file should have been rejected"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn builtin_templates_parse() {
+        if let Err(err) = parse_templates(BUILTIN_TEMPLATES) {
+            panic!("{}", err);
+        }
+    }
+
+    #[test]
+    fn valid_file_parses() {
+        let contents = template_file(
+            &[
+                template("mook", "(\"punch\", 0.5), (\"block\", 0.5)"),
+                template("archer", "(\"shoot\", 1.0)"),
+            ],
+            &[spawn_table(
+                "(base: 0, step: 2)",
+                "(base: 2, step: 1)",
+                "(\"mook\", 0.75), (\"archer\", 0.25)",
+            )],
+        );
+
+        match parse_templates(&contents) {
+            Ok(list) => {
+                assert_eq!(list.templates.len(), 2);
+                assert_eq!(list.spawn_tables[0].min.at(4), 2);
+                assert_eq!(list.spawn_tables[0].max.at(4), 6);
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn missing_fields_are_rejected() {
+        let contents = template_file(&["(health: 5)".to_string()], &[]);
+        let message = rejection(&contents);
+
+        // ron points at the template, and names the field it wanted
+        assert!(
+            message.starts_with("resources/spawns.ron:2:"),
+            "{}",
+            message
+        );
+        assert!(message.contains("name"), "{}", message);
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        let contents = template_file(&[template("mook", "(\"kick\", 1.0)")], &[]);
+        assert_eq!(
+            rejection(&contents),
+            "resources/spawns.ron: \"mook\" uses the undefined card \"kick\""
+        );
+
+        let contents = template_file(
+            &[template("mook", "(\"punch\", 1.0)")],
+            &[spawn_table("(base: 0)", "(base: 2)", "(\"ogre\", 1.0)")],
+        );
+        assert_eq!(
+            rejection(&contents),
+            "resources/spawns.ron: \"spawn table 1\" spawns the undefined template \"ogre\""
+        );
+    }
+
+    #[test]
+    fn spawn_counts_that_cross_are_rejected() {
+        let message = "resources/spawns.ron: \"spawn table 1\" needs a min of at least 0 that \
+                       never goes over its max";
+
+        for (min, max) in [
+            ("(base: 3)", "(base: 2)"),
+            ("(base: -1)", "(base: 2)"),
+            // fine at first, but the min catches up as quality goes up
+            ("(base: 0, step: 1)", "(base: 2)"),
+            ("(base: 0, step: 1)", "(base: 2, step: 2)"),
+        ]
+        .iter()
+        {
+            let contents = template_file(
+                &[template("mook", "(\"punch\", 1.0)")],
+                &[spawn_table(min, max, "(\"mook\", 1.0)")],
+            );
+            assert_eq!(rejection(&contents), message, "min {} max {}", min, max);
+        }
+    }
+
+    #[test]
+    fn spawn_chances_have_to_add_up() {
+        let table = |spawns| {
+            template_file(
+                &[template("mook", "(\"punch\", 1.0)")],
+                &[spawn_table("(base: 0)", "(base: 2)", spawns)],
+            )
+        };
+
+        assert_eq!(
+            rejection(&table("(\"mook\", 0.5)")),
+            "resources/spawns.ron: \"spawn table 1\" has spawn chances that add up to 0.5 \
+             instead of 1"
+        );
+        assert_eq!(
+            rejection(&table("")),
+            "resources/spawns.ron: \"spawn table 1\" doesn't spawn anything"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use counterpuncher::autoplay::{self, GreedyPolicy, Policy, RandomPolicy, RunLimits};
+use counterpuncher::observation::Observation;
+use counterpuncher::replay::Playback;
+use counterpuncher::*;
+
+const SEEDS: u64 = 6;
+
+const LIMITS: RunLimits = RunLimits {
+    max_depth: 4,
+    max_turns: 200,
+};
+
+// everything the player can see, which is as much as a run can be compared on from the outside
+fn observe(game: &Game) -> String {
+    serde_json::to_string(&Observation::new(game, 0)).expect("Failed to serialize observation")
+}
+
+// play a run with one of the autoplayers, half of the seeds wander and the other half fight
+fn play_run(seed: u64) -> Game {
+    let mut policy: Box<dyn Policy> = if seed % 2 == 0 {
+        Box::new(RandomPolicy::new(seed))
+    } else {
+        Box::new(GreedyPolicy)
+    };
+
+    let mut game = Game::new(seed, ruleset::default_ruleset());
+    autoplay::play(&mut game, policy.as_mut(), LIMITS);
+    game
+}
+
+#[test]
+fn same_seed_and_commands_play_out_the_same() {
+    for seed in 0..SEEDS {
+        let played = play_run(seed);
+        let commands = &played.recording().commands;
+        assert!(!commands.is_empty(), "seed {} didn't record anything", seed);
+
+        let mut game = Game::new(seed, ruleset::default_ruleset());
+        game.advance();
+        for recorded in commands.iter() {
+            game.step(recorded.command);
+        }
+
+        assert!(
+            observe(&game) == observe(&played),
+            "seed {} ended up somewhere else when given the same commands",
+            seed
+        );
+    }
+}
+
+#[test]
+fn replays_land_on_the_same_turn_either_way() {
+    for seed in 0..SEEDS {
+        let played = play_run(seed);
+        let mut playback = match Playback::new(played.recording().clone()) {
+            Ok(playback) => playback,
+            Err(err) => panic!("seed {} can't be played back: {}", seed, err),
+        };
+        let mut game = playback.start();
+
+        // the whole replay ends up where the run did
+        playback.jump_to_turn(&mut game, u32::MAX);
+        assert!(
+            observe(&game) == observe(&played),
+            "seed {} replayed to a different ending",
+            seed
+        );
+
+        // jumping back restarts from the seed, which should land exactly where playing forward
+        // from the start does
+        let middle = played.turn() / 2;
+        let mut forward = playback.start();
+        playback.jump_to_turn(&mut forward, middle);
+
+        playback.jump_to_turn(&mut game, middle);
+        assert_eq!(
+            game.turn(),
+            middle,
+            "seed {} jumped to the wrong turn",
+            seed
+        );
+        assert!(
+            observe(&game) == observe(&forward),
+            "seed {} jumped back to a different turn {}",
+            seed,
+            middle
+        );
+    }
+}
+
+#[test]
+fn replays_of_other_data_are_refused() {
+    let mut recording = play_run(0).recording().clone();
+    recording.data_hash = data_hash().wrapping_add(1);
+
+    match Playback::new(recording) {
+        Err(replay::ReplayError::DataMismatch) => {}
+        Err(err) => panic!("refused for the wrong reason: {}", err),
+        Ok(_) => panic!("played back a replay recorded with other data"),
+    }
+}
@@ -0,0 +1,120 @@
+use counterpuncher::map_builders::{self, MapBuilder};
+use counterpuncher::*;
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+// every generator gets run on this many seeds at each depth
+const SEEDS_PER_BUILDER: u64 = 400;
+// terrain changes with depth, chasms only open up from depth 3
+const BUILDER_DEPTHS: [i32; 3] = [1, 3, 8];
+
+// whole runs, going down through the levels with everything spawned in
+const LEVEL_SEEDS: u64 = 100;
+const DEEPEST_LEVEL: i32 = 12;
+
+fn check_builder(builder: &dyn MapBuilder) {
+    for depth in BUILDER_DEPTHS.iter() {
+        for seed in 0..SEEDS_PER_BUILDER {
+            let mut rng = RandomNumberGenerator::seeded(seed);
+            let map = builder.build(map::MAP_W, map::MAP_H, *depth, &mut rng);
+
+            if let Err(err) = map_builders::validate_map(&map) {
+                panic!(
+                    "{} map at depth {} on seed {} is broken: {}",
+                    builder.name(),
+                    depth,
+                    seed,
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn rogue_maps_are_valid() {
+    check_builder(&map_builders::RogueBuilder);
+}
+
+#[test]
+fn bsp_maps_are_valid() {
+    check_builder(&map_builders::BspBuilder);
+}
+
+#[test]
+fn cellular_maps_are_valid() {
+    check_builder(&map_builders::CellularBuilder);
+}
+
+#[test]
+fn drunkard_maps_are_valid() {
+    check_builder(&map_builders::DrunkardBuilder);
+}
+
+#[test]
+fn maze_maps_are_valid() {
+    check_builder(&map_builders::MazeBuilder);
+}
+
+#[test]
+fn fallback_maps_are_valid() {
+    for depth in BUILDER_DEPTHS.iter() {
+        let map = map::build_fallback_level(map::MAP_W, map::MAP_H, *depth);
+
+        if let Err(err) = map_builders::validate_map(&map) {
+            panic!("fallback map at depth {} is broken: {}", depth, err);
+        }
+    }
+}
+
+#[test]
+fn every_builder_is_tested() {
+    let names: Vec<&str> = map_builders::all_builders()
+        .iter()
+        .map(|builder| builder.name())
+        .collect();
+
+    assert_eq!(names, ["rogue", "bsp", "cellular", "drunkard", "maze"]);
+}
+
+#[test]
+fn levels_are_valid_at_every_depth() {
+    for seed in 0..LEVEL_SEEDS {
+        let mut game = Game::new(seed, ruleset::default_ruleset());
+
+        for _ in 1..DEEPEST_LEVEL {
+            check_level(&game, seed);
+            game.change_level();
+        }
+    }
+}
+
+fn check_level(game: &Game, seed: u64) {
+    let map = game.ecs.fetch::<Map>();
+    let player = *game.ecs.fetch::<Entity>();
+    let entities = game.ecs.entities();
+    let positions = game.ecs.read_storage::<Position>();
+
+    if let Err(err) = map_builders::validate_map(&map) {
+        panic!("depth {} on seed {} is broken: {}", map.depth, seed, err);
+    }
+
+    let spawns: Vec<Point> = (&entities, &positions)
+        .join()
+        .filter(|(ent, _)| *ent != player)
+        .map(|(_, pos)| pos.as_point())
+        .collect();
+    if let Err(err) = map_builders::validate_spawns(&map, &spawns) {
+        panic!("depth {} on seed {} is broken: {}", map.depth, seed, err);
+    }
+
+    let player_pos = positions
+        .get(player)
+        .expect("player didn't have a position");
+    assert!(
+        player_pos.as_point() == map.rooms[0].center(),
+        "depth {} on seed {} didn't start the player in the first room",
+        map.depth,
+        seed
+    );
+}
@@ -0,0 +1,146 @@
+use counterpuncher::autoplay::{GreedyPolicy, Policy};
+use counterpuncher::observation::Observation;
+use counterpuncher::*;
+use specs::prelude::*;
+
+const SEEDS: u64 = 20;
+const MAX_COMMANDS: u32 = 2000;
+
+fn observe(game: &Game) -> String {
+    serde_json::to_string(&Observation::new(game, 0)).expect("Failed to serialize observation")
+}
+
+fn save_path(seed: u64) -> String {
+    let path = std::env::temp_dir().join(format!("counterpuncher-test-save-{}.json", seed));
+    path.to_str()
+        .expect("Temp dir isn't valid utf-8")
+        .to_string()
+}
+
+// play until an enemy attack is waiting on the player to react
+fn play_to_reaction(seed: u64) -> Option<(Game, Option<Entity>)> {
+    let mut game = Game::new(seed, ruleset::default_ruleset());
+    let mut policy = GreedyPolicy;
+
+    for _ in 0..MAX_COMMANDS {
+        let command = match game.advance() {
+            RunState::AwaitingInput => match game.reaction_status() {
+                (true, attacker) => return Some((game, attacker)),
+                (false, _) => policy.take_turn(&game),
+            },
+            RunState::Targetting { attack_type, .. } => policy.aim(&game, attack_type),
+            RunState::ChooseReward { choices } => policy.choose_reward(&game, &choices),
+            _ => return None,
+        };
+
+        game.apply(command);
+    }
+
+    None
+}
+
+#[test]
+fn saves_made_mid_exchange_resume_the_same_way() {
+    let mut checked = 0;
+
+    for seed in 0..SEEDS {
+        let (mut game, attacker) = match play_to_reaction(seed) {
+            Some(found) => found,
+            None => continue,
+        };
+
+        let path = save_path(seed);
+        if let Err(err) = game.save(&path) {
+            panic!("seed {} failed to save: {}", seed, err);
+        }
+        let mut loaded = match Game::load(&path) {
+            Ok(loaded) => loaded,
+            Err(err) => panic!("seed {} failed to load: {}", seed, err),
+        };
+        saveload::delete_save(&path);
+
+        assert!(
+            observe(&loaded) == observe(&game),
+            "seed {} loaded into a different state",
+            seed
+        );
+
+        // both games get the same answer to the attack, and should play it out the same way
+        let reaction = GreedyPolicy.react(&game, attacker);
+        game.step(reaction);
+        loaded.step(reaction);
+
+        assert!(
+            observe(&loaded) == observe(&game),
+            "seed {} settled the exchange differently after loading",
+            seed
+        );
+        checked += 1;
+    }
+
+    assert!(checked > 0, "no seed got far enough to react to anything");
+}
+
+// save the game, break the save file, and try to load it back
+fn load_broken(game: &Game, path: &str, corrupt: impl FnOnce(&mut serde_json::Value)) -> String {
+    if let Err(err) = game.save(path) {
+        panic!("failed to save: {}", err);
+    }
+
+    let contents = std::fs::read_to_string(path).expect("Failed to read save");
+    let mut data: serde_json::Value = serde_json::from_str(&contents).expect("Save isn't json");
+    corrupt(&mut data);
+    std::fs::write(path, data.to_string()).expect("Failed to write save");
+
+    let loaded = Game::load(path);
+    saveload::delete_save(path);
+
+    match loaded {
+        Ok(_) => panic!("loaded a broken save"),
+        Err(err) => err.to_string(),
+    }
+}
+
+#[test]
+fn saves_pointing_at_missing_entities_fail_to_load() {
+    let game = Game::new(0, ruleset::default_ruleset());
+    let path = save_path(SEEDS);
+
+    // point the player at an entity that was never saved
+    let err = load_broken(&game, &path, |data| {
+        data["player"] = serde_json::json!(100_000);
+    });
+    assert_eq!(err, "save file refers to entity 100000, which isn't in it");
+}
+
+#[test]
+fn saved_exchanges_with_bad_indexes_fail_to_load() {
+    let (game, _) = (0..SEEDS)
+        .find_map(play_to_reaction)
+        .expect("no seed got far enough to react to anything");
+    let path = save_path(SEEDS + 1);
+
+    // the card that started the exchange came from nobody in the save
+    let err = load_broken(&game, &path, |data| {
+        data["events"]["processing"]["links"][0]["event"]["source"] = serde_json::json!(100_000);
+    });
+    assert_eq!(err, "save file refers to entity 100000, which isn't in it");
+
+    // the player is answering a card that isn't in the exchange
+    let err = load_broken(&game, &path, |data| {
+        data["events"]["processing"]["reacting_to"] = serde_json::json!(7);
+    });
+    assert_eq!(
+        err,
+        "save file has an exchange answering card 7, which isn't in it"
+    );
+
+    // a card answering itself would never settle
+    let err = load_broken(&game, &path, |data| {
+        data["events"]["processing"]["links"][0]["reacting_to"] = serde_json::json!(0);
+    });
+    assert_eq!(
+        err,
+        "save file has an exchange answering card 0, which isn't in it"
+    );
+}